@@ -0,0 +1,139 @@
+use serde::{Deserialize, Serialize};
+
+/// 网卡及其原有 DNS 设置，name_server 为空表示自动获取（DHCP）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AdapterDns {
+    pub guid: String,
+    pub alias: String,
+    pub name_server: String,
+}
+
+/// 按名称查找网卡，返回 (GUID, 名称)
+pub fn find_adapter(alias: &str) -> Option<(String, String)> {
+    list_adapters(false).into_iter()
+        .find(|(_, _, name)| name.eq_ignore_ascii_case(alias))
+        .map(|(_, guid, name)| (guid, name))
+}
+
+/// 按接口索引查找网卡，返回 (GUID, 名称)
+pub fn adapter_by_index(index: u32) -> Option<(String, String)> {
+    list_adapters(false).into_iter()
+        .find(|(i, _, _)| *i == index)
+        .map(|(_, guid, name)| (guid, name))
+}
+
+/// 已连接的以太网 / Wi-Fi 网卡
+pub fn physical_adapters() -> Vec<(String, String)> {
+    list_adapters(true).into_iter().map(|(_, guid, name)| (guid, name)).collect()
+}
+
+/// (接口索引, GUID, 名称)
+fn list_adapters(physical_only: bool) -> Vec<(u32, String, String)> {
+    #[cfg(windows)]
+    return win::list_adapters(physical_only);
+    #[cfg(not(windows))]
+    {
+        let _ = physical_only;
+        Vec::new()
+    }
+}
+
+/// 读取网卡当前的 DNS 服务器（逗号分隔）
+pub fn get_adapter_dns(guid: &str) -> Result<String, String> {
+    #[cfg(windows)]
+    return win::get_name_server(guid);
+    #[cfg(not(windows))]
+    {
+        let _ = guid;
+        Ok(String::new())
+    }
+}
+
+/// 设置网卡 DNS 服务器，传入空字符串恢复为自动获取
+pub fn set_adapter_dns(guid: &str, name_server: &str) -> Result<(), String> {
+    #[cfg(windows)]
+    return win::set_name_server(guid, name_server);
+    #[cfg(not(windows))]
+    {
+        let _ = (guid, name_server);
+        Ok(())
+    }
+}
+
+#[cfg(windows)]
+mod win {
+    use windows::core::{GUID, PWSTR};
+    use windows::Win32::NetworkManagement::IpHelper::{
+        FreeInterfaceDnsSettings, FreeMibTable, GetIfTable2, GetInterfaceDnsSettings, SetInterfaceDnsSettings,
+        DNS_INTERFACE_SETTINGS, DNS_INTERFACE_SETTINGS_VERSION1, DNS_SETTING_NAMESERVER, IF_TYPE_ETHERNET_CSMACD,
+        IF_TYPE_IEEE80211, MIB_IF_TABLE2,
+    };
+    use windows::Win32::NetworkManagement::Ndis::IfOperStatusUp;
+
+    fn parse_guid(guid: &str) -> Result<GUID, String> {
+        GUID::try_from(guid).map_err(|_| format!("Invalid adapter GUID: {}", guid))
+    }
+
+    pub fn list_adapters(physical_only: bool) -> Vec<(u32, String, String)> {
+        let mut table: *mut MIB_IF_TABLE2 = std::ptr::null_mut();
+        let mut adapters = Vec::new();
+        unsafe {
+            if GetIfTable2(&mut table).is_err() || table.is_null() {
+                return adapters;
+            }
+            let rows = std::slice::from_raw_parts((*table).Table.as_ptr(), (*table).NumEntries as usize);
+            for row in rows {
+                // 标志位：bit0 硬件网卡，bit1 过滤驱动；同一块网卡会以过滤驱动的形式重复出现
+                let flags = row.InterfaceAndOperStatusFlags._bitfield;
+                if flags & 0b10 != 0 {
+                    continue;
+                }
+                if physical_only
+                    && (flags & 0b1 == 0
+                        || row.OperStatus != IfOperStatusUp
+                        || ![IF_TYPE_ETHERNET_CSMACD, IF_TYPE_IEEE80211].contains(&row.Type))
+                {
+                    continue;
+                }
+                let len = row.Alias.iter().position(|&c| c == 0).unwrap_or(row.Alias.len());
+                adapters.push((row.InterfaceIndex, format!("{:?}", row.InterfaceGuid), String::from_utf16_lossy(&row.Alias[..len])));
+            }
+            FreeMibTable(table as *const core::ffi::c_void);
+        }
+        adapters
+    }
+
+    pub fn get_name_server(guid: &str) -> Result<String, String> {
+        let guid = parse_guid(guid)?;
+        let mut settings = DNS_INTERFACE_SETTINGS { Version: DNS_INTERFACE_SETTINGS_VERSION1, ..Default::default() };
+        unsafe {
+            GetInterfaceDnsSettings(guid, &mut settings)
+                .ok()
+                .map_err(|e| format!("Failed to read adapter DNS: {}", e))?;
+            let name_server = if settings.NameServer.is_null() {
+                String::new()
+            } else {
+                settings.NameServer.to_string().unwrap_or_default()
+            };
+            FreeInterfaceDnsSettings(&mut settings);
+            Ok(name_server)
+        }
+    }
+
+    pub fn set_name_server(guid: &str, name_server: &str) -> Result<(), String> {
+        let guid = parse_guid(guid)?;
+        let mut buf: Vec<u16> = name_server.encode_utf16().chain(std::iter::once(0)).collect();
+        let settings = DNS_INTERFACE_SETTINGS {
+            Version: DNS_INTERFACE_SETTINGS_VERSION1,
+            Flags: DNS_SETTING_NAMESERVER as u64,
+            NameServer: PWSTR(buf.as_mut_ptr()),
+            ..Default::default()
+        };
+        unsafe {
+            SetInterfaceDnsSettings(guid, &settings)
+                .ok()
+                .map_err(|e| format!("Failed to set adapter DNS: {}", e))
+        }
+    }
+}
@@ -0,0 +1,127 @@
+use std::path::Path;
+use tauri::{AppHandle, Manager};
+use crate::state::AppState;
+use crate::types::{ProxyState, SessionState};
+
+/// 命令行控制参数。首次启动时直接执行；程序已在运行时由 single-instance 转发给已运行的实例执行
+#[derive(Debug, Default)]
+pub struct CliArgs {
+    pub connect: bool,
+    pub disconnect: bool,
+    pub switch_node: Option<String>,
+    pub profile: Option<String>,
+    pub status: bool,
+}
+
+impl CliArgs {
+    /// 是否有需要在运行中的实例里执行的操作（--status 在启动界面前单独处理）
+    pub fn has_actions(&self) -> bool {
+        self.connect || self.disconnect || self.switch_node.is_some() || self.profile.is_some()
+    }
+}
+
+/// 支持 --flag value 和 --flag=value 两种写法，无法识别的参数（如导入链接）忽略
+pub fn parse(args: &[String]) -> CliArgs {
+    let mut parsed = CliArgs::default();
+    let mut iter = args.iter().skip(1);
+    while let Some(arg) = iter.next() {
+        let (flag, inline) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag, Some(value.to_string())),
+            _ => (arg.as_str(), None),
+        };
+        match flag {
+            "--connect" => parsed.connect = true,
+            "--disconnect" => parsed.disconnect = true,
+            "--status" => parsed.status = true,
+            "--switch-node" => parsed.switch_node = inline.or_else(|| iter.next().cloned()),
+            "--profile" => parsed.profile = inline.or_else(|| iter.next().cloned()),
+            _ => {}
+        }
+    }
+    parsed
+}
+
+/// 发布版是 GUI 程序，没有控制台，输出前先附加到启动它的命令行窗口
+#[cfg(windows)]
+fn attach_parent_console() {
+    use windows::Win32::System::Console::{AttachConsole, ATTACH_PARENT_PROCESS};
+    let _ = unsafe { AttachConsole(ATTACH_PARENT_PROCESS) };
+}
+
+#[cfg(not(windows))]
+fn attach_parent_console() {}
+
+async fn clash_get(path: &str) -> Option<serde_json::Value> {
+    reqwest::Client::new()
+        .get(format!("http://127.0.0.1:9090{}", path))
+        .timeout(std::time::Duration::from_secs(2))
+        .send()
+        .await
+        .ok()?
+        .json()
+        .await
+        .ok()
+}
+
+/// --status：不启动界面，读取上次会话并查询运行中内核的 Clash API，以 JSON 输出到标准输出。
+/// 返回进程退出码：已连接为 0，未连接为 1，方便脚本判断
+pub fn print_status(data_dir: &Path) -> i32 {
+    attach_parent_console();
+
+    let workspace = crate::commands::workspace::load_active_workspace(data_dir);
+    let session_file = crate::commands::workspace::workspace_path(data_dir, workspace.as_deref()).join("session.json");
+    let session: Option<SessionState> = std::fs::read_to_string(session_file).ok()
+        .and_then(|content| serde_json::from_str(&content).ok());
+
+    let (selector, configs) = match tokio::runtime::Runtime::new() {
+        Ok(runtime) => runtime.block_on(async { tokio::join!(clash_get("/proxies/PROXY"), clash_get("/configs")) }),
+        Err(_) => (None, None),
+    };
+    let connected = configs.is_some();
+    let status = serde_json::json!({
+        "connected": connected,
+        "workspace": workspace.as_deref().unwrap_or("default"),
+        "profileId": session.as_ref().and_then(|s| s.profile_id.clone()),
+        "node": selector.as_ref().and_then(|s| s["now"].as_str().map(str::to_string))
+            .or_else(|| session.as_ref().and_then(|s| s.node_tag.clone())),
+        "mode": configs.as_ref().and_then(|c| c["mode"].as_str().map(str::to_string)),
+    });
+    println!("{}", serde_json::to_string_pretty(&status).unwrap_or_default());
+    if connected { 0 } else { 1 }
+}
+
+async fn run_actions(app: &AppHandle, args: &CliArgs) -> Result<(), String> {
+    let state = app.state::<AppState>();
+    if let Some(name) = &args.profile {
+        let data = crate::commands::profiles::load_profiles_data(&state);
+        let profile = data.profiles.iter()
+            .find(|p| p.id == *name || p.name.eq_ignore_ascii_case(name))
+            .ok_or_else(|| format!("Profile not found: {}", name))?;
+        crate::tray::select_profile(app, profile.id.clone()).await?;
+    }
+    if let Some(tag) = &args.switch_node {
+        crate::tray::select_node(app, tag.clone()).await?;
+    }
+
+    let proxy_state = state.proxy_state.lock().await.clone();
+    let result = if args.disconnect {
+        if matches!(proxy_state, ProxyState::Idle) { return Ok(()) }
+        crate::commands::singbox_stop(app.clone(), state).await?
+    } else if args.connect {
+        if !matches!(proxy_state, ProxyState::Idle | ProxyState::Error) { return Ok(()) }
+        crate::commands::singbox_start(app.clone(), state).await?
+    } else {
+        return Ok(());
+    };
+    if result.success { Ok(()) } else { Err(result.error.unwrap_or_default()) }
+}
+
+/// 在后台执行命令行操作，失败只记录日志
+pub(crate) fn dispatch(app: &AppHandle, args: CliArgs) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = run_actions(&app, &args).await {
+            log::warn!("Command line action failed: {}", e);
+        }
+    });
+}
@@ -0,0 +1,178 @@
+use tauri::{AppHandle, State};
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use crate::state::AppState;
+use crate::types::ProxyState;
+
+/// 备份包内的说明文件，恢复时用来确认是 KunBox 的备份
+const BACKUP_MANIFEST: &str = "kunbox-backup.json";
+pub(crate) const BACKUP_FILES: [&str; 4] = ["settings.json", "profiles.json", "rulesets.json", "custom_rules.json"];
+pub(crate) const BACKUP_DIRS: [&str; 2] = ["configs", "rulesets"];
+/// 单个条目解压后的上限，防止压缩炸弹
+const BACKUP_MAX_ENTRY_SIZE: u64 = 64 * 1024 * 1024;
+
+/// 备份包内允许出现的路径：根目录下的数据文件，或 configs / rulesets 下的一层文件
+fn is_backup_entry(path: &Path) -> bool {
+    let parts: Vec<&str> = path.iter().filter_map(|p| p.to_str()).collect();
+    match parts.as_slice() {
+        [name] => BACKUP_FILES.contains(name),
+        [dir, _] => BACKUP_DIRS.contains(dir),
+        _ => false,
+    }
+}
+
+/// 打包配置、节点、规则集（含缓存）和设置，用于重装系统后恢复
+#[tauri::command]
+pub async fn backup_create(state: State<'_, AppState>, path: String) -> Result<serde_json::Value, String> {
+    let target = PathBuf::from(path.trim());
+    let file = fs::File::create(&target).map_err(|e| format!("Failed to create backup file: {}", e))?;
+    let mut zip = zip::ZipWriter::new(file);
+    let options = zip::write::SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+
+    let manifest = serde_json::json!({
+        "app": "KunBox",
+        "version": env!("CARGO_PKG_VERSION"),
+        "createdAt": chrono::Utc::now().to_rfc3339(),
+    });
+    zip.start_file(BACKUP_MANIFEST, options).map_err(|e| e.to_string())?;
+    zip.write_all(manifest.to_string().as_bytes()).map_err(|e| e.to_string())?;
+
+    let mut entries = Vec::new();
+    for name in BACKUP_FILES {
+        let file = state.workspace_dir().join(name);
+        if file.is_file() {
+            entries.push((name.to_string(), file));
+        }
+    }
+    for dir in BACKUP_DIRS {
+        let Ok(read_dir) = fs::read_dir(state.workspace_dir().join(dir)) else { continue };
+        for entry in read_dir.flatten() {
+            let file = entry.path();
+            // 跳过下载或解压中途留下的临时文件
+            if !file.is_file() || file.extension().is_some_and(|e| e == "tmp") {
+                continue;
+            }
+            if let Some(name) = file.file_name().and_then(|n| n.to_str()) {
+                entries.push((format!("{}/{}", dir, name), file));
+            }
+        }
+    }
+
+    for (name, file) in &entries {
+        // DPAPI 密钥与当前 Windows 用户绑定，重装系统后无法解密，备份中一律保存明文
+        let content = if crate::dpapi::is_encrypted_file(file) {
+            crate::dpapi::read_data_file(file).map(String::into_bytes)
+        } else {
+            fs::read(file).map_err(|e| e.to_string())
+        }
+        .map_err(|e| format!("Failed to read {}: {}", name, e))?;
+        zip.start_file(name.as_str(), options).map_err(|e| e.to_string())?;
+        zip.write_all(&content).map_err(|e| e.to_string())?;
+    }
+    zip.finish().map_err(|e| e.to_string())?;
+
+    Ok(serde_json::json!({
+        "success": true,
+        "path": target,
+        "files": entries.len(),
+    }))
+}
+
+/// 读取并校验备份包，返回 (相对路径, 内容)
+fn read_backup(path: &Path) -> Result<Vec<(PathBuf, Vec<u8>)>, String> {
+    let file = fs::File::open(path).map_err(|e| format!("Failed to open backup: {}", e))?;
+    let mut archive = zip::ZipArchive::new(file).map_err(|e| format!("Invalid backup file: {}", e))?;
+
+    let manifest: serde_json::Value = {
+        let mut entry = archive.by_name(BACKUP_MANIFEST).map_err(|_| "Not a KunBox backup".to_string())?;
+        let mut content = String::new();
+        entry.read_to_string(&mut content).map_err(|e| e.to_string())?;
+        serde_json::from_str(&content).map_err(|_| "Not a KunBox backup".to_string())?
+    };
+    if manifest["app"] != "KunBox" {
+        return Err("Not a KunBox backup".to_string());
+    }
+
+    let mut entries = Vec::new();
+    for i in 0..archive.len() {
+        let entry = archive.by_index(i).map_err(|e| e.to_string())?;
+        if !entry.is_file() || entry.name() == BACKUP_MANIFEST {
+            continue;
+        }
+        // enclosed_name 拒绝 .. 和绝对路径（zip-slip）
+        let Some(name) = entry.enclosed_name().filter(|p| is_backup_entry(p)) else {
+            log::warn!("Skipping unexpected backup entry: {}", entry.name());
+            continue;
+        };
+        if entry.size() > BACKUP_MAX_ENTRY_SIZE {
+            return Err(format!("Backup entry is too large: {}", name.display()));
+        }
+        let mut content = Vec::new();
+        entry.take(BACKUP_MAX_ENTRY_SIZE + 1).read_to_end(&mut content).map_err(|e| e.to_string())?;
+        if content.len() as u64 > BACKUP_MAX_ENTRY_SIZE {
+            return Err(format!("Backup entry is too large: {}", name.display()));
+        }
+        // 数据文件必须是合法 JSON，避免恢复后全部回落为默认值
+        if name.extension().is_some_and(|e| e == "json") && name.parent() != Some(Path::new("rulesets")) {
+            serde_json::from_slice::<serde_json::Value>(&content)
+                .map_err(|e| format!("Corrupted file in backup {}: {}", name.display(), e))?;
+        }
+        entries.push((name, content));
+    }
+    if !entries.iter().any(|(name, _)| name == Path::new("settings.json") || name == Path::new("profiles.json")) {
+        return Err("Backup contains no settings or profiles".to_string());
+    }
+    Ok(entries)
+}
+
+/// 从备份包恢复全部数据，恢复前会先停止内核，完成后重新加载内存中的状态
+#[tauri::command]
+pub async fn backup_restore(app: AppHandle, state: State<'_, AppState>, path: String) -> Result<serde_json::Value, String> {
+    let entries = read_backup(Path::new(path.trim()))?;
+
+    if matches!(*state.proxy_state.lock().await, ProxyState::Connected) {
+        crate::commands::singbox_stop(app.clone(), state.clone()).await?;
+    }
+
+    // 先整体写入临时目录，成功后再替换，避免中途失败留下一半旧一半新的数据
+    let staging = state.workspace_dir().join("restore.tmp");
+    let _ = fs::remove_dir_all(&staging);
+    let written = (|| -> Result<(), String> {
+        for (name, content) in &entries {
+            let file = staging.join(name);
+            if let Some(parent) = file.parent() {
+                fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+            }
+            fs::write(&file, content).map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    })();
+    if let Err(e) = written {
+        let _ = fs::remove_dir_all(&staging);
+        return Err(format!("Failed to restore backup: {}", e));
+    }
+
+    for dir in BACKUP_DIRS {
+        let restored = staging.join(dir);
+        if restored.is_dir() {
+            let current = state.workspace_dir().join(dir);
+            let _ = fs::remove_dir_all(&current);
+            fs::rename(&restored, &current).map_err(|e| format!("Failed to restore {}: {}", dir, e))?;
+        }
+    }
+    for name in BACKUP_FILES {
+        let restored = staging.join(name);
+        if restored.is_file() {
+            fs::rename(&restored, state.workspace_dir().join(name)).map_err(|e| format!("Failed to restore {}: {}", name, e))?;
+        }
+    }
+    let _ = fs::remove_dir_all(&staging);
+
+    crate::commands::workspace::reload_workspace_data(&app, &state).await?;
+
+    Ok(serde_json::json!({
+        "success": true,
+        "files": entries.len(),
+    }))
+}
@@ -0,0 +1,164 @@
+use tauri::{AppHandle, Emitter, Manager, State};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::state::AppState;
+use crate::types::Profile;
+
+/// 作为启动参数传入时按订阅内容导入的文件类型
+const PROFILE_FILE_EXTENSIONS: [&str; 5] = ["yaml", "yml", "json", "txt", "conf"];
+/// 导入文件的大小上限，避免误传大文件时整个读入内存
+const PROFILE_FILE_MAX_SIZE: u64 = 10 * 1024 * 1024;
+
+/// 机场网页“一键导入”使用的 (协议, 导入动作)
+const DEEP_LINK_SCHEMES: [(&str, &str); 2] = [("clash", "install-config"), ("sing-box", "import-remote-profile")];
+
+/// 等待用户确认的导入，启动时收到的链接要等前端加载后再读取
+static PENDING_IMPORT: once_cell::sync::Lazy<std::sync::Mutex<Option<DeepLinkImport>>> =
+    once_cell::sync::Lazy::new(|| std::sync::Mutex::new(None));
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DeepLinkImport {
+    pub url: String,
+    pub name: Option<String>,
+}
+
+/// 解析 clash://install-config?url=...&name=... 和 sing-box://import-remote-profile?url=...#name
+fn parse_deep_link(link: &str) -> Option<DeepLinkImport> {
+    let parsed = url::Url::parse(link.trim()).ok()?;
+    let (_, action) = DEEP_LINK_SCHEMES.iter().find(|(scheme, _)| *scheme == parsed.scheme())?;
+    if parsed.host_str() != Some(*action) {
+        return None;
+    }
+
+    let query = |key: &str| parsed.query_pairs().find(|(k, _)| k == key).map(|(_, v)| v.trim().to_string());
+    let url = query("url")?;
+    if !url.starts_with("http://") && !url.starts_with("https://") {
+        return None;
+    }
+    let name = query("name")
+        .or_else(|| parsed.fragment().and_then(|f| urlencoding::decode(f).ok()).map(|f| f.trim().to_string()))
+        .filter(|n| !n.is_empty());
+    Some(DeepLinkImport { url, name })
+}
+
+/// 参数中的导入链接记为待确认并通知前端
+fn handle_deep_link_args(app: &AppHandle, args: &[String]) {
+    let Some(import) = args.iter().find_map(|arg| parse_deep_link(arg)) else { return };
+    log::info!("Received profile import link for {}", crate::redact::redact(&import.url));
+    *PENDING_IMPORT.lock().unwrap_or_else(|e| e.into_inner()) = Some(import.clone());
+
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+    let _ = app.emit("deeplink:import", &import);
+}
+
+/// 启动参数中的配置文件路径，相对路径按启动时的工作目录解析
+fn profile_file_args(args: &[String], cwd: &Path) -> Vec<PathBuf> {
+    args.iter().skip(1)
+        .filter(|arg| !arg.starts_with("--"))
+        .map(|arg| cwd.join(arg))
+        .filter(|path| {
+            path.extension().and_then(|e| e.to_str())
+                .is_some_and(|ext| PROFILE_FILE_EXTENSIONS.iter().any(|e| e.eq_ignore_ascii_case(ext)))
+        })
+        .filter(|path| path.metadata().is_ok_and(|m| m.is_file()))
+        .collect()
+}
+
+async fn import_profile_file(app: &AppHandle, path: &Path) -> Result<Profile, String> {
+    let size = fs::metadata(path).map_err(|e| e.to_string())?.len();
+    if size > PROFILE_FILE_MAX_SIZE {
+        return Err(format!("File is too large: {} bytes", size));
+    }
+    let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let name = path.file_stem().and_then(|s| s.to_str()).unwrap_or("Imported").to_string();
+    crate::commands::profile_import_content(app.state::<AppState>(), name, content, None, None, None).await
+}
+
+/// 处理启动参数或二次启动转发的参数：导入链接等待确认，配置文件直接导入，结果通过 profile:file-imported 通知前端
+pub(crate) fn handle_launch_args(app: &AppHandle, args: &[String], cwd: &Path) {
+    handle_deep_link_args(app, args);
+
+    let files = profile_file_args(args, cwd);
+    if files.is_empty() {
+        return;
+    }
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        for path in files {
+            let result = import_profile_file(&app, &path).await;
+            match &result {
+                Ok(profile) => log::info!("Imported profile {} from {:?}", profile.name, path),
+                Err(e) => log::warn!("Failed to import profile from {:?}: {}", path, e),
+            }
+            let _ = app.emit("profile:file-imported", serde_json::json!({
+                "path": path,
+                "profile": result.as_ref().ok(),
+                "error": result.err(),
+            }));
+        }
+    });
+}
+
+/// 在当前用户下注册 clash:// 和 sing-box://，每次启动重写以跟随程序位置
+#[cfg(windows)]
+pub(crate) fn register_url_schemes() {
+    use windows::core::{HSTRING, PCWSTR};
+    use windows::Win32::System::Registry::{RegSetKeyValueW, HKEY_CURRENT_USER, REG_SZ};
+
+    let Ok(exe) = std::env::current_exe() else { return };
+    let command = format!("\"{}\" \"%1\"", exe.display());
+    let set = |key: &str, name: Option<&str>, value: &str| {
+        let data: Vec<u16> = value.encode_utf16().chain(std::iter::once(0)).collect();
+        let name = name.map(HSTRING::from);
+        let result = unsafe {
+            RegSetKeyValueW(
+                HKEY_CURRENT_USER,
+                &HSTRING::from(key),
+                name.as_ref().map(|n| PCWSTR(n.as_ptr())).unwrap_or(PCWSTR::null()),
+                REG_SZ.0,
+                Some(data.as_ptr() as *const _),
+                (data.len() * 2) as u32,
+            )
+        };
+        if result.is_err() {
+            log::warn!("Failed to register URL scheme key {}: {:?}", key, result);
+        }
+    };
+    for (scheme, _) in DEEP_LINK_SCHEMES {
+        let root = format!("Software\\Classes\\{}", scheme);
+        set(&root, None, &format!("URL:{} Protocol", scheme));
+        set(&root, Some("URL Protocol"), "");
+        set(&format!("{}\\shell\\open\\command", root), None, &command);
+    }
+}
+
+#[cfg(not(windows))]
+pub(crate) fn register_url_schemes() {}
+
+/// 待确认的导入，前端启动后读取一次以弹出确认框
+#[tauri::command]
+pub async fn deeplink_pending() -> Result<Option<DeepLinkImport>, String> {
+    Ok(PENDING_IMPORT.lock().map_err(|e| e.to_string())?.clone())
+}
+
+/// 用户确认后导入待确认的订阅
+#[tauri::command]
+pub async fn deeplink_accept(state: State<'_, AppState>) -> Result<Profile, String> {
+    let import = PENDING_IMPORT.lock().map_err(|e| e.to_string())?.take()
+        .ok_or("No pending import")?;
+    crate::commands::profile_add(state, import.url, import.name, None, None, None).await
+}
+
+#[tauri::command]
+pub async fn deeplink_reject() -> Result<(), String> {
+    PENDING_IMPORT.lock().map_err(|e| e.to_string())?.take();
+    Ok(())
+}
@@ -0,0 +1,156 @@
+use tauri::{AppHandle, Emitter, Manager, State};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use tokio_util::sync::CancellationToken;
+use crate::commands::profiles::{record_latency_sample, test_latency_via_clash_api, LatencyTestOptions};
+use crate::state::AppState;
+
+/// 检查当前节点的间隔
+const FAILOVER_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+/// 切换时最多试这么多个候选节点，全部失败说明多半是本地网络问题
+const FAILOVER_MAX_CANDIDATES: usize = 10;
+/// 记录最多保留的条数
+const FAILOVER_HISTORY_LIMIT: usize = 50;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FailoverEvent {
+    pub from: String,
+    pub to: String,
+    /// 切换前连续失败的次数
+    pub failures: u32,
+    /// 新节点的延迟（毫秒）
+    pub latency: i64,
+    pub timestamp: u64,
+}
+
+fn load_history(state: &AppState) -> Vec<FailoverEvent> {
+    fs::read_to_string(state.failover_history_file()).ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn append_history(state: &AppState, event: &FailoverEvent) {
+    let mut history = load_history(state);
+    history.push(event.clone());
+    let overflow = history.len().saturating_sub(FAILOVER_HISTORY_LIMIT);
+    history.drain(..overflow);
+    let written = serde_json::to_string_pretty(&history)
+        .map_err(|e| e.to_string())
+        .and_then(|content| fs::write(state.failover_history_file(), content).map_err(|e| e.to_string()));
+    if let Err(e) = written {
+        log::warn!("Failed to record failover: {}", e);
+    }
+}
+
+/// PROXY 选择器当前选中的节点和全部可选节点
+async fn proxy_selector() -> Option<(String, Vec<String>)> {
+    let selector: serde_json::Value = reqwest::Client::new()
+        .get("http://127.0.0.1:9090/proxies/PROXY")
+        .timeout(std::time::Duration::from_secs(2))
+        .send()
+        .await
+        .ok()?
+        .json()
+        .await
+        .ok()?;
+    let now = selector["now"].as_str()?.to_string();
+    let all = selector["all"].as_array()?.iter().filter_map(|n| n.as_str().map(str::to_string)).collect();
+    Some((now, all))
+}
+
+/// 按延迟历史的等效延迟（没有历史时用最近一次延迟）从低到高试候选节点，返回第一个可用的 (节点, 延迟)；没测过的排在最后
+async fn find_healthy_node(state: &AppState, current: &str, all: Vec<String>, options: &LatencyTestOptions) -> Option<(String, i64)> {
+    let known = state.node_latency.lock().await.clone();
+    let scores = crate::commands::profiles::node_scores(state).await;
+    let mut candidates: Vec<String> = all.into_iter()
+        .filter(|tag| tag != current && !matches!(tag.as_str(), "direct" | "block"))
+        .filter(|tag| known.get(tag).map_or(true, |latency| *latency >= 0))
+        .collect();
+    candidates.sort_by_key(|tag| match scores.get(tag) {
+        Some(score) => score.effective_latency(),
+        None => known.get(tag).copied().unwrap_or(i64::MAX),
+    });
+
+    for tag in candidates.into_iter().take(FAILOVER_MAX_CANDIDATES) {
+        let latency = test_latency_via_clash_api(&tag, 9090, options).await.unwrap_or(-1);
+        record_latency_sample(state, &tag, latency).await;
+        if latency >= 0 {
+            return Some((tag, latency));
+        }
+    }
+    None
+}
+
+async fn fail_over(app: &AppHandle, from: &str, all: Vec<String>, failures: u32) -> Result<(), String> {
+    let state = app.state::<AppState>();
+    let options = LatencyTestOptions::from_settings(&state).await;
+    let (to, latency) = find_healthy_node(&state, from, all, &options).await
+        .ok_or("No healthy node to fail over to")?;
+
+    crate::commands::selection::activate_node(app, &to).await?;
+
+    log::warn!("Active node {} failed {} health checks, switched to {} ({} ms)", from, failures, to, latency);
+    let event = FailoverEvent {
+        from: from.to_string(),
+        to,
+        failures,
+        latency,
+        timestamp: chrono::Utc::now().timestamp_millis() as u64,
+    };
+    append_history(&state, &event);
+    let _ = app.emit("node:failover", &event);
+    Ok(())
+}
+
+/// 连接期间定期测试当前节点，连续失败达到 failoverThreshold 次后切换到可用的最快节点
+pub(crate) async fn run_failover_monitor(app: AppHandle, cancel: CancellationToken) {
+    let mut failures: u32 = 0;
+    loop {
+        tokio::select! {
+            _ = cancel.cancelled() => break,
+            _ = tokio::time::sleep(FAILOVER_CHECK_INTERVAL) => {}
+        }
+        let state = app.state::<AppState>();
+        let (enabled, threshold) = {
+            let settings = state.settings.lock().await;
+            (settings.failover_enabled, settings.failover_threshold)
+        };
+        if !enabled {
+            failures = 0;
+            continue;
+        }
+        let Some((current, all)) = proxy_selector().await else { continue };
+
+        let options = LatencyTestOptions::from_settings(&state).await;
+        let latency = test_latency_via_clash_api(&current, 9090, &options).await.unwrap_or(-1);
+        record_latency_sample(&state, &current, latency).await;
+        if latency >= 0 {
+            failures = 0;
+            continue;
+        }
+        failures += 1;
+        log::info!("Health check for {} failed ({}/{})", current, failures, threshold);
+        if failures < threshold {
+            continue;
+        }
+        match fail_over(&app, &current, all, failures).await {
+            Ok(_) => failures = 0,
+            Err(e) => log::warn!("Failover failed: {}", e),
+        }
+    }
+}
+
+/// 自动切换节点的记录，最新的在最后
+#[tauri::command]
+pub async fn failover_history(state: State<'_, AppState>) -> Result<Vec<FailoverEvent>, String> {
+    Ok(load_history(&state))
+}
+
+#[tauri::command]
+pub async fn failover_history_clear(state: State<'_, AppState>) -> Result<(), String> {
+    match fs::remove_file(state.failover_history_file()) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.to_string()),
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,161 @@
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutState};
+use crate::state::AppState;
+use crate::types::{HotkeySettings, ProxyState};
+
+/// (动作, 快捷键) 或 (动作, 错误原因)
+type HotkeyEntries<T> = Vec<(&'static str, T)>;
+
+/// 老板键隐藏的窗口 label，None 表示未处于隐藏状态
+static BOSS_HIDDEN: std::sync::Mutex<Option<Vec<String>>> = std::sync::Mutex::new(None);
+
+/// 解析快捷键配置，返回 (动作, 快捷键)；格式错误或与其他动作重复的以 (动作, 原因) 返回
+pub(crate) fn parse_hotkeys(hotkeys: &HotkeySettings) -> Result<HotkeyEntries<Shortcut>, HotkeyEntries<String>> {
+    let mut parsed: HotkeyEntries<Shortcut> = Vec::new();
+    let mut errors = Vec::new();
+    for (action, accelerator) in hotkeys.entries() {
+        let Some(accelerator) = accelerator.map(str::trim).filter(|a| !a.is_empty()) else { continue };
+        match accelerator.parse::<Shortcut>() {
+            Ok(shortcut) => match parsed.iter().find(|(_, s)| *s == shortcut) {
+                Some((other, _)) => errors.push((action, format!("{} is already used by {}", accelerator, other))),
+                None => parsed.push((action, shortcut)),
+            },
+            Err(e) => errors.push((action, format!("Invalid shortcut {}: {}", accelerator, e))),
+        }
+    }
+    if errors.is_empty() { Ok(parsed) } else { Err(errors) }
+}
+
+/// 按配置重新注册全部全局快捷键，返回注册失败的 (动作, 原因)，通常是被其他程序占用
+pub(crate) fn register_hotkeys(app: &AppHandle, hotkeys: &HotkeySettings) -> HotkeyEntries<String> {
+    let shortcuts = match parse_hotkeys(hotkeys) {
+        Ok(shortcuts) => shortcuts,
+        Err(errors) => return errors,
+    };
+    let manager = app.global_shortcut();
+    if let Err(e) = manager.unregister_all() {
+        log::warn!("Failed to unregister hotkeys: {}", e);
+    }
+
+    let mut failed = Vec::new();
+    for (action, shortcut) in shortcuts {
+        let result = manager.on_shortcut(shortcut, move |app, _shortcut, event| {
+            if event.state() == ShortcutState::Pressed {
+                let app = app.clone();
+                tauri::async_runtime::spawn(async move { run_hotkey_action(&app, action).await });
+            }
+        });
+        if let Err(e) = result {
+            failed.push((action, format!("Shortcut is in use by another application: {}", e)));
+        }
+    }
+    failed
+}
+
+async fn run_hotkey_action(app: &AppHandle, action: &str) {
+    let result = match action {
+        "toggleConnect" => toggle_connect(app).await,
+        "toggleSystemProxy" => toggle_system_proxy(app).await,
+        "toggleWindow" => toggle_window(app),
+        "nextNode" => switch_to_next_node(app).await,
+        "bossKey" => toggle_boss_key(app).await,
+        "bestNode" => crate::commands::selection::select_best_node(app, &Default::default()).await.map(|_| ()),
+        _ => Ok(()),
+    };
+    if let Err(e) = &result {
+        log::warn!("Hotkey {} failed: {}", action, e);
+    }
+    let _ = app.emit("hotkey:triggered", serde_json::json!({ "action": action, "error": result.err() }));
+}
+
+pub(crate) async fn toggle_connect(app: &AppHandle) -> Result<(), String> {
+    let state = app.state::<AppState>();
+    let proxy_state = state.proxy_state.lock().await.clone();
+    let result = match proxy_state {
+        ProxyState::Connected => crate::commands::singbox_stop(app.clone(), state).await?,
+        ProxyState::Idle | ProxyState::Error => crate::commands::singbox_start(app.clone(), state).await?,
+        // 正在连接或断开时忽略
+        _ => return Ok(()),
+    };
+    if result.success { Ok(()) } else { Err(result.error.unwrap_or_default()) }
+}
+
+/// 切换并保存系统代理开关，连接中立即生效
+async fn toggle_system_proxy(app: &AppHandle) -> Result<(), String> {
+    let state = app.state::<AppState>();
+    let mut settings = state.settings.lock().await.clone();
+    settings.system_proxy = !settings.system_proxy;
+    crate::commands::settings::save_settings(&state, settings).await?;
+    if matches!(*state.proxy_state.lock().await, ProxyState::Connected) {
+        crate::commands::singbox::reapply_proxy_settings(app, &state).await?;
+    }
+    Ok(())
+}
+
+fn toggle_window(app: &AppHandle) -> Result<(), String> {
+    let Some(window) = app.get_webview_window("main") else { return Ok(()) };
+    if window.is_visible().unwrap_or(false) && window.is_focused().unwrap_or(false) {
+        window.hide().map_err(|e| e.to_string())
+    } else {
+        window.show().map_err(|e| e.to_string())?;
+        window.set_focus().map_err(|e| e.to_string())
+    }
+}
+
+/// 第一次按下隐藏所有可见窗口，再按一次只恢复这些窗口；开启 bossKeyMute 时通知前端期间不弹通知
+async fn toggle_boss_key(app: &AppHandle) -> Result<(), String> {
+    let mute = app.state::<AppState>().settings.lock().await.boss_key_mute;
+    let hidden = BOSS_HIDDEN.lock().map_err(|e| e.to_string())?.take();
+    let active = match hidden {
+        Some(labels) => {
+            for label in if labels.is_empty() { vec!["main".to_string()] } else { labels } {
+                if let Some(window) = app.get_webview_window(&label) {
+                    window.show().map_err(|e| e.to_string())?;
+                    let _ = window.set_focus();
+                }
+            }
+            false
+        }
+        None => {
+            let mut labels = Vec::new();
+            for (label, window) in app.webview_windows() {
+                if window.is_visible().unwrap_or(false) {
+                    window.hide().map_err(|e| e.to_string())?;
+                    labels.push(label);
+                }
+            }
+            *BOSS_HIDDEN.lock().map_err(|e| e.to_string())? = Some(labels);
+            true
+        }
+    };
+    let _ = app.emit("app:boss-key", serde_json::json!({ "hidden": active, "muteNotifications": active && mute }));
+    Ok(())
+}
+
+/// 在 PROXY 选择器中切换到下一个节点，到末尾后回到第一个
+async fn switch_to_next_node(app: &AppHandle) -> Result<(), String> {
+    let state = app.state::<AppState>();
+    if !matches!(*state.proxy_state.lock().await, ProxyState::Connected) {
+        return Err(crate::i18n::tr("error.notRunning").to_string());
+    }
+    let selector: serde_json::Value = reqwest::Client::new()
+        .get("http://127.0.0.1:9090/proxies/PROXY")
+        .timeout(std::time::Duration::from_secs(3))
+        .send()
+        .await
+        .map_err(|e| e.to_string())?
+        .json()
+        .await
+        .map_err(|e| e.to_string())?;
+    let all: Vec<&str> = selector["all"].as_array()
+        .map(|items| items.iter().filter_map(|v| v.as_str()).collect())
+        .unwrap_or_default();
+    if all.is_empty() {
+        return Err(crate::i18n::tr("error.noNodesToSwitch").to_string());
+    }
+    let now = selector["now"].as_str().unwrap_or_default();
+    let next = all.iter().position(|tag| *tag == now).map(|i| (i + 1) % all.len()).unwrap_or(0);
+
+    let result = crate::commands::singbox_switch_node(state, all[next].to_string()).await?;
+    if result.success { Ok(()) } else { Err(result.error.unwrap_or_default()) }
+}
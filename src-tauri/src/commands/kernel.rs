@@ -1,7 +1,9 @@
 use tauri::{AppHandle, Emitter, Manager, State};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use tokio_util::sync::CancellationToken;
 use crate::state::AppState;
+use crate::types::{KernelSource, ProxyState};
 
 #[cfg(windows)]
 use std::os::windows::process::CommandExt;
@@ -9,9 +11,14 @@ use std::os::windows::process::CommandExt;
 #[cfg(windows)]
 const CREATE_NO_WINDOW: u32 = 0x08000000;
 
-const GITHUB_API_STABLE: &str = "https://api.github.com/repos/SagerNet/sing-box/releases/latest";
-const GITHUB_API_RELEASES: &str = "https://api.github.com/repos/SagerNet/sing-box/releases?per_page=10";
 const KERNEL_FILENAME: &str = "sing-box.exe";
+const KERNELS_SUBDIR: &str = "kernels";
+const KERNELS_MANIFEST: &str = "active.json";
+const BUNDLED_KERNEL_VERSION: &str = "bundled";
+const KERNEL_BACKUP_KEEP: usize = 5;
+const KERNEL_MAX_EXTRACT_SIZE: u64 = 256 * 1024 * 1024;
+const DOWNLOAD_MAX_RETRIES: u32 = 3;
+const RELEASES_CACHE_TTL: std::time::Duration = std::time::Duration::from_secs(300);
 
 #[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
@@ -30,8 +37,28 @@ pub struct RemoteRelease {
     pub is_prerelease: bool,
     pub download_url: String,
     pub asset_name: String,
+    #[serde(default)]
+    pub sha256: Option<String>,
+    #[serde(default)]
+    pub checksum_url: Option<String>,
 }
 
+#[derive(serde::Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct KernelUpdateInfo {
+    pub local: Option<KernelVersion>,
+    pub releases: Vec<RemoteRelease>,
+}
+
+struct ReleasesCache {
+    source_key: String,
+    fetched_at: std::time::Instant,
+    releases: Vec<RemoteRelease>,
+}
+
+static RELEASES_CACHE: once_cell::sync::Lazy<tokio::sync::Mutex<Option<ReleasesCache>>> =
+    once_cell::sync::Lazy::new(|| tokio::sync::Mutex::new(None));
+
 #[derive(serde::Deserialize, Debug)]
 struct GithubRelease {
     tag_name: String,
@@ -40,54 +67,342 @@ struct GithubRelease {
     assets: Vec<GithubAsset>,
 }
 
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct KernelChangelog {
+    pub tag_name: String,
+    pub name: String,
+    pub body: String,
+    pub published_at: String,
+    pub html_url: String,
+}
+
+#[derive(serde::Deserialize, Debug)]
+struct GithubReleaseNotes {
+    tag_name: String,
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    body: Option<String>,
+    published_at: String,
+    html_url: String,
+}
+
 #[derive(serde::Deserialize, Debug)]
 struct GithubAsset {
     name: String,
     browser_download_url: String,
+    #[serde(default)]
+    digest: Option<String>,
 }
 
 fn get_kernel_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    Ok(app.state::<AppState>().kernel_dir())
+}
+
+/// 安装包自带的内核目录（安装在 Program Files 时为只读）
+fn get_bundled_kernel_dir(app: &AppHandle) -> Result<PathBuf, String> {
     let resource_dir = app.path().resource_dir().map_err(|e| e.to_string())?;
     Ok(resource_dir.join("resources").join("libs"))
 }
 
-fn get_kernel_path(app: &AppHandle) -> Result<PathBuf, String> {
-    Ok(get_kernel_dir(app)?.join(KERNEL_FILENAME))
+/// 数据目录中的 sing-box.exe 优先，不存在时使用安装包自带的内核
+fn get_default_kernel_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let path = get_kernel_dir(app)?.join(KERNEL_FILENAME);
+    if path.exists() {
+        return Ok(path);
+    }
+    Ok(get_bundled_kernel_dir(app)?.join(KERNEL_FILENAME))
+}
+
+/// 旧版本把下载的内核写进资源目录，首次启动时复制到数据目录
+pub(crate) fn migrate_legacy_kernel_dir(app: &AppHandle) {
+    let (old_dir, new_dir) = match (get_bundled_kernel_dir(app), get_kernel_dir(app)) {
+        (Ok(old_dir), Ok(new_dir)) => (old_dir, new_dir),
+        _ => return,
+    };
+    if new_dir.exists() || !old_dir.exists() {
+        return;
+    }
+    if let Err(e) = fs::create_dir_all(&new_dir) {
+        log::error!("Failed to create kernel dir {:?}: {}", new_dir, e);
+        return;
+    }
+    
+    // 存在备份说明用户升级过内核，当前与备份一起迁移以保留回滚能力
+    let old_backup = old_dir.join("sing-box.exe.bak");
+    if old_backup.exists() {
+        for name in [KERNEL_FILENAME, "sing-box.exe.bak"] {
+            let src = old_dir.join(name);
+            if src.exists() {
+                if let Err(e) = fs::copy(&src, new_dir.join(name)) {
+                    log::warn!("Failed to migrate {:?}: {}", src, e);
+                }
+            }
+        }
+    }
+    
+    let old_kernels = old_dir.join(KERNELS_SUBDIR);
+    if let Ok(entries) = fs::read_dir(&old_kernels) {
+        let new_kernels = new_dir.join(KERNELS_SUBDIR);
+        let _ = fs::create_dir_all(&new_kernels);
+        for entry in entries.flatten() {
+            if let Err(e) = fs::copy(entry.path(), new_kernels.join(entry.file_name())) {
+                log::warn!("Failed to migrate {:?}: {}", entry.path(), e);
+            }
+        }
+    }
+    
+    log::info!("Migrated kernels from {:?} to {:?}", old_dir, new_dir);
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, Default)]
+struct KernelsManifest {
+    active: Option<String>,
+    #[serde(default)]
+    backups: Vec<KernelBackupEntry>,
+    // 旧版的单级回滚记录，读取时并入 backups
+    #[serde(default, skip_serializing)]
+    previous: Option<String>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct KernelBackupEntry {
+    version: String,
+    backed_up_at: u64,
 }
 
-fn find_windows_asset<'a>(assets: &'a [GithubAsset], tag_name: &str) -> Option<&'a GithubAsset> {
-    let version = tag_name.trim_start_matches('v');
-    let expected_name = format!("sing-box-{}-windows-amd64.zip", version);
+#[derive(serde::Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct KernelBackup {
+    pub version: String,
+    pub backed_up_at: u64,
+    pub path: String,
+    pub size: u64,
+}
+
+#[derive(serde::Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct InstalledKernel {
+    pub version: String,
+    pub path: String,
+    pub size: u64,
+    pub is_active: bool,
+}
+
+fn get_kernels_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    Ok(get_kernel_dir(app)?.join(KERNELS_SUBDIR))
+}
+
+fn versioned_kernel_filename(version: &str) -> String {
+    format!("sing-box-{}.exe", version)
+}
+
+fn load_kernels_manifest(kernels_dir: &Path) -> KernelsManifest {
+    let mut manifest: KernelsManifest = fs::read_to_string(kernels_dir.join(KERNELS_MANIFEST))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default();
+    if let Some(previous) = manifest.previous.take() {
+        if manifest.backups.is_empty() {
+            manifest.backups.push(KernelBackupEntry { version: previous, backed_up_at: 0 });
+        }
+    }
+    manifest
+}
+
+/// 切换生效的内核版本，被替换的版本记为带时间戳的备份，回滚记录只保留最近 KERNEL_BACKUP_KEEP 个
+fn switch_active_kernel(app: &AppHandle, version: &str) -> Result<(), String> {
+    let kernels_dir = get_kernels_dir(app)?;
+    let mut manifest = load_kernels_manifest(&kernels_dir);
+    let current = manifest.active.clone().unwrap_or_else(|| BUNDLED_KERNEL_VERSION.to_string());
+    if current == version {
+        return Ok(());
+    }
+    
+    manifest.backups.retain(|b| b.version != current && b.version != version);
+    manifest.backups.push(KernelBackupEntry {
+        version: current,
+        backed_up_at: chrono::Utc::now().timestamp_millis() as u64,
+    });
+    
+    // 备份记录与已安装的版本共用同一个文件，仍可在已安装列表中切换，过期时只移出回滚记录，不删文件
+    if manifest.backups.len() > KERNEL_BACKUP_KEEP {
+        let expired = manifest.backups.len() - KERNEL_BACKUP_KEEP;
+        manifest.backups.drain(..expired);
+    }
+    
+    manifest.active = if version == BUNDLED_KERNEL_VERSION { None } else { Some(version.to_string()) };
+    save_kernels_manifest(&kernels_dir, &manifest)
+}
+
+fn save_kernels_manifest(kernels_dir: &Path, manifest: &KernelsManifest) -> Result<(), String> {
+    fs::create_dir_all(kernels_dir).map_err(|e| e.to_string())?;
+    let content = serde_json::to_string_pretty(manifest).map_err(|e| e.to_string())?;
+    fs::write(kernels_dir.join(KERNELS_MANIFEST), content).map_err(|e| e.to_string())
+}
+
+/// 解析当前生效的内核路径：用户自定义路径 > kernels 目录中选中的版本 > sing-box.exe
+pub(crate) async fn resolve_kernel_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let custom_path = app.state::<AppState>().settings.lock().await.kernel_path.clone();
+    if let Some(path) = custom_path.filter(|p| !p.is_empty()) {
+        return Ok(PathBuf::from(path));
+    }
+    
+    let kernel_dir = get_kernel_dir(app)?;
+    let kernels_dir = kernel_dir.join(KERNELS_SUBDIR);
+    if let Some(version) = load_kernels_manifest(&kernels_dir).active {
+        let path = kernels_dir.join(versioned_kernel_filename(&version));
+        if path.exists() {
+            return Ok(path);
+        }
+        log::warn!("Active kernel {} missing, falling back to {}", version, KERNEL_FILENAME);
+    }
+    get_default_kernel_path(app)
+}
+
+fn is_valid_kernel_version(version: &str) -> bool {
+    !version.is_empty()
+        && version.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '+' | '_'))
+}
+
+fn find_windows_asset<'a>(source: &KernelSource, assets: &'a [GithubAsset], tag_name: &str) -> Option<&'a GithubAsset> {
+    let expected_name = source.asset_name(tag_name);
     assets.iter().find(|a| a.name == expected_name)
 }
 
+async fn kernel_source_settings(state: &State<'_, AppState>) -> (KernelSource, Option<String>) {
+    let settings = state.settings.lock().await;
+    (settings.kernel_source.clone(), settings.github_token.clone())
+}
+
+/// GitHub 提供的 asset digest 格式为 "sha256:<hex>"
+fn asset_sha256(asset: &GithubAsset) -> Option<String> {
+    asset.digest.as_deref()
+        .and_then(|d| d.strip_prefix("sha256:"))
+        .map(|d| d.to_lowercase())
+}
+
+/// 查找 release 中的校验文件（sing-box-x.y.z-windows-amd64.zip.sha256 或 checksums 列表）
+fn find_checksum_asset<'a>(assets: &'a [GithubAsset], asset_name: &str) -> Option<&'a GithubAsset> {
+    let sidecar = format!("{}.sha256", asset_name);
+    assets.iter()
+        .find(|a| a.name == sidecar || a.name == format!("{}sum", sidecar))
+        .or_else(|| assets.iter().find(|a| {
+            let name = a.name.to_lowercase();
+            name.contains("checksum") || name.ends_with("sha256sums") || name.ends_with("sha256sums.txt")
+        }))
+}
+
+fn build_remote_release(release: &GithubRelease, asset: &GithubAsset) -> RemoteRelease {
+    RemoteRelease {
+        version: release.tag_name.trim_start_matches('v').to_string(),
+        tag_name: release.tag_name.clone(),
+        published_at: release.published_at.clone(),
+        is_prerelease: release.prerelease,
+        download_url: asset.browser_download_url.clone(),
+        asset_name: asset.name.clone(),
+        sha256: asset_sha256(asset),
+        checksum_url: find_checksum_asset(&release.assets, &asset.name)
+            .map(|a| a.browser_download_url.clone()),
+    }
+}
+
+/// 从校验文件内容中取出指定文件的 SHA256（支持 "<hex>  <name>" 列表和仅含 hex 的单文件格式）
+pub(crate) fn parse_checksum_file(content: &str, asset_name: &str) -> Option<String> {
+    let is_hex = |s: &str| s.len() == 64 && s.chars().all(|c| c.is_ascii_hexdigit());
+    for line in content.lines() {
+        let mut parts = line.split_whitespace();
+        let hash = match parts.next() {
+            Some(h) if is_hex(h) => h,
+            _ => continue,
+        };
+        match parts.next() {
+            Some(name) if name.trim_start_matches('*') == asset_name => return Some(hash.to_lowercase()),
+            None => return Some(hash.to_lowercase()),
+            _ => {}
+        }
+    }
+    None
+}
+
+async fn resolve_expected_sha256(clients: &[(&str, reqwest::Client)], release: &RemoteRelease) -> Option<String> {
+    fetch_expected_sha256(clients, release.sha256.as_deref(), release.checksum_url.as_deref(), &release.asset_name).await
+}
+
+/// 优先使用 API 给出的 digest，否则下载校验文件并从中查找
+pub(crate) async fn fetch_expected_sha256(
+    clients: &[(&str, reqwest::Client)],
+    sha256: Option<&str>,
+    checksum_url: Option<&str>,
+    asset_name: &str,
+) -> Option<String> {
+    if let Some(sha) = sha256 {
+        return Some(sha.to_lowercase());
+    }
+    let url = checksum_url?;
+    for (via, client) in clients {
+        match client.get(url).send().await {
+            Ok(resp) if resp.status().is_success() => {
+                let content = resp.text().await.ok()?;
+                return parse_checksum_file(&content, asset_name);
+            }
+            Ok(resp) => log::warn!("Failed to fetch checksum file via {}: HTTP {}", via, resp.status()),
+            Err(e) => log::warn!("Failed to fetch checksum file via {}: {}", via, e),
+        }
+    }
+    None
+}
+
+pub(crate) fn sha256_file(path: &Path) -> Result<String, String> {
+    use sha2::{Digest, Sha256};
+    let mut file = fs::File::open(path).map_err(|e| e.to_string())?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher).map_err(|e| e.to_string())?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
 #[tauri::command]
 pub async fn kernel_get_local_version(app: AppHandle) -> Result<Option<KernelVersion>, String> {
-    let kernel_path = get_kernel_path(&app)?;
+    let kernel_path = resolve_kernel_path(&app).await?;
     
     if !kernel_path.exists() {
         return Ok(None);
     }
     
+    probe_kernel_version(&kernel_path).await
+}
+
+async fn run_kernel_version(kernel_path: &Path) -> std::io::Result<std::process::Output> {
     #[cfg(windows)]
-    let output = tokio::process::Command::new(&kernel_path)
+    let output = tokio::process::Command::new(kernel_path)
         .arg("version")
         .creation_flags(CREATE_NO_WINDOW)
         .output()
-        .await
-        .map_err(|e| e.to_string())?;
+        .await;
 
     #[cfg(not(windows))]
-    let output = tokio::process::Command::new(&kernel_path)
+    let output = tokio::process::Command::new(kernel_path)
         .arg("version")
         .output()
-        .await
-        .map_err(|e| e.to_string())?;
+        .await;
+
+    output
+}
+
+/// 运行 `sing-box version` 读取版本信息，非 sing-box 程序返回 None
+pub(crate) async fn probe_kernel_version(kernel_path: &Path) -> Result<Option<KernelVersion>, String> {
+    let output = run_kernel_version(kernel_path).await.map_err(|e| e.to_string())?;
     
     if output.status.success() {
         let version_str = String::from_utf8_lossy(&output.stdout);
         let version_detail = version_str.trim().to_string();
         
+        if !version_detail.contains("sing-box version") {
+            return Ok(None);
+        }
+        
         // Parse version from output like "sing-box version 1.8.0"
         let version = version_str
             .lines()
@@ -106,167 +421,609 @@ pub async fn kernel_get_local_version(app: AppHandle) -> Result<Option<KernelVer
     Ok(None)
 }
 
-#[tauri::command]
-pub async fn kernel_get_remote_releases(include_prerelease: Option<bool>) -> Result<Vec<RemoteRelease>, String> {
-    let client = reqwest::Client::builder()
-        .user_agent("KunBox/1.0")
-        .timeout(std::time::Duration::from_secs(30))
-        .build()
-        .map_err(|e| e.to_string())?;
+#[derive(serde::Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct KernelIntegrityReport {
+    pub ok: bool,
+    pub path: String,
+    pub version: Option<String>,
+    pub reason: Option<String>,
+    pub hints: Vec<String>,
+}
+
+// Windows NTSTATUS codes surfaced as process exit codes
+const STATUS_DLL_NOT_FOUND: i32 = 0xC0000135_u32 as i32;
+const STATUS_INVALID_IMAGE_FORMAT: i32 = 0xC000007B_u32 as i32;
+
+/// 检查当前内核是否存在且能正常运行 `version`
+pub(crate) async fn check_kernel_integrity(app: &AppHandle) -> KernelIntegrityReport {
+    let kernel_path = match resolve_kernel_path(app).await {
+        Ok(path) => path,
+        Err(e) => return KernelIntegrityReport {
+            ok: false,
+            path: String::new(),
+            version: None,
+            reason: Some(e),
+            hints: vec!["Reinstall KunBox or download a kernel from the kernel page".to_string()],
+        },
+    };
+    let path = kernel_path.to_string_lossy().to_string();
+    let fail = |reason: &str, hints: &[&str]| KernelIntegrityReport {
+        ok: false,
+        path: path.clone(),
+        version: None,
+        reason: Some(reason.to_string()),
+        hints: hints.iter().map(|h| h.to_string()).collect(),
+    };
     
-    let mut releases = Vec::new();
+    if !kernel_path.exists() {
+        return fail("Kernel not found", &["Download a kernel from the kernel page", "Check the custom kernel path in settings"]);
+    }
     
-    // Get stable release
-    let stable_response = client.get(GITHUB_API_STABLE)
-        .header("Accept", "application/vnd.github.v3+json")
-        .send()
-        .await;
+    let output = match run_kernel_version(&kernel_path).await {
+        Ok(output) => output,
+        Err(e) => return fail(&format!("Failed to run kernel: {}", e), &[
+            "The file may be corrupt or blocked by antivirus software",
+            "Re-download the kernel or restore it from the antivirus quarantine",
+        ]),
+    };
     
-    if let Ok(resp) = stable_response {
-        if resp.status().is_success() {
-            if let Ok(stable) = resp.json::<GithubRelease>().await {
-                if let Some(asset) = find_windows_asset(&stable.assets, &stable.tag_name) {
-                    releases.push(RemoteRelease {
-                        version: stable.tag_name.trim_start_matches('v').to_string(),
-                        tag_name: stable.tag_name.clone(),
-                        published_at: stable.published_at,
-                        is_prerelease: false,
-                        download_url: asset.browser_download_url.clone(),
-                        asset_name: asset.name.clone(),
-                    });
-                }
+    if !output.status.success() {
+        return match output.status.code() {
+            Some(STATUS_DLL_NOT_FOUND) => fail("Kernel is missing a required DLL", &[
+                "Make sure wintun.dll is present next to sing-box.exe when using TUN mode",
+                "Install the latest Microsoft Visual C++ Redistributable",
+            ]),
+            Some(STATUS_INVALID_IMAGE_FORMAT) => fail("Kernel binary is not a valid Windows executable", &[
+                "Download the windows-amd64 build of sing-box",
+            ]),
+            code => fail(&format!("Kernel exited with code {:?}", code), &[
+                "Re-download the kernel",
+            ]),
+        };
+    }
+    
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if !stdout.contains("sing-box version") {
+        return fail("Binary does not look like sing-box", &["Check the custom kernel path in settings"]);
+    }
+    
+    KernelIntegrityReport {
+        ok: true,
+        path,
+        version: stdout
+            .lines()
+            .find(|line| line.contains("version"))
+            .and_then(|line| line.split_whitespace().last())
+            .map(|v| v.to_string()),
+        reason: None,
+        hints: Vec::new(),
+    }
+}
+
+#[tauri::command]
+pub async fn kernel_check_integrity(app: AppHandle) -> Result<KernelIntegrityReport, String> {
+    Ok(check_kernel_integrity(&app).await)
+}
+
+/// 校验用户指定的内核路径是否为可用的 sing-box 程序
+#[tauri::command]
+pub async fn kernel_validate_path(path: String) -> Result<KernelVersion, String> {
+    let kernel_path = PathBuf::from(&path);
+    if !kernel_path.is_file() {
+        return Err(format!("Kernel not found: {}", path));
+    }
+    probe_kernel_version(&kernel_path)
+        .await?
+        .ok_or_else(|| format!("Not a valid sing-box binary: {}", path))
+}
+
+#[tauri::command]
+pub async fn kernel_get_remote_releases(state: State<'_, AppState>, include_prerelease: Option<bool>, refresh: Option<bool>) -> Result<Vec<RemoteRelease>, String> {
+    let releases = fetch_remote_releases(&state, refresh.unwrap_or(false)).await?;
+    
+    // Drop the prerelease unless requested
+    let include_pre = include_prerelease.unwrap_or(true);
+    Ok(releases.into_iter().filter(|r| include_pre || !r.is_prerelease).collect())
+}
+
+/// 本地版本与远端版本一起返回，两者并发获取
+#[tauri::command]
+pub async fn kernel_check_update(app: AppHandle, state: State<'_, AppState>, include_prerelease: Option<bool>) -> Result<KernelUpdateInfo, String> {
+    let (local, releases) = tokio::join!(
+        kernel_get_local_version(app.clone()),
+        kernel_get_remote_releases(state.clone(), include_prerelease, None),
+    );
+    Ok(KernelUpdateInfo {
+        local: local?,
+        releases: releases?,
+    })
+}
+
+/// 单次请求 releases 列表，从中取最新的正式版和预发布版，结果缓存几分钟
+async fn fetch_remote_releases(state: &State<'_, AppState>, refresh: bool) -> Result<Vec<RemoteRelease>, String> {
+    let (source, token) = kernel_source_settings(state).await;
+    let source_key = format!("{}/{}/{}", source.api_base, source.repo, source.asset_pattern);
+    
+    if !refresh {
+        if let Some(cache) = RELEASES_CACHE.lock().await.as_ref() {
+            if cache.source_key == source_key && cache.fetched_at.elapsed() < RELEASES_CACHE_TTL {
+                return Ok(cache.releases.clone());
             }
         }
     }
     
-    // Get prerelease if requested
-    let include_pre = include_prerelease.unwrap_or(true);
-    if include_pre {
-        let releases_response = client.get(GITHUB_API_RELEASES)
-            .header("Accept", "application/vnd.github.v3+json")
-            .send()
-            .await;
-        
-        if let Ok(resp) = releases_response {
-            if resp.status().is_success() {
-                if let Ok(all_releases) = resp.json::<Vec<GithubRelease>>().await {
-                    for release in all_releases {
-                        if release.prerelease {
-                            if let Some(asset) = find_windows_asset(&release.assets, &release.tag_name) {
-                                releases.push(RemoteRelease {
-                                    version: release.tag_name.trim_start_matches('v').to_string(),
-                                    tag_name: release.tag_name.clone(),
-                                    published_at: release.published_at,
-                                    is_prerelease: true,
-                                    download_url: asset.browser_download_url.clone(),
-                                    asset_name: asset.name.clone(),
-                                });
-                                break; // Only get latest prerelease
-                            }
-                        }
-                    }
-                }
+    let clients = build_http_clients(state, 30).await?;
+    let releases_url = format!("{}?per_page=20", source.releases_api());
+    let all_releases = fetch_github_json::<Vec<GithubRelease>>(&clients, &releases_url, token.as_deref())
+        .await
+        .map_err(|e| format!("Failed to fetch kernel releases: {}", e))?;
+    
+    let mut stable = None;
+    let mut prerelease = None;
+    for release in &all_releases {
+        let slot = if release.prerelease { &mut prerelease } else { &mut stable };
+        if slot.is_some() {
+            continue;
+        }
+        if let Some(asset) = find_windows_asset(&source, &release.assets, &release.tag_name) {
+            *slot = Some(build_remote_release(release, asset));
+        }
+        if stable.is_some() && prerelease.is_some() {
+            break;
+        }
+    }
+    // 两个正式版之间预发布版很多时，正式版可能不在这一页里
+    if stable.is_none() {
+        let latest_url = format!("{}/latest", source.releases_api());
+        if let Ok(release) = fetch_github_json::<GithubRelease>(&clients, &latest_url, token.as_deref()).await {
+            if let Some(asset) = find_windows_asset(&source, &release.assets, &release.tag_name) {
+                stable = Some(build_remote_release(&release, asset));
             }
         }
     }
+    let releases: Vec<RemoteRelease> = stable.into_iter().chain(prerelease).collect();
     
+    *RELEASES_CACHE.lock().await = Some(ReleasesCache {
+        source_key,
+        fetched_at: std::time::Instant::now(),
+        releases: releases.clone(),
+    });
     Ok(releases)
 }
 
+/// 获取指定版本的 release 说明（Markdown）；tag 为 releases 列表返回的 tagName 原样，自定义源的 tag 不一定带 v
 #[tauri::command]
-pub async fn kernel_download(app: AppHandle, release: RemoteRelease) -> Result<serde_json::Value, String> {
-    let _ = app.emit("kernel:download-start", ());
+pub async fn kernel_get_changelog(state: State<'_, AppState>, tag: String) -> Result<KernelChangelog, String> {
+    let clients = build_http_clients(&state, 30).await?;
+    let (source, token) = kernel_source_settings(&state).await;
     
-    let client = reqwest::Client::builder()
+    let url = format!("{}/tags/{}", source.releases_api(), urlencoding::encode(&tag));
+    let notes = fetch_github_json::<GithubReleaseNotes>(&clients, &url, token.as_deref())
+        .await
+        .map_err(|e| format!("Failed to fetch changelog for {}: {}", tag, e))?;
+    
+    Ok(KernelChangelog {
+        name: notes.name.filter(|n| !n.is_empty()).unwrap_or_else(|| notes.tag_name.clone()),
+        tag_name: notes.tag_name,
+        body: notes.body.unwrap_or_default(),
+        published_at: notes.published_at,
+        html_url: notes.html_url,
+    })
+}
+
+/// HTTP 客户端列表：核心运行时优先走本地代理，之后回退直连
+pub(crate) async fn build_http_clients(state: &AppState, timeout_secs: u64) -> Result<Vec<(&'static str, reqwest::Client)>, String> {
+    let builder = || reqwest::Client::builder()
         .user_agent("KunBox/1.0")
-        .timeout(std::time::Duration::from_secs(600))
-        .build()
-        .map_err(|e| e.to_string())?;
+        .timeout(std::time::Duration::from_secs(timeout_secs));
     
-    // Download the zip file
-    let response = client.get(&release.download_url)
-        .send()
-        .await
-        .map_err(|e| {
-            let _ = app.emit("kernel:download-error", e.to_string());
-            e.to_string()
-        })?;
-    
-    if !response.status().is_success() {
-        let err = format!("Download failed: {}", response.status());
-        let _ = app.emit("kernel:download-error", &err);
-        return Err(err);
+    let mut clients = Vec::new();
+    
+    let is_running = matches!(*state.proxy_state.lock().await, ProxyState::Connected);
+    if is_running {
+        let port = state.settings.lock().await.local_port;
+        let proxy_client = reqwest::Proxy::all(format!("http://127.0.0.1:{}", port))
+            .and_then(|proxy| builder().proxy(proxy).build());
+        match proxy_client {
+            Ok(client) => clients.push(("proxy", client)),
+            Err(e) => log::warn!("Failed to create proxy client: {}", e),
+        }
     }
     
-    let total_size = response.content_length().unwrap_or(0);
-    let mut downloaded: u64 = 0;
+    let direct_client = builder().build().map_err(|e| e.to_string())?;
+    clients.push(("direct", direct_client));
     
-    let mut bytes = Vec::new();
-    let mut stream = response.bytes_stream();
+    Ok(clients)
+}
+
+/// 依次使用各客户端请求 GitHub API，返回第一个成功解析的结果；全部失败时返回最后一个错误
+pub(crate) async fn fetch_github_json<T: serde::de::DeserializeOwned>(clients: &[(&str, reqwest::Client)], url: &str, token: Option<&str>) -> Result<T, String> {
+    let mut last_error = String::from("No HTTP client available");
+    for (via, client) in clients {
+        let resp = crate::github::api_get(client, url, token)
+            .send()
+            .await;
+        last_error = match resp {
+            Ok(resp) if resp.status().is_success() => match resp.json::<T>().await {
+                Ok(data) => return Ok(data),
+                Err(e) => format!("Parse error: {}", e),
+            },
+            Ok(resp) => format!("HTTP {}", resp.status()),
+            Err(e) => format!("Request failed: {}", e),
+        };
+        log::warn!("GitHub API request via {} failed: {}", via, last_error);
+    }
+    Err(last_error)
+}
+
+/// 下载并安装内核。找不到 SHA256 时默认拒绝安装，allow_unverified 为 true 时由用户明确同意后才跳过校验
+#[tauri::command]
+pub async fn kernel_download(app: AppHandle, state: State<'_, AppState>, release: RemoteRelease, allow_unverified: Option<bool>) -> Result<serde_json::Value, String> {
+    // 文件名会拼进缓存目录，不能带路径
+    if release.asset_name.is_empty() || release.asset_name.contains(['/', '\\']) || release.asset_name.contains("..") {
+        return Err(format!("Invalid kernel asset name: {}", release.asset_name));
+    }
+    // 版本号会拼进内核文件名，下载前就检查，免得白下载一遍
+    if !is_valid_kernel_version(&release.version) {
+        return Err(format!("Invalid kernel version: {}", release.version));
+    }
+    let _ = app.emit("kernel:download-start", ());
     
-    use futures_util::StreamExt;
-    while let Some(chunk) = stream.next().await {
-        let chunk = chunk.map_err(|e| e.to_string())?;
-        bytes.extend_from_slice(&chunk);
-        downloaded += chunk.len() as u64;
-        
-        if total_size > 0 {
-            let progress = serde_json::json!({
-                "downloaded": downloaded,
-                "total": total_size,
-                "percent": (downloaded as f64 / total_size as f64 * 100.0) as u32
-            });
-            let _ = app.emit("kernel:download-progress", progress);
+    let clients = build_http_clients(&state, 600).await?;
+    
+    let cancel_token = CancellationToken::new();
+    *state.kernel_download_cancel.lock().await = Some(cancel_token.clone());
+    state.download_progress.lock().await.remove("kernel:download-progress");
+    
+    // Stream into a partial file under the cache dir so interrupted downloads can resume
+    let download_dir = state.data_dir.join("cache").join("kernel");
+    fs::create_dir_all(&download_dir).map_err(|e| e.to_string())?;
+    let part_path = download_dir.join(format!("{}.part", release.asset_name));
+    
+    // GitHub 地址依次尝试镜像
+    let mut used_url = None;
+    let mut last_error = String::new();
+    let mirrors = state.settings.lock().await.github_mirrors.clone();
+    'urls: for url in crate::github::mirror_urls(&release.download_url, &mirrors) {
+        // 每个地址先尝试代理，再回退直连
+        for (via, client) in &clients {
+            let _ = app.emit("kernel:download-source", serde_json::json!({ "url": crate::redact::redact(&url), "via": via }));
+            match download_with_resume(&app, client, &url, &part_path, &cancel_token, "kernel:download-progress").await {
+                Ok(_) => {
+                    log::info!("Kernel downloaded via {}: {}", via, url);
+                    used_url = Some(url);
+                    break 'urls;
+                }
+                Err(_) if cancel_token.is_cancelled() => break 'urls,
+                Err(e) => {
+                    log::warn!("Kernel download via {} failed for {}: {}", via, url, e);
+                    last_error = e;
+                }
+            }
         }
     }
+    state.kernel_download_cancel.lock().await.take();
     
-    // Extract zip
-    let kernel_dir = get_kernel_dir(&app)?;
-    fs::create_dir_all(&kernel_dir).map_err(|e| e.to_string())?;
+    if cancel_token.is_cancelled() {
+        log::info!("Kernel download cancelled, partial file kept at {:?}", part_path);
+        let _ = app.emit("kernel:download-cancelled", ());
+        return Ok(serde_json::json!({ "success": false, "cancelled": true }));
+    }
     
-    let cursor = std::io::Cursor::new(bytes);
-    let mut archive = zip::ZipArchive::new(cursor).map_err(|e| e.to_string())?;
+    let used_url = match used_url {
+        Some(url) => url,
+        None => {
+            let err = format!("All download attempts failed: {}", last_error);
+            let _ = app.emit("kernel:download-error", crate::redact::redact(&err));
+            return Err(err);
+        }
+    };
+    
+    // Verify checksum before touching the installed kernel; mirrors are third-party, so no checksum means no install
+    let verified = match resolve_expected_sha256(&clients, &release).await {
+        Some(expected) => {
+            let actual = sha256_file(&part_path)?;
+            if actual != expected {
+                let _ = fs::remove_file(&part_path);
+                let err = format!("SHA256 mismatch for {}: expected {}, got {}", release.asset_name, expected, actual);
+                let _ = app.emit("kernel:download-error", crate::redact::redact(&err));
+                return Err(err);
+            }
+            log::info!("Kernel archive SHA256 verified: {}", actual);
+            true
+        }
+        None if allow_unverified == Some(true) => {
+            log::warn!("No checksum available for {}, installing unverified at the user's request", release.asset_name);
+            false
+        }
+        None => {
+            let _ = fs::remove_file(&part_path);
+            let err = format!("No checksum available for {}, refusing unverified kernel", release.asset_name);
+            let _ = app.emit("kernel:download-error", crate::redact::redact(&err));
+            return Err(err);
+        }
+    };
+    let _ = app.emit("kernel:download-verified", serde_json::json!({ "verified": verified }));
+
+    // 运行中的核心会占用内核文件，先停止，安装完成后按原配置重启
+    let was_running = stop_core_if_running(&app, &state).await?;
+    let install_result = install_kernel_archive(&app, &part_path, &release.version);
+    let restart = restart_core_if_needed(&app, &state, was_running).await;
+    
+    if let Err(err) = install_result {
+        let _ = app.emit("kernel:download-error", crate::redact::redact(&err));
+        return Err(err);
+    }
+    
+    let _ = fs::remove_file(&part_path);
+    let _ = app.emit("kernel:download-complete", ());
+    
+    let mut result = serde_json::json!({ "success": true, "url": used_url, "verified": verified });
+    merge_restart_result(&mut result, restart);
+    Ok(result)
+}
+
+fn install_kernel_archive(app: &AppHandle, archive_path: &Path, version: &str) -> Result<(), String> {
+    let kernels_dir = get_kernels_dir(app)?;
+    fs::create_dir_all(&kernels_dir).map_err(|e| e.to_string())?;
+    
+    let archive_file = fs::File::open(archive_path).map_err(|e| e.to_string())?;
+    let mut archive = zip::ZipArchive::new(archive_file).map_err(|e| e.to_string())?;
     
     let mut found = false;
     for i in 0..archive.len() {
-        let mut file = archive.by_index(i).map_err(|e| e.to_string())?;
-        let name = file.name().to_string();
+        let file = archive.by_index(i).map_err(|e| e.to_string())?;
         
-        if name.ends_with("sing-box.exe") {
-            let kernel_path = kernel_dir.join(KERNEL_FILENAME);
-            
-            // Backup existing
-            if kernel_path.exists() {
-                let backup_path = kernel_dir.join("sing-box.exe.bak");
-                if backup_path.exists() {
-                    let _ = fs::remove_file(&backup_path);
-                }
-                let _ = fs::rename(&kernel_path, &backup_path);
+        // 拒绝包含 .. 或绝对路径的条目（zip-slip）
+        let entry_path = match file.enclosed_name() {
+            Some(path) => path,
+            None => {
+                log::warn!("Skipping unsafe archive entry: {}", file.name());
+                continue;
             }
-            
-            let mut outfile = fs::File::create(&kernel_path).map_err(|e| e.to_string())?;
-            std::io::copy(&mut file, &mut outfile).map_err(|e| e.to_string())?;
-            
-            log::info!("Kernel installed to {:?}", kernel_path);
-            found = true;
-            break;
+        };
+        if !file.is_file() || entry_path.file_name().and_then(|n| n.to_str()) != Some(KERNEL_FILENAME) {
+            continue;
+        }
+        if file.size() > KERNEL_MAX_EXTRACT_SIZE {
+            return Err(format!("Kernel in archive is too large: {}", format_bytes(file.size())));
+        }
+        
+        let kernel_path = kernels_dir.join(versioned_kernel_filename(version));
+        let tmp_path = kernels_dir.join(format!("{}.tmp", versioned_kernel_filename(version)));
+        
+        // 先解压到临时文件，完整写入后再原子替换
+        let extract_result = (|| -> Result<(), String> {
+            let mut outfile = fs::File::create(&tmp_path).map_err(|e| e.to_string())?;
+            // 不信任条目头中声明的大小，实际解压时同样限制
+            let mut limited = std::io::Read::take(file, KERNEL_MAX_EXTRACT_SIZE + 1);
+            let written = std::io::copy(&mut limited, &mut outfile).map_err(|e| e.to_string())?;
+            if written > KERNEL_MAX_EXTRACT_SIZE {
+                return Err("Kernel in archive exceeds the maximum extracted size".to_string());
+            }
+            outfile.sync_all().map_err(|e| e.to_string())?;
+            Ok(())
+        })();
+        if let Err(e) = extract_result {
+            let _ = fs::remove_file(&tmp_path);
+            return Err(e);
+        }
+        
+        if let Err(e) = fs::rename(&tmp_path, &kernel_path) {
+            let _ = fs::remove_file(&tmp_path);
+            return Err(format!("Failed to install kernel: {}", e));
         }
+        
+        log::info!("Kernel installed to {:?}", kernel_path);
+        found = true;
+        break;
     }
     
     if !found {
-        let err = "sing-box.exe not found in archive";
-        let _ = app.emit("kernel:download-error", err);
-        return Err(err.to_string());
+        return Err("sing-box.exe not found in archive".to_string());
     }
     
-    let _ = app.emit("kernel:download-complete", ());
+    // 切换到新版本，旧版本进入备份列表用于回滚
+    switch_active_kernel(app, version)
+}
+
+async fn stop_core_if_running(app: &AppHandle, state: &State<'_, AppState>) -> Result<bool, String> {
+    let is_running = matches!(*state.proxy_state.lock().await, ProxyState::Connected);
+    if is_running {
+        log::info!("Stopping core before replacing kernel");
+        crate::commands::singbox_stop(app.clone(), state.clone()).await?;
+    }
+    Ok(is_running)
+}
+
+async fn restart_core_if_needed(app: &AppHandle, state: &State<'_, AppState>, was_running: bool) -> Option<Result<(), String>> {
+    if !was_running {
+        return None;
+    }
+    let result = match crate::commands::singbox_start(app.clone(), state.clone()).await {
+        Ok(r) if r.success => Ok(()),
+        Ok(r) => Err(r.error.unwrap_or_else(|| "Unknown error".to_string())),
+        Err(e) => Err(e),
+    };
+    if let Err(ref e) = result {
+        log::error!("Failed to restart core after kernel change: {}", e);
+    }
+    Some(result)
+}
+
+fn merge_restart_result(result: &mut serde_json::Value, restart: Option<Result<(), String>>) {
+    if let Some(restart) = restart {
+        result["restarted"] = serde_json::Value::Bool(restart.is_ok());
+        if let Err(e) = restart {
+            result["restartError"] = serde_json::Value::String(e);
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn kernel_download_cancel(state: State<'_, AppState>) -> Result<bool, String> {
+    if let Some(cancel) = state.kernel_download_cancel.lock().await.take() {
+        cancel.cancel();
+        return Ok(true);
+    }
+    Ok(false)
+}
+
+/// Download with automatic Range-based resume on interruption
+pub(crate) async fn download_with_resume(
+    app: &AppHandle,
+    client: &reqwest::Client,
+    url: &str,
+    part_path: &Path,
+    cancel: &CancellationToken,
+    progress_event: &str,
+) -> Result<u64, String> {
+    let mut attempt = 0;
+    loop {
+        match download_to_part_file(app, client, url, part_path, cancel, progress_event).await {
+            Ok(size) => return Ok(size),
+            Err(e) if cancel.is_cancelled() => return Err(e),
+            Err(e) => {
+                attempt += 1;
+                if attempt > DOWNLOAD_MAX_RETRIES {
+                    return Err(e);
+                }
+                log::warn!("Download interrupted: {}, resuming ({}/{})", e, attempt, DOWNLOAD_MAX_RETRIES);
+                tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+            }
+        }
+    }
+}
+
+async fn download_to_part_file(
+    app: &AppHandle,
+    client: &reqwest::Client,
+    url: &str,
+    part_path: &Path,
+    cancel: &CancellationToken,
+    progress_event: &str,
+) -> Result<u64, String> {
+    use futures_util::StreamExt;
+    use tokio::io::AsyncWriteExt;
+
+    let existing = fs::metadata(part_path).map(|m| m.len()).unwrap_or(0);
     
-    Ok(serde_json::json!({ "success": true }))
+    let mut request = client.get(url);
+    if existing > 0 {
+        request = request.header(reqwest::header::RANGE, format!("bytes={}-", existing));
+    }
+    
+    let response = tokio::select! {
+        _ = cancel.cancelled() => return Err("Download cancelled".to_string()),
+        resp = request.send() => resp.map_err(|e| e.to_string())?,
+    };
+    
+    let status = response.status();
+    if status == reqwest::StatusCode::RANGE_NOT_SATISFIABLE {
+        // Partial file is stale or larger than the remote asset; start over
+        let _ = fs::remove_file(part_path);
+        return Err("Range not satisfiable, restarting download".to_string());
+    }
+    if !status.is_success() {
+        return Err(format!("Download failed: {}", status));
+    }
+    
+    // 镜像失效时常返回 HTML 错误页
+    let is_html = response.headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.contains("text/html"))
+        .unwrap_or(false);
+    if is_html {
+        return Err("Received HTML instead of archive".to_string());
+    }
+    
+    let resumed = existing > 0 && status == reqwest::StatusCode::PARTIAL_CONTENT;
+    let mut downloaded: u64 = if resumed { existing } else { 0 };
+    let total_size = response.content_length().map(|len| len + downloaded).unwrap_or(0);
+    
+    if resumed {
+        log::info!("Resuming download of {:?} at {} bytes", part_path, existing);
+    }
+    
+    let mut file = if resumed {
+        tokio::fs::OpenOptions::new().append(true).open(part_path).await
+    } else {
+        tokio::fs::File::create(part_path).await
+    }.map_err(|e| e.to_string())?;
+    
+    let mut stream = response.bytes_stream();
+    let mut speed_sample_time = std::time::Instant::now();
+    let mut speed_sample_bytes = downloaded;
+    let mut speed: u64 = 0;
+    loop {
+        let chunk = tokio::select! {
+            _ = cancel.cancelled() => {
+                let _ = file.flush().await;
+                return Err("Download cancelled".to_string());
+            }
+            chunk = stream.next() => chunk,
+        };
+        let chunk = match chunk {
+            Some(chunk) => chunk.map_err(|e| e.to_string())?,
+            None => break,
+        };
+        file.write_all(&chunk).await.map_err(|e| e.to_string())?;
+        downloaded += chunk.len() as u64;
+        
+        // 每 500ms 采样一次速度，平滑处理避免数值跳动
+        let elapsed = speed_sample_time.elapsed();
+        if elapsed >= std::time::Duration::from_millis(500) {
+            let instant_speed = ((downloaded - speed_sample_bytes) as f64 / elapsed.as_secs_f64()) as u64;
+            speed = if speed == 0 { instant_speed } else { (speed * 7 + instant_speed * 3) / 10 };
+            speed_sample_time = std::time::Instant::now();
+            speed_sample_bytes = downloaded;
+        }
+        
+        if total_size > 0 {
+            let eta = (speed > 0).then(|| total_size.saturating_sub(downloaded) / speed);
+            let progress = serde_json::json!({
+                "downloaded": downloaded,
+                "total": total_size,
+                "percent": (downloaded as f64 / total_size as f64 * 100.0) as u32,
+                "speed": speed,
+                "eta": eta,
+                "downloadedText": format_bytes(downloaded),
+                "totalText": format_bytes(total_size),
+                "speedText": format!("{}/s", format_bytes(speed))
+            });
+            app.state::<AppState>().download_progress.lock().await.insert(progress_event.to_string(), progress.clone());
+            let _ = app.emit(progress_event, progress);
+        }
+    }
+    file.flush().await.map_err(|e| e.to_string())?;
+    
+    if total_size > 0 && downloaded != total_size {
+        return Err(format!("Download incomplete: {} of {} bytes", downloaded, total_size));
+    }
+    
+    Ok(downloaded)
 }
 
 #[tauri::command]
-pub async fn kernel_rollback(app: AppHandle) -> Result<serde_json::Value, String> {
-    let kernel_dir = get_kernel_dir(&app)?;
+pub async fn kernel_rollback(app: AppHandle, state: State<'_, AppState>) -> Result<serde_json::Value, String> {
+    let was_running = stop_core_if_running(&app, &state).await?;
+    let result = rollback_kernel(&app);
+    let restart = restart_core_if_needed(&app, &state, was_running).await;
+    let mut result = result?;
+    merge_restart_result(&mut result, restart);
+    Ok(result)
+}
+
+fn rollback_kernel(app: &AppHandle) -> Result<serde_json::Value, String> {
+    // 切回最近一次备份的版本
+    if let Some(backup) = list_kernel_backups(app)?.into_iter().next() {
+        switch_active_kernel(app, &backup.version)?;
+        return Ok(serde_json::json!({ "success": true, "version": backup.version }));
+    }
+    
+    let kernel_dir = get_kernel_dir(app)?;
     let kernel_path = kernel_dir.join(KERNEL_FILENAME);
     let backup_path = kernel_dir.join("sing-box.exe.bak");
     
@@ -290,13 +1047,131 @@ pub async fn kernel_rollback(app: AppHandle) -> Result<serde_json::Value, String
     Ok(serde_json::json!({ "success": true }))
 }
 
+#[tauri::command]
+pub async fn kernel_rollback_to(app: AppHandle, state: State<'_, AppState>, version: String) -> Result<serde_json::Value, String> {
+    if !list_kernel_backups(&app)?.iter().any(|b| b.version == version) {
+        return Ok(serde_json::json!({ "success": false, "error": format!("No backup for kernel {}", version) }));
+    }
+    
+    let was_running = stop_core_if_running(&app, &state).await?;
+    let result = switch_active_kernel(&app, &version);
+    let restart = restart_core_if_needed(&app, &state, was_running).await;
+    result?;
+    
+    let mut result = serde_json::json!({ "success": true, "version": version });
+    merge_restart_result(&mut result, restart);
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn kernel_list_backups(app: AppHandle) -> Result<Vec<KernelBackup>, String> {
+    list_kernel_backups(&app)
+}
+
+/// 仍在磁盘上的备份，按时间从新到旧排列
+fn list_kernel_backups(app: &AppHandle) -> Result<Vec<KernelBackup>, String> {
+    let kernels_dir = get_kernels_dir(app)?;
+    let manifest = load_kernels_manifest(&kernels_dir);
+    let mut backups = Vec::new();
+    
+    for entry in manifest.backups.iter().rev() {
+        let path = if entry.version == BUNDLED_KERNEL_VERSION {
+            get_default_kernel_path(app)?
+        } else {
+            kernels_dir.join(versioned_kernel_filename(&entry.version))
+        };
+        if let Ok(meta) = path.metadata() {
+            backups.push(KernelBackup {
+                version: entry.version.clone(),
+                backed_up_at: entry.backed_up_at,
+                path: path.to_string_lossy().to_string(),
+                size: meta.len(),
+            });
+        }
+    }
+    
+    Ok(backups)
+}
+
 #[tauri::command]
 pub async fn kernel_can_rollback(app: AppHandle) -> Result<bool, String> {
+    if !list_kernel_backups(&app)?.is_empty() {
+        return Ok(true);
+    }
     let kernel_dir = get_kernel_dir(&app)?;
     let backup_path = kernel_dir.join("sing-box.exe.bak");
     Ok(backup_path.exists())
 }
 
+fn kernel_version_installed(app: &AppHandle, version: &str) -> Result<bool, String> {
+    if version == BUNDLED_KERNEL_VERSION {
+        return Ok(get_default_kernel_path(app)?.exists());
+    }
+    Ok(get_kernels_dir(app)?.join(versioned_kernel_filename(version)).exists())
+}
+
+#[tauri::command]
+pub async fn kernel_list_installed(app: AppHandle) -> Result<Vec<InstalledKernel>, String> {
+    let kernels_dir = get_kernels_dir(&app)?;
+    let active_path = resolve_kernel_path(&app).await?;
+    let mut kernels = Vec::new();
+    
+    let bundled_path = get_default_kernel_path(&app)?;
+    if let Ok(meta) = bundled_path.metadata() {
+        kernels.push(InstalledKernel {
+            version: BUNDLED_KERNEL_VERSION.to_string(),
+            path: bundled_path.to_string_lossy().to_string(),
+            size: meta.len(),
+            is_active: bundled_path == active_path,
+        });
+    }
+    
+    if let Ok(entries) = fs::read_dir(&kernels_dir) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            let version = match file_name.strip_prefix("sing-box-").and_then(|n| n.strip_suffix(".exe")) {
+                Some(v) => v.to_string(),
+                None => continue,
+            };
+            let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+            kernels.push(InstalledKernel {
+                version,
+                path: path.to_string_lossy().to_string(),
+                size,
+                is_active: path == active_path,
+            });
+        }
+    }
+    
+    Ok(kernels)
+}
+
+#[tauri::command]
+pub async fn kernel_set_active(app: AppHandle, state: State<'_, AppState>, version: String) -> Result<serde_json::Value, String> {
+    if version != BUNDLED_KERNEL_VERSION && !is_valid_kernel_version(&version) {
+        return Ok(serde_json::json!({ "success": false, "error": "Invalid kernel version" }));
+    }
+    if !kernel_version_installed(&app, &version)? {
+        return Ok(serde_json::json!({ "success": false, "error": format!("Kernel {} is not installed", version) }));
+    }
+    
+    let manifest = load_kernels_manifest(&get_kernels_dir(&app)?);
+    let current = manifest.active.unwrap_or_else(|| BUNDLED_KERNEL_VERSION.to_string());
+    if current == version {
+        return Ok(serde_json::json!({ "success": true }));
+    }
+    
+    let was_running = stop_core_if_running(&app, &state).await?;
+    let save_result = switch_active_kernel(&app, &version);
+    let restart = restart_core_if_needed(&app, &state, was_running).await;
+    save_result?;
+    
+    let mut result = serde_json::json!({ "success": true });
+    merge_restart_result(&mut result, restart);
+    Ok(result)
+}
+
 #[tauri::command]
 pub async fn kernel_clear_cache(state: State<'_, AppState>) -> Result<serde_json::Value, String> {
     let cache_dir = state.data_dir.join("cache");
@@ -310,6 +1185,21 @@ pub async fn kernel_clear_cache(state: State<'_, AppState>) -> Result<serde_json
     Ok(serde_json::json!({ "success": true, "freedBytes": freed_bytes }))
 }
 
+pub(crate) fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
 fn get_dir_size(path: &std::path::Path) -> u64 {
     let mut size = 0;
     if let Ok(entries) = fs::read_dir(path) {
@@ -326,8 +1216,9 @@ fn get_dir_size(path: &std::path::Path) -> u64 {
 }
 
 #[tauri::command]
-pub async fn kernel_open_releases_page() -> Result<(), String> {
-    open::that("https://github.com/SagerNet/sing-box/releases").map_err(|e| e.to_string())
+pub async fn kernel_open_releases_page(state: State<'_, AppState>) -> Result<(), String> {
+    let page = state.settings.lock().await.kernel_source.releases_page();
+    open::that(page).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -0,0 +1,82 @@
+use tauri::State;
+use crate::state::AppState;
+
+// 用于测速的小文件
+const MIRROR_TEST_URL: &str = "https://raw.githubusercontent.com/SagerNet/sing-geosite/rule-set/geosite-private.srs";
+
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct MirrorLatency {
+    pub mirror: String,
+    pub latency: Option<u64>,
+    pub error: Option<String>,
+}
+
+/// 镜像统一保存为以 / 结尾的 http(s) 前缀
+pub(crate) fn normalize_mirror(mirror: &str) -> Result<String, String> {
+    let mirror = mirror.trim();
+    if !mirror.starts_with("https://") && !mirror.starts_with("http://") {
+        return Err(format!("Invalid mirror address: {}", mirror));
+    }
+    if mirror.ends_with('/') {
+        Ok(mirror.to_string())
+    } else {
+        Ok(format!("{}/", mirror))
+    }
+}
+
+#[tauri::command]
+pub async fn mirror_list(state: State<'_, AppState>) -> Result<Vec<String>, String> {
+    Ok(state.settings.lock().await.github_mirrors.clone())
+}
+
+#[tauri::command]
+pub async fn mirror_add(state: State<'_, AppState>, mirror: String) -> Result<Vec<String>, String> {
+    let mirror = normalize_mirror(&mirror)?;
+    let mut settings = state.settings.lock().await.clone();
+    if !settings.github_mirrors.contains(&mirror) {
+        settings.github_mirrors.push(mirror);
+    }
+    let mirrors = settings.github_mirrors.clone();
+    crate::commands::settings::save_settings(&state, settings).await?;
+    Ok(mirrors)
+}
+
+#[tauri::command]
+pub async fn mirror_remove(state: State<'_, AppState>, mirror: String) -> Result<Vec<String>, String> {
+    let mirror = normalize_mirror(&mirror)?;
+    let mut settings = state.settings.lock().await.clone();
+    settings.github_mirrors.retain(|m| m != &mirror);
+    let mirrors = settings.github_mirrors.clone();
+    crate::commands::settings::save_settings(&state, settings).await?;
+    Ok(mirrors)
+}
+
+/// 并发测试直连和各镜像的下载延迟，空字符串代表直连
+#[tauri::command]
+pub async fn mirror_test(state: State<'_, AppState>) -> Result<Vec<MirrorLatency>, String> {
+    let mirrors = state.settings.lock().await.github_mirrors.clone();
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(10))
+        .build()
+        .map_err(|e| e.to_string())?;
+    
+    let candidates = std::iter::once(String::new()).chain(mirrors);
+    let tests = candidates.map(|mirror| {
+        let client = client.clone();
+        async move {
+            let url = format!("{}{}", mirror, MIRROR_TEST_URL);
+            let start = std::time::Instant::now();
+            let result = match client.get(&url).send().await {
+                Ok(resp) if resp.status().is_success() => resp.bytes().await.map(|_| ()).map_err(|e| e.to_string()),
+                Ok(resp) => Err(format!("HTTP {}", resp.status())),
+                Err(e) => Err(e.to_string()),
+            };
+            match result {
+                Ok(_) => MirrorLatency { mirror, latency: Some(start.elapsed().as_millis() as u64), error: None },
+                Err(e) => MirrorLatency { mirror, latency: None, error: Some(e) },
+            }
+        }
+    });
+    
+    Ok(futures::future::join_all(tests).await)
+}
@@ -1,13 +1,48 @@
 pub mod settings;
 pub mod profiles;
 pub mod rulesets;
+pub mod rules;
 pub mod singbox;
 pub mod window;
 pub mod kernel;
+pub mod updater;
+pub mod mirrors;
+pub mod network;
+pub mod uwp;
+pub mod terminal;
+pub mod backup;
+pub mod hotkeys;
+pub mod workspace;
+pub mod session;
+pub mod deeplink;
+pub mod theme;
+pub mod quota;
+pub mod speedtest;
+pub mod failover;
+pub mod selection;
+pub mod unlock;
+pub mod udptest;
 
 pub use settings::*;
 pub use profiles::*;
 pub use rulesets::*;
+pub use rules::*;
 pub use singbox::*;
 pub use window::*;
 pub use kernel::*;
+pub use updater::*;
+pub use mirrors::*;
+pub use network::*;
+pub use uwp::*;
+pub use terminal::*;
+pub use backup::*;
+pub use workspace::*;
+pub use session::*;
+pub use deeplink::*;
+pub use theme::*;
+pub use quota::*;
+pub use speedtest::*;
+pub use failover::*;
+pub use selection::*;
+pub use unlock::*;
+pub use udptest::*;
@@ -0,0 +1,636 @@
+use tauri::{AppHandle, Emitter, Manager};
+use std::fs;
+use crate::adapter_dns::AdapterDns;
+use crate::state::AppState;
+use crate::types::{NetworkProfile, ProxyState};
+
+#[cfg(windows)]
+const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+/// TUN 网卡名称与其 DNS 地址，需要与生成的 TUN 入站保持一致
+pub(crate) const TUN_INTERFACE_NAME: &str = "KunBox";
+pub(crate) const TUN_DNS_SERVER: &str = "172.19.0.2";
+
+/// 收不到网卡变化通知时的轮询间隔
+const NETWORK_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5);
+/// 能收到通知时只按这个间隔醒来检查是否刚从睡眠中唤醒
+const RESUME_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+/// 收到变化后等这么久再读取默认网卡，两次读到相同结果才认为切换完成
+const NETWORK_SETTLE_DELAY: std::time::Duration = std::time::Duration::from_millis(1500);
+/// 系统回调中通知监听任务网卡发生了变化
+static NETWORK_CHANGED: once_cell::sync::Lazy<tokio::sync::Notify> = once_cell::sync::Lazy::new(tokio::sync::Notify::new);
+/// 内核刚启动时网卡可能还在变化（如创建 TUN），这段时间内不因网络变化重启
+const NETWORK_RESTART_GRACE_MS: u64 = 15_000;
+
+/// 当前默认出口网卡的索引，没有可用网络时返回 None
+fn default_interface_index() -> Option<u32> {
+    #[cfg(windows)]
+    {
+        use windows::Win32::NetworkManagement::IpHelper::GetBestInterface;
+        // 目标地址按网络字节序传入
+        let dest = u32::from_ne_bytes([8, 8, 8, 8]);
+        let mut index = 0u32;
+        let result = unsafe { GetBestInterface(dest, &mut index) };
+        (result == 0).then_some(index)
+    }
+    #[cfg(not(windows))]
+    None
+}
+
+/// 注册 NotifyIpInterfaceChange，网卡增删或连接状态变化时唤醒监听任务；注册失败时返回 false，改为轮询
+fn subscribe_interface_changes() -> bool {
+    #[cfg(windows)]
+    {
+        use windows::Win32::Foundation::HANDLE;
+        use windows::Win32::NetworkManagement::IpHelper::{NotifyIpInterfaceChange, MIB_IPINTERFACE_ROW, MIB_NOTIFICATION_TYPE};
+        use windows::Win32::Networking::WinSock::AF_UNSPEC;
+
+        unsafe extern "system" fn on_change(_context: *const std::ffi::c_void, _row: *const MIB_IPINTERFACE_ROW, _kind: MIB_NOTIFICATION_TYPE) {
+            NETWORK_CHANGED.notify_one();
+        }
+
+        // 通知在应用退出前一直有效，句柄无需注销
+        let mut handle = HANDLE::default();
+        let result = unsafe { NotifyIpInterfaceChange(AF_UNSPEC, Some(on_change), None, false, &mut handle) };
+        if result.is_err() {
+            log::warn!("NotifyIpInterfaceChange failed ({:?}), polling for network changes", result);
+            return false;
+        }
+        true
+    }
+    #[cfg(not(windows))]
+    false
+}
+
+/// TUN 模式连接后默认路由会指向内核创建的 TUN 网卡，它不代表用户所在的网络
+fn is_tun_interface(index: Option<u32>) -> bool {
+    index.and_then(crate::adapter_dns::adapter_by_index)
+        .is_some_and(|(_, alias)| alias.eq_ignore_ascii_case(TUN_INTERFACE_NAME))
+}
+
+/// Windows NCSI 使用的探测地址，能访问且内容正确才认为网络可用
+const CONNECTIVITY_PROBE_URL: &str = "http://www.msftconnecttest.com/connecttest.txt";
+const CONNECTIVITY_PROBE_BODY: &str = "Microsoft Connect Test";
+const NETWORK_WAIT_RETRY: std::time::Duration = std::time::Duration::from_secs(3);
+const NETWORK_WAIT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(120);
+
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Connectivity {
+    Online,
+    /// 能连上但内容被改写，通常是需要网页登录的公共 Wi-Fi
+    CaptivePortal,
+    Offline,
+}
+
+/// 直连探测网络状态；不走系统代理，避免探测到的是本程序自己的代理
+pub(crate) async fn probe_connectivity() -> Connectivity {
+    let client = match reqwest::Client::builder()
+        .no_proxy()
+        .timeout(std::time::Duration::from_secs(5))
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+    {
+        Ok(client) => client,
+        Err(_) => return Connectivity::Offline,
+    };
+    match client.get(CONNECTIVITY_PROBE_URL).send().await {
+        Ok(resp) if resp.status().is_success() => match resp.text().await {
+            Ok(body) if body.trim() == CONNECTIVITY_PROBE_BODY => Connectivity::Online,
+            _ => Connectivity::CaptivePortal,
+        },
+        Ok(_) => Connectivity::CaptivePortal,
+        Err(_) => Connectivity::Offline,
+    }
+}
+
+/// 等待网络可用，期间向前端报告当前状态；超时返回 false
+pub(crate) async fn wait_for_network(app: &AppHandle) -> bool {
+    let deadline = tokio::time::Instant::now() + NETWORK_WAIT_TIMEOUT;
+    let mut last = None;
+    loop {
+        let connectivity = probe_connectivity().await;
+        if connectivity == Connectivity::Online {
+            if last.is_some() {
+                let _ = app.emit("network:online", ());
+            }
+            return true;
+        }
+        if last != Some(connectivity) {
+            log::info!("Waiting for network: {:?}", connectivity);
+            let _ = app.emit("network:waiting", connectivity);
+            last = Some(connectivity);
+        }
+        if tokio::time::Instant::now() >= deadline {
+            return false;
+        }
+        tokio::time::sleep(NETWORK_WAIT_RETRY).await;
+    }
+}
+
+#[tauri::command]
+pub async fn network_check_connectivity() -> Result<Connectivity, String> {
+    Ok(probe_connectivity().await)
+}
+
+/// 当前连接的 Wi-Fi 名称（netsh wlan show interfaces），未连接 Wi-Fi 时为 None
+async fn current_ssid() -> Option<String> {
+    #[cfg(windows)]
+    {
+        let output = tokio::process::Command::new("netsh")
+            .args(["wlan", "show", "interfaces"])
+            .creation_flags(CREATE_NO_WINDOW)
+            .output()
+            .await
+            .ok()?;
+        String::from_utf8_lossy(&output.stdout).lines()
+            .map(|line| line.trim())
+            // 跳过同样以 SSID 结尾的 BSSID 行
+            .filter(|line| line.starts_with("SSID"))
+            .find_map(|line| line.split_once(':').map(|(_, ssid)| ssid.trim().to_string()))
+            .filter(|ssid| !ssid.is_empty())
+    }
+
+    #[cfg(not(windows))]
+    None
+}
+
+/// 找到与当前网络匹配的第一个网络配置；配置了 SSID 和网卡时两者都需匹配
+async fn match_network_profile(profiles: &[NetworkProfile], interface_index: Option<u32>) -> Option<NetworkProfile> {
+    let enabled: Vec<_> = profiles.iter().filter(|p| p.enabled).collect();
+    if enabled.is_empty() {
+        return None;
+    }
+    let ssid = if enabled.iter().any(|p| p.ssid.is_some()) { current_ssid().await } else { None };
+    let adapter = interface_index.and_then(crate::adapter_dns::adapter_by_index);
+
+    enabled.into_iter()
+        .find(|p| {
+            let ssid_matches = p.ssid.as_ref().map(|s| ssid.as_ref() == Some(s)).unwrap_or(true);
+            let adapter_matches = p.adapter.as_ref()
+                .map(|a| adapter.as_ref().is_some_and(|(guid, alias)| guid.eq_ignore_ascii_case(a.trim_matches(['{', '}'])) || alias.eq_ignore_ascii_case(a)))
+                .unwrap_or(true);
+            ssid_matches && adapter_matches
+        })
+        .cloned()
+}
+
+/// 网络配置被编辑后更新当前命中的那一份，使其覆盖项（如 systemProxy）立即生效；被删除或停用时视为未命中
+pub(crate) async fn refresh_network_profile(state: &AppState, profiles: &[NetworkProfile]) {
+    let mut current = state.network_profile.lock().await;
+    if let Some(id) = current.as_ref().map(|p| p.id.clone()) {
+        *current = profiles.iter().find(|p| p.id == id && p.enabled).cloned();
+    }
+}
+
+/// 网络变化后重新匹配网络配置并应用；返回 true 表示已经重启或启动了内核
+async fn apply_network_profile(app: &AppHandle, interface_index: Option<u32>) -> bool {
+    let state = app.state::<AppState>();
+    let profiles = state.settings.lock().await.network_profiles.clone();
+    let matched = match_network_profile(&profiles, interface_index).await;
+
+    let previous = std::mem::replace(&mut *state.network_profile.lock().await, matched.clone());
+    if previous.as_ref().map(|p| &p.id) == matched.as_ref().map(|p| &p.id) {
+        return false;
+    }
+    let Some(profile) = matched else { return false };
+
+    log::info!("Network profile matched: {}", profile.name);
+    let _ = app.emit("network:profile", &profile);
+
+    let connected = matches!(*state.proxy_state.lock().await, ProxyState::Connected);
+    let mut switched_profile = false;
+    if let Some(ref profile_id) = profile.profile_id {
+        let active = state.profiles_data.lock().await.active_profile_id.clone();
+        if active.as_ref() != Some(profile_id) {
+            match crate::commands::profile_set_active(state.clone(), profile_id.clone()).await {
+                Ok(_) => switched_profile = true,
+                Err(e) => log::warn!("Failed to switch to profile {} for network {}: {}", profile_id, profile.name, e),
+            }
+        }
+    }
+
+    if connected {
+        if switched_profile {
+            if let Err(e) = crate::commands::singbox_restart(app.clone(), state.clone()).await {
+                log::error!("Failed to restart core for network profile: {}", e);
+            }
+            return true;
+        }
+        if let Some(enabled) = profile.system_proxy {
+            if let Err(e) = crate::commands::singbox::apply_system_proxy(app, &state, enabled).await {
+                log::warn!("Failed to apply system proxy for network profile: {}", e);
+            }
+        }
+        return false;
+    }
+
+    if profile.auto_connect == Some(true) && matches!(*state.proxy_state.lock().await, ProxyState::Idle) {
+        if let Err(e) = crate::commands::singbox_start(app.clone(), state.clone()).await {
+            log::warn!("Auto connect for network profile failed: {}", e);
+        }
+        return true;
+    }
+    false
+}
+
+/// 当前网络信息，供前端创建网络配置时选择
+#[tauri::command]
+pub async fn network_current(state: tauri::State<'_, AppState>) -> Result<serde_json::Value, String> {
+    let adapter = default_interface_index().and_then(crate::adapter_dns::adapter_by_index);
+    Ok(serde_json::json!({
+        "ssid": current_ssid().await,
+        "adapter": adapter.map(|(guid, alias)| serde_json::json!({ "guid": guid, "alias": alias })),
+        "profile": *state.network_profile.lock().await,
+    }))
+}
+
+/// 读取默认路由（含网卡跃点数）和 TUN 网卡状态
+#[cfg(windows)]
+async fn query_routes_and_tun() -> Result<serde_json::Value, String> {
+    let script = format!(
+        "$routes = Get-NetRoute -DestinationPrefix '0.0.0.0/0','::/0' -ErrorAction SilentlyContinue | \
+         Select-Object DestinationPrefix,ifIndex,InterfaceAlias,NextHop,RouteMetric,\
+         @{{n='InterfaceMetric';e={{(Get-NetIPInterface -InterfaceIndex $_.ifIndex -AddressFamily $_.AddressFamily).InterfaceMetric}}}}; \
+         $tun = Get-NetAdapter -Name '{}' -ErrorAction SilentlyContinue | Select-Object Name,Status,ifIndex,MtuSize,LinkSpeed; \
+         @{{routes=@($routes);tun=$tun}} | ConvertTo-Json -Depth 4 -Compress",
+        TUN_INTERFACE_NAME
+    );
+    powershell_json(&script).await
+}
+
+#[cfg(windows)]
+async fn powershell_json(script: &str) -> Result<serde_json::Value, String> {
+    let output = tokio::process::Command::new("powershell")
+        .args(["-NoProfile", "-NonInteractive", "-Command", script])
+        .creation_flags(CREATE_NO_WINDOW)
+        .output()
+        .await
+        .map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+    serde_json::from_slice(&output.stdout).map_err(|e| e.to_string())
+}
+
+/// 网络诊断：默认路由、TUN 网卡状态、网卡跃点数，以及内核 auto_detect_interface 是否选中了预期的物理网卡
+#[tauri::command]
+pub async fn network_diagnose(state: tauri::State<'_, AppState>) -> Result<serde_json::Value, String> {
+    #[cfg(windows)]
+    let info = query_routes_and_tun().await?;
+    #[cfg(not(windows))]
+    let info = serde_json::json!({ "routes": [], "tun": null });
+
+    let routes = info["routes"].as_array().cloned().unwrap_or_default();
+    // 预期出口：除 TUN 外总跃点数最小的 IPv4 默认路由
+    let expected = routes.iter()
+        .filter(|r| r["DestinationPrefix"] == "0.0.0.0/0" && r["InterfaceAlias"] != TUN_INTERFACE_NAME)
+        .min_by_key(|r| r["RouteMetric"].as_u64().unwrap_or(0) + r["InterfaceMetric"].as_u64().unwrap_or(0))
+        .and_then(|r| r["InterfaceAlias"].as_str())
+        .map(|a| a.to_string());
+    let kernel_interface = state.kernel_default_interface.lock().await.clone();
+    let picked_expected = match (&expected, &kernel_interface) {
+        (Some(expected), Some(kernel)) => Some(expected.eq_ignore_ascii_case(kernel)),
+        _ => None,
+    };
+    let best_interface = default_interface_index().and_then(|index| {
+        crate::adapter_dns::adapter_by_index(index).map(|(guid, alias)| serde_json::json!({ "index": index, "guid": guid, "alias": alias }))
+    });
+
+    Ok(serde_json::json!({
+        "proxyState": *state.proxy_state.lock().await,
+        "tunEnabled": state.settings.lock().await.tun_enabled,
+        "defaultRoutes": routes,
+        "bestInterface": best_interface,
+        "tunAdapter": info["tun"],
+        "expectedInterface": expected,
+        "kernelInterface": kernel_interface,
+        "kernelPickedExpected": picked_expected,
+        "connectivity": probe_connectivity().await,
+    }))
+}
+
+/// Windows 移动热点的默认网关地址
+const MOBILE_HOTSPOT_ADDRESS: &str = "192.168.137.1";
+
+/// 本机局域网 IPv4 地址，排除回环、APIPA 和 TUN 网卡
+#[cfg(windows)]
+async fn query_lan_addresses() -> Result<Vec<serde_json::Value>, String> {
+    let script = format!(
+        "@(Get-NetIPAddress -AddressFamily IPv4 -ErrorAction SilentlyContinue | \
+         Where-Object {{ $_.InterfaceAlias -ne '{}' -and $_.IPAddress -notlike '127.*' -and $_.IPAddress -notlike '169.254.*' }} | \
+         Select-Object IPAddress,PrefixLength,InterfaceAlias,\
+         @{{n='Description';e={{(Get-NetAdapter -InterfaceIndex $_.ifIndex -ErrorAction SilentlyContinue).InterfaceDescription}}}}) | \
+         ConvertTo-Json -Compress",
+        TUN_INTERFACE_NAME
+    );
+    // 只有一个地址时 ConvertTo-Json 输出单个对象而不是数组
+    Ok(match powershell_json(&script).await? {
+        serde_json::Value::Array(items) => items,
+        serde_json::Value::Null => Vec::new(),
+        other => vec![other],
+    })
+}
+
+/// 入站监听地址：IP 原样使用，网卡名称解析为其第一个 IPv4 地址（WSL / Hyper-V 虚拟网卡的地址每次开机都可能变化）
+pub(crate) async fn resolve_listen_address(target: &str) -> Option<String> {
+    if target.parse::<std::net::IpAddr>().is_ok() {
+        return Some(target.to_string());
+    }
+    #[cfg(windows)]
+    {
+        query_lan_addresses().await.ok()?.iter()
+            .find(|item| item["InterfaceAlias"].as_str().is_some_and(|a| a.eq_ignore_ascii_case(target)))
+            .and_then(|item| item["IPAddress"].as_str())
+            .map(|a| a.to_string())
+    }
+    #[cfg(not(windows))]
+    None
+}
+
+/// 可供入站绑定的本机网卡及地址
+#[tauri::command]
+pub async fn network_list_bind_addresses() -> Result<Vec<serde_json::Value>, String> {
+    #[cfg(windows)]
+    let items = query_lan_addresses().await?;
+    #[cfg(not(windows))]
+    let items: Vec<serde_json::Value> = Vec::new();
+
+    Ok(items.iter()
+        .map(|item| serde_json::json!({
+            "address": item["IPAddress"],
+            "interfaceAlias": item["InterfaceAlias"],
+            "description": item["Description"],
+        }))
+        .collect())
+}
+
+/// 局域网共享信息：本机地址、供其他设备扫描的二维码内容，以及移动热点开启时建议的监听地址
+#[tauri::command]
+pub async fn network_lan_share(state: tauri::State<'_, AppState>) -> Result<serde_json::Value, String> {
+    let (allow_lan, http_port, socks_port) = {
+        let settings = state.settings.lock().await;
+        (settings.allow_lan, settings.local_port, settings.socks_port)
+    };
+    if !allow_lan {
+        return Err("Allow LAN is disabled".to_string());
+    }
+
+    #[cfg(windows)]
+    let items = query_lan_addresses().await?;
+    #[cfg(not(windows))]
+    let items: Vec<serde_json::Value> = Vec::new();
+
+    let addresses: Vec<serde_json::Value> = items.iter()
+        .filter_map(|item| {
+            let address = item["IPAddress"].as_str()?;
+            // 移动热点使用 Wi-Fi Direct 虚拟网卡，地址固定为 192.168.137.1
+            let hotspot = address == MOBILE_HOTSPOT_ADDRESS
+                || item["Description"].as_str().is_some_and(|d| d.contains("Wi-Fi Direct Virtual Adapter"));
+            Some(serde_json::json!({
+                "address": address,
+                "prefixLength": item["PrefixLength"],
+                "interfaceAlias": item["InterfaceAlias"],
+                "hotspot": hotspot,
+                "socks": format!("socks://{}:{}", address, socks_port),
+                "http": format!("http://{}:{}", address, http_port),
+            }))
+        })
+        .collect();
+
+    let hotspot = addresses.iter().find(|a| a["hotspot"] == true);
+    let suggested = hotspot.or_else(|| addresses.first());
+
+    Ok(serde_json::json!({
+        "addresses": addresses,
+        "hotspotActive": hotspot.is_some(),
+        "suggestedAddress": suggested.map(|a| a["address"].clone()),
+        "qrPayload": suggested.map(|a| a["socks"].clone()),
+    }))
+}
+
+/// 启动时的自动连接：等网络可用后再启动内核和系统代理。
+/// 上次退出或崩溃时仍在连接的会话按 resumeSession 自动恢复，或通知前端询问
+pub fn start_auto_connect(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let state = app.state::<AppState>();
+        let (auto_connect, resume_session, has_network_profiles) = {
+            let settings = state.settings.lock().await;
+            (settings.auto_connect, settings.resume_session.clone(), !settings.network_profiles.is_empty())
+        };
+        let session = crate::commands::session::load_session(&state).filter(|s| s.connected);
+        let resume = session.is_some() && resume_session == "auto";
+        if let Some(session) = session.as_ref().filter(|_| resume) {
+            if let Err(e) = crate::commands::session::restore_session_selection(&state, session).await {
+                log::warn!("Failed to restore last session: {}", e);
+            }
+        } else {
+            *state.profiles_data.lock().await = crate::commands::profiles::load_profiles_data(&state);
+        }
+        // 自动连接本来就会连上，不必再询问
+        if session.is_some() && !auto_connect && resume_session == "ask" {
+            let _ = app.emit("session:resumable", &session);
+        }
+
+        let auto_connect = auto_connect || resume;
+        if !auto_connect && !has_network_profiles {
+            return;
+        }
+        if state.profiles_data.lock().await.active_profile_id.is_none() {
+            if auto_connect {
+                log::info!("No active profile, skipping auto connect");
+                let _ = app.emit("autoconnect:progress", serde_json::json!({ "stage": "skipped", "reason": crate::i18n::tr("error.noActiveProfile") }));
+            }
+            return;
+        }
+
+        if auto_connect {
+            *state.proxy_state.lock().await = ProxyState::Waiting;
+            let _ = app.emit("singbox:state", "waiting");
+            let _ = app.emit("autoconnect:progress", serde_json::json!({ "stage": "waiting-network" }));
+        }
+        let online = wait_for_network(&app).await;
+
+        // 网络配置可以覆盖全局的自动连接设置
+        if online {
+            apply_network_profile(&app, default_interface_index()).await;
+        }
+        let network_auto_connect = state.network_profile.lock().await.as_ref().and_then(|p| p.auto_connect);
+        if !online || !network_auto_connect.unwrap_or(auto_connect) {
+            if !online {
+                log::warn!("Network not available, skipping auto connect");
+            }
+            if auto_connect {
+                *state.proxy_state.lock().await = ProxyState::Idle;
+                let _ = app.emit("singbox:state", "idle");
+                let reason = if online { "Disabled by network profile" } else { "Network not available" };
+                let _ = app.emit("autoconnect:progress", serde_json::json!({ "stage": "skipped", "reason": reason }));
+            }
+            return;
+        }
+
+        *state.proxy_state.lock().await = ProxyState::Idle;
+        let _ = app.emit("autoconnect:progress", serde_json::json!({ "stage": "starting" }));
+        // 与前端点击连接走同一条路径，系统代理 / WinHTTP / TUN DNS 都在 singbox_start 中处理
+        let error = match crate::commands::singbox_start(app.clone(), state.clone()).await {
+            Ok(result) if !result.success => result.error.or_else(|| Some("Unknown error".to_string())),
+            Err(e) => Some(e),
+            Ok(_) => None,
+        };
+        match error {
+            Some(e) => {
+                log::warn!("Auto connect failed: {}", e);
+                let _ = app.emit("autoconnect:progress", serde_json::json!({ "stage": "failed", "error": e }));
+            }
+            None => {
+                let _ = app.emit("autoconnect:progress", serde_json::json!({ "stage": "connected" }));
+            }
+        }
+    });
+}
+
+/// 监听默认出口网卡变化（Wi-Fi 切换到有线、VPN 连接断开等），连接中时重启内核重新选择出口并恢复系统代理。
+/// 由系统的网卡变化通知驱动，空闲时只为检测睡眠唤醒定期醒来
+pub fn start_network_monitor(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let subscribed = subscribe_interface_changes();
+        let tick = if subscribed { RESUME_CHECK_INTERVAL } else { NETWORK_POLL_INTERVAL };
+        let mut current = default_interface_index();
+
+        loop {
+            let before = std::time::SystemTime::now();
+            tokio::select! {
+                _ = NETWORK_CHANGED.notified() => {}
+                _ = tokio::time::sleep(tick) => {}
+            }
+
+            // 实际经过的时间远超等待上限说明系统刚从睡眠中唤醒
+            let slept = before.elapsed().map(|e| e > tick + std::time::Duration::from_secs(30)).unwrap_or(false);
+            if slept {
+                let state = app.state::<AppState>();
+                if matches!(*state.proxy_state.lock().await, ProxyState::Connected) {
+                    log::info!("Resumed from sleep, waiting for network before restarting core");
+                    if wait_for_network(&app).await {
+                        if let Err(e) = crate::commands::singbox_restart(app.clone(), state.clone()).await {
+                            log::error!("Failed to recover after resume: {}", e);
+                        }
+                    }
+                    let observed = default_interface_index();
+                    if !is_tun_interface(observed) {
+                        current = observed;
+                    }
+                    continue;
+                }
+            }
+
+            let observed = default_interface_index();
+            // 切到或切离 TUN 网卡只是连接 / 断开造成的，current 始终记录物理网卡，
+            // 否则断开后物理网卡重新成为默认出口时会被当作新网络，再次触发网络配置的自动连接
+            if observed == current || is_tun_interface(observed) {
+                continue;
+            }
+            // 切换过程中默认网卡可能来回变化，稍后再读一次相同才算切换完成
+            tokio::time::sleep(NETWORK_SETTLE_DELAY).await;
+            if default_interface_index() != observed {
+                continue;
+            }
+
+            log::info!("Default network interface changed: {:?} -> {:?}", current, observed);
+            let _ = app.emit("network:changed", serde_json::json!({ "previous": current, "current": observed }));
+            current = observed;
+
+            if apply_network_profile(&app, observed).await {
+                continue;
+            }
+
+            let state = app.state::<AppState>();
+            if observed.is_none() || !matches!(*state.proxy_state.lock().await, ProxyState::Connected) {
+                continue;
+            }
+            // TUN 模式下默认路由指向 TUN 网卡，由内核的 auto_detect_interface 自行跟随物理网卡
+            if state.settings.lock().await.tun_enabled {
+                continue;
+            }
+            let started_at = state.start_time.lock().await.unwrap_or(0);
+            if (chrono::Utc::now().timestamp_millis() as u64).saturating_sub(started_at) < NETWORK_RESTART_GRACE_MS {
+                continue;
+            }
+
+            log::info!("Restarting core after network change");
+            if let Err(e) = crate::commands::singbox_restart(app.clone(), state.clone()).await {
+                log::error!("Failed to recover after network change: {}", e);
+            }
+        }
+    });
+}
+
+/// TUN 模式下把 TUN 网卡的 DNS 指向内核；开启 FakeIP 时其他物理网卡也一并改写，避免 DNS 绕过隧道泄漏
+pub(crate) async fn apply_tun_dns(state: &AppState) -> Result<(), String> {
+    let fake_dns = state.settings.lock().await.fake_dns;
+    
+    // TUN 网卡在内核启动后才创建，稍等片刻
+    let mut tun = None;
+    for _ in 0..10 {
+        tun = crate::adapter_dns::find_adapter(TUN_INTERFACE_NAME);
+        if tun.is_some() {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+    }
+    let tun = tun.ok_or_else(|| format!("TUN adapter {} not found", TUN_INTERFACE_NAME))?;
+    
+    let mut targets = vec![tun];
+    if fake_dns {
+        targets.extend(crate::adapter_dns::physical_adapters());
+    }
+    
+    // 已有备份（上次未正常恢复）时保留最早的原始设置
+    let backup_file = state.adapter_dns_backup_file();
+    let mut backup: Vec<AdapterDns> = fs::read_to_string(&backup_file)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default();
+    for (guid, alias) in &targets {
+        if backup.iter().any(|b| &b.guid == guid) {
+            continue;
+        }
+        let name_server = crate::adapter_dns::get_adapter_dns(guid)?;
+        backup.push(AdapterDns { guid: guid.clone(), alias: alias.clone(), name_server });
+    }
+    let content = serde_json::to_string_pretty(&backup).map_err(|e| e.to_string())?;
+    fs::write(&backup_file, content).map_err(|e| e.to_string())?;
+    
+    for (guid, alias) in &targets {
+        crate::adapter_dns::set_adapter_dns(guid, TUN_DNS_SERVER)?;
+        log::info!("Set DNS of adapter {} to {}", alias, TUN_DNS_SERVER);
+    }
+    Ok(())
+}
+
+/// 恢复被修改过的网卡 DNS；失败的网卡保留在备份中，下次停止时重试
+pub(crate) fn restore_adapter_dns(state: &AppState) {
+    let backup_file = state.adapter_dns_backup_file();
+    let Some(backup) = fs::read_to_string(&backup_file)
+        .ok()
+        .and_then(|content| serde_json::from_str::<Vec<AdapterDns>>(&content).ok())
+    else {
+        return;
+    };
+    
+    let remaining: Vec<AdapterDns> = backup.into_iter()
+        .filter(|adapter| match crate::adapter_dns::set_adapter_dns(&adapter.guid, &adapter.name_server) {
+            Ok(_) => false,
+            Err(e) => {
+                log::warn!("Failed to restore DNS of adapter {}: {}", adapter.alias, e);
+                // TUN 网卡随内核退出而消失，无需再恢复
+                adapter.alias != TUN_INTERFACE_NAME
+            }
+        })
+        .collect();
+    
+    if remaining.is_empty() {
+        let _ = fs::remove_file(&backup_file);
+    } else if let Ok(content) = serde_json::to_string_pretty(&remaining) {
+        let _ = fs::write(&backup_file, content);
+    }
+}
@@ -1,10 +1,14 @@
-use tauri::{AppHandle, Manager, State};
+use tauri::{AppHandle, Emitter, State};
+use serde::Serialize;
+use tokio_util::sync::CancellationToken;
 use std::fs;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tokio::sync::Mutex;
 use uuid::Uuid;
+use crate::migrate::{self, DataFile};
 use crate::state::AppState;
-use crate::types::{Profile, ProfilesData, ProxyState, SingBoxOutbound};
+use crate::types::{LatencyStats, NodeScore, Profile, ProfilesData, ProxyState, SingBoxOutbound, SubscriptionInfo};
 
 #[cfg(windows)]
 use std::os::windows::process::CommandExt;
@@ -13,33 +17,40 @@ use std::os::windows::process::CommandExt;
 const CREATE_NO_WINDOW: u32 = 0x08000000;
 
 // Temporary sing-box for latency testing
-static TEMP_SINGBOX_PROCESS: once_cell::sync::Lazy<Arc<Mutex<Option<tokio::process::Child>>>> = 
+static TEMP_SINGBOX_PROCESS: once_cell::sync::Lazy<Arc<Mutex<Option<TempSingbox>>>> = 
     once_cell::sync::Lazy::new(|| Arc::new(Mutex::new(None)));
 const TEMP_SINGBOX_PORT: u16 = 19090;
+/// 每个节点保留的延迟历史条数
+const LATENCY_HISTORY_LIMIT: usize = 20;
+/// 临时内核空闲这么久没有测速就结束，释放端口和内存
+const TEMP_SINGBOX_IDLE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(120);
+/// 每次启动临时内核递增，空闲检查任务据此判断自己负责的内核是否已被替换
+static TEMP_SINGBOX_GENERATION: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
 
-fn load_profiles_data(state: &AppState) -> ProfilesData {
-    let file = state.profiles_file();
-    if file.exists() {
-        if let Ok(content) = fs::read_to_string(&file) {
-            if let Ok(data) = serde_json::from_str(&content) {
-                return data;
-            }
-        }
-    }
-    ProfilesData::default()
+struct TempSingbox {
+    child: tokio::process::Child,
+    config_path: PathBuf,
+    /// 启动时使用的配置内容，节点或当前配置变化后据此判断需要重新生成
+    config: String,
+    last_used: std::time::Instant,
+    generation: u64,
+}
+
+pub(crate) fn load_profiles_data(state: &AppState) -> ProfilesData {
+    migrate::load(&state.profiles_file(), DataFile::Profiles).unwrap_or_default()
 }
 
-fn save_profiles_data(state: &AppState, data: &ProfilesData) -> Result<(), String> {
-    fs::create_dir_all(&state.data_dir).map_err(|e| e.to_string())?;
-    let content = serde_json::to_string_pretty(data).map_err(|e| e.to_string())?;
-    fs::write(state.profiles_file(), content).map_err(|e| e.to_string())?;
+pub(crate) fn save_profiles_data(state: &AppState, data: &ProfilesData) -> Result<(), String> {
+    fs::create_dir_all(state.workspace_dir()).map_err(|e| e.to_string())?;
+    migrate::save(&state.profiles_file(), DataFile::Profiles, data, state.encrypt_data())?;
+    state.tray_refresh.notify_one();
     Ok(())
 }
 
-fn load_profile_nodes(state: &AppState, profile_id: &str) -> Vec<SingBoxOutbound> {
+pub(crate) fn load_profile_nodes(state: &AppState, profile_id: &str) -> Vec<SingBoxOutbound> {
     let file = state.configs_dir().join(format!("{}.json", profile_id));
     if file.exists() {
-        if let Ok(content) = fs::read_to_string(&file) {
+        if let Ok(content) = crate::dpapi::read_data_file(&file) {
             if let Ok(nodes) = serde_json::from_str(&content) {
                 return nodes;
             }
@@ -48,10 +59,10 @@ fn load_profile_nodes(state: &AppState, profile_id: &str) -> Vec<SingBoxOutbound
     Vec::new()
 }
 
-fn load_profile_nodes_raw(state: &AppState, profile_id: &str) -> Vec<serde_json::Value> {
+pub(crate) fn load_profile_nodes_raw(state: &AppState, profile_id: &str) -> Vec<serde_json::Value> {
     let file = state.configs_dir().join(format!("{}.json", profile_id));
     if file.exists() {
-        if let Ok(content) = fs::read_to_string(&file) {
+        if let Ok(content) = crate::dpapi::read_data_file(&file) {
             if let Ok(nodes) = serde_json::from_str(&content) {
                 return nodes;
             }
@@ -64,7 +75,23 @@ fn save_profile_nodes(state: &AppState, profile_id: &str, nodes: &[SingBoxOutbou
     fs::create_dir_all(state.configs_dir()).map_err(|e| e.to_string())?;
     let file = state.configs_dir().join(format!("{}.json", profile_id));
     let content = serde_json::to_string_pretty(nodes).map_err(|e| e.to_string())?;
-    fs::write(file, content).map_err(|e| e.to_string())?;
+    crate::dpapi::write_data_file(&file, &content, state.encrypt_data())
+}
+
+/// 切换 encryptData 后按新设置重写 profiles.json 和全部节点文件
+pub(crate) fn rewrite_encrypted_files(state: &AppState) -> Result<(), String> {
+    let encrypt = state.encrypt_data();
+    let mut files = vec![state.profiles_file()];
+    if let Ok(entries) = fs::read_dir(state.configs_dir()) {
+        files.extend(entries.flatten().map(|e| e.path()).filter(|p| p.extension().is_some_and(|e| e == "json")));
+    }
+    for file in files.iter().filter(|f| f.is_file()) {
+        if crate::dpapi::is_encrypted_file(file) == encrypt {
+            continue;
+        }
+        let content = crate::dpapi::read_data_file(file)?;
+        crate::dpapi::write_data_file(file, &content, encrypt)?;
+    }
     Ok(())
 }
 
@@ -84,7 +111,7 @@ pub async fn profile_add(
     dns_pre_resolve: Option<bool>,
     dns_server: Option<String>,
 ) -> Result<Profile, String> {
-    let nodes = fetch_subscription(&url).await?;
+    let (nodes, subscription) = fetch_subscription(&url).await?;
     
     let profile = Profile {
         id: Uuid::new_v4().to_string(),
@@ -96,6 +123,8 @@ pub async fn profile_add(
         auto_update_interval: auto_update_interval.unwrap_or(0),
         dns_pre_resolve: dns_pre_resolve.unwrap_or(false),
         dns_server,
+        subscription,
+        quota_alert: Default::default(),
     };
 
     save_profile_nodes(&state, &profile.id, &nodes)?;
@@ -108,6 +137,7 @@ pub async fn profile_add(
     data.profiles.push(profile.clone());
     save_profiles_data(&state, &data)?;
     *state.profiles_data.lock().await = data;
+    crate::commands::quota::request_quota_check();
 
     Ok(profile)
 }
@@ -119,16 +149,20 @@ pub async fn profile_update(state: State<'_, AppState>, id: String) -> Result<Pr
         .ok_or("Profile not found")?;
 
     let url = data.profiles[profile_idx].url.clone();
-    let nodes = fetch_subscription(&url).await?;
+    let (nodes, subscription) = fetch_subscription(&url).await?;
     
     data.profiles[profile_idx].last_update = Some(chrono::Utc::now().timestamp_millis() as u64);
     data.profiles[profile_idx].node_count = nodes.len() as u32;
+    if subscription.is_some() {
+        data.profiles[profile_idx].subscription = subscription;
+    }
     
     save_profile_nodes(&state, &id, &nodes)?;
     save_profiles_data(&state, &data)?;
     
     let profile = data.profiles[profile_idx].clone();
     *state.profiles_data.lock().await = data;
+    crate::commands::quota::request_quota_check();
     Ok(profile)
 }
 
@@ -163,6 +197,8 @@ pub async fn profile_set_active(state: State<'_, AppState>, id: String) -> Resul
     
     save_profiles_data(&state, &data)?;
     *state.profiles_data.lock().await = data;
+    // 临时内核里还是旧配置的节点，下次测速时按新配置重新生成
+    stop_temp_singbox().await;
     Ok(())
 }
 
@@ -209,14 +245,27 @@ pub async fn profile_set_enabled(state: State<'_, AppState>, id: String, enabled
     Ok(())
 }
 
+/// 节点列表项：节点本身的字段加上根据延迟历史得出的稳定性评分
+#[derive(Debug, Clone, Serialize)]
+pub struct NodeListItem {
+    #[serde(flatten)]
+    pub node: SingBoxOutbound,
+    pub score: Option<NodeScore>,
+}
+
 #[tauri::command]
-pub async fn node_list(state: State<'_, AppState>) -> Result<Vec<SingBoxOutbound>, String> {
+pub async fn node_list(state: State<'_, AppState>) -> Result<Vec<NodeListItem>, String> {
     let data = load_profiles_data(&state);
-    if let Some(id) = data.active_profile_id {
-        Ok(load_profile_nodes(&state, &id))
-    } else {
-        Ok(Vec::new())
-    }
+    let Some(id) = data.active_profile_id else {
+        return Ok(Vec::new());
+    };
+    let scores = node_scores(&state).await;
+    Ok(load_profile_nodes(&state, &id).into_iter()
+        .map(|node| {
+            let score = node.tag.as_ref().and_then(|tag| scores.get(tag).cloned());
+            NodeListItem { node, score }
+        })
+        .collect())
 }
 
 #[tauri::command]
@@ -256,16 +305,39 @@ pub async fn node_delete(state: State<'_, AppState>, tag: String) -> Result<(),
     Ok(())
 }
 
-async fn fetch_subscription(url: &str) -> Result<Vec<SingBoxOutbound>, String> {
+async fn fetch_subscription(url: &str) -> Result<(Vec<SingBoxOutbound>, Option<SubscriptionInfo>), String> {
     let client = reqwest::Client::builder()
         .timeout(std::time::Duration::from_secs(30))
         .build()
         .map_err(|e| e.to_string())?;
 
     let response = client.get(url).send().await.map_err(|e| e.to_string())?;
+    let subscription = response.headers().get("subscription-userinfo")
+        .and_then(|v| v.to_str().ok())
+        .and_then(parse_subscription_userinfo);
     let content = response.text().await.map_err(|e| e.to_string())?;
     
-    parse_subscription_content(&content)
+    Ok((parse_subscription_content(&content)?, subscription))
+}
+
+/// 解析 upload=123; download=456; total=789; expire=1700000000，一个字段都没有时视为未提供
+fn parse_subscription_userinfo(header: &str) -> Option<SubscriptionInfo> {
+    let mut info = SubscriptionInfo::default();
+    let mut found = false;
+    for (key, value) in header.split(';').filter_map(|part| part.split_once('=')) {
+        // 部分机场会下发小数或科学计数法
+        let Ok(value) = value.trim().parse::<f64>() else { continue };
+        let value = value.max(0.0) as u64;
+        match key.trim() {
+            "upload" => info.upload = value,
+            "download" => info.download = value,
+            "total" => info.total = value,
+            "expire" => info.expire = (value > 0).then_some(value),
+            _ => continue,
+        }
+        found = true;
+    }
+    found.then_some(info)
 }
 
 fn parse_subscription_content(content: &str) -> Result<Vec<SingBoxOutbound>, String> {
@@ -543,97 +615,332 @@ fn extract_hostname(url: &str) -> String {
         .unwrap_or_else(|_| "Unknown".to_string())
 }
 
+/// 测试方式不适用于该节点时（如 TCP 测 UDP 协议）返回 None；只有走代理的结果会保存
 #[tauri::command]
-pub async fn node_test_latency(app: AppHandle, state: State<'_, AppState>, tag: String) -> Result<i64, String> {
-    // Check if main VPN is running
-    let is_vpn_running = {
-        let proxy_state = state.proxy_state.lock().await;
-        matches!(*proxy_state, ProxyState::Connected)
-    };
-    
-    if is_vpn_running {
-        // Use main sing-box Clash API
-        test_latency_via_clash_api(&tag, 9090).await
-    } else {
-        // Start temp sing-box if needed
-        let started = start_temp_singbox(&app, &state).await;
-        if !started {
-            return Ok(-1);
+pub async fn node_test_latency(app: AppHandle, state: State<'_, AppState>, tag: String, method: Option<String>) -> Result<Option<i64>, String> {
+    let (probe, stats) = test_node_latency(&app, &state, &tag, method.as_deref()).await?;
+    let Some(stats) = stats else { return Ok(None) };
+    let latency = stats.latency();
+    if probe.is_proxy_delay() {
+        record_latency(&state, tag, stats).await;
+    }
+    Ok(Some(latency))
+}
+
+/// 保存测速结果并刷新托盘和任务栏角标
+async fn record_latency(state: &AppState, tag: String, stats: LatencyStats) {
+    record_latency_sample(state, &tag, stats.latency()).await;
+    state.node_latency_stats.lock().await.insert(tag, stats);
+}
+
+/// 保存一次测试的延迟并追加到历史，超出 LATENCY_HISTORY_LIMIT 的旧记录丢弃
+pub(crate) async fn record_latency_sample(state: &AppState, tag: &str, latency: i64) {
+    state.node_latency.lock().await.insert(tag.to_string(), latency);
+    let mut history = state.node_latency_history.lock().await;
+    let samples = history.entry(tag.to_string()).or_default();
+    samples.push_back(latency);
+    while samples.len() > LATENCY_HISTORY_LIMIT {
+        samples.pop_front();
+    }
+    drop(history);
+    state.tray_refresh.notify_one();
+}
+
+/// urltest 的容差：取历史足够的节点延迟标准差的中位数，节点间的差距在正常波动内时不来回切换
+pub(crate) async fn urltest_tolerance(state: &AppState) -> i64 {
+    const DEFAULT_TOLERANCE: i64 = 50;
+    let mut stddevs: Vec<i64> = node_scores(state).await.into_values()
+        .filter(|score| score.samples >= 3 && score.stddev >= 0)
+        .map(|score| score.stddev)
+        .collect();
+    if stddevs.is_empty() {
+        return DEFAULT_TOLERANCE;
+    }
+    stddevs.sort_unstable();
+    stddevs[stddevs.len() / 2].clamp(DEFAULT_TOLERANCE, 300)
+}
+
+/// 各节点根据延迟历史得出的稳定性评分
+pub(crate) async fn node_scores(state: &AppState) -> std::collections::HashMap<String, NodeScore> {
+    state.node_latency_history.lock().await.iter()
+        .filter_map(|(tag, history)| NodeScore::from_history(&history.iter().copied().collect::<Vec<_>>()).map(|score| (tag.clone(), score)))
+        .collect()
+}
+
+/// 各节点最近一次测试的详细结果
+#[tauri::command]
+pub async fn node_latency_stats(state: State<'_, AppState>) -> Result<std::collections::HashMap<String, LatencyStats>, String> {
+    Ok(state.node_latency_stats.lock().await.clone())
+}
+
+/// 延迟测试参数，取自 latencyTestUrl / latencyTestTimeout / latencyTestConcurrency / latencyTestProbes
+#[derive(Debug, Clone)]
+pub(crate) struct LatencyTestOptions {
+    url: String,
+    timeout_ms: u32,
+    concurrency: usize,
+    probes: u32,
+}
+
+impl LatencyTestOptions {
+    pub(crate) async fn from_settings(state: &AppState) -> Self {
+        let settings = state.settings.lock().await;
+        Self {
+            url: settings.latency_test_url.clone(),
+            timeout_ms: settings.latency_test_timeout,
+            concurrency: settings.latency_test_concurrency.max(1) as usize,
+            probes: settings.latency_test_probes.max(1),
         }
-        
+    }
+}
+
+/// 延迟的测量方式
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum LatencyProbe {
+    /// 通过内核的 Clash API 走完整代理握手，参数为 API 端口
+    ClashApi(u16),
+    /// 内核无法启动时直接测到节点服务器的 TCP 连接耗时，只能粗略反映可达性
+    TcpConnect,
+    /// 到节点服务器的 ICMP 往返时间，不含代理握手；只支持 IPv4
+    Icmp,
+}
+
+impl LatencyProbe {
+    fn method(self) -> &'static str {
+        match self {
+            LatencyProbe::ClashApi(_) => "proxy",
+            LatencyProbe::TcpConnect => "tcp",
+            LatencyProbe::Icmp => "icmp",
+        }
+    }
+
+    /// 只有走代理的结果能与其他节点比较；tcp / icmp 只测到服务器，数值偏小且可能被屏蔽，不写入 node_latency 和历史
+    fn is_proxy_delay(self) -> bool {
+        matches!(self, LatencyProbe::ClashApi(_))
+    }
+
+    /// TCP 连接测不了基于 QUIC/UDP 的节点，这类节点不测，免得一律显示超时
+    fn supports(self, node: &SingBoxOutbound) -> bool {
+        self != LatencyProbe::TcpConnect || !is_udp_outbound(node)
+    }
+
+    /// 按 probes 连续测试多次并汇总；不支持该节点时返回 None
+    async fn run(self, node: &SingBoxOutbound, options: &LatencyTestOptions) -> Option<LatencyStats> {
+        if !self.supports(node) {
+            return None;
+        }
+        let uses_temp_singbox = self == LatencyProbe::ClashApi(TEMP_SINGBOX_PORT);
+        let mut samples = Vec::with_capacity(options.probes as usize);
+        for _ in 0..options.probes {
+            // 每次测试前刷新空闲计时，探测次数多、超时长时也不会在测试中途被结束
+            if uses_temp_singbox {
+                touch_temp_singbox().await;
+            }
+            samples.push(self.run_once(node, options).await);
+        }
+        if uses_temp_singbox {
+            touch_temp_singbox().await;
+        }
+        Some(LatencyStats::from_samples(&samples))
+    }
+
+    async fn run_once(self, node: &SingBoxOutbound, options: &LatencyTestOptions) -> i64 {
+        let Some(tag) = node.tag.as_deref() else { return -1 };
+        match self {
+            LatencyProbe::ClashApi(port) => test_latency_via_clash_api(tag, port, options).await.unwrap_or(-1),
+            LatencyProbe::TcpConnect => match (node.server.as_deref(), node.server_port) {
+                (Some(server), Some(port)) => test_latency_via_tcp(server, port, options.timeout_ms).await,
+                _ => -1,
+            },
+            LatencyProbe::Icmp => match node.server.as_deref() {
+                Some(server) => test_latency_via_icmp(server, options.timeout_ms).await,
+                None => -1,
+            },
+        }
+    }
+}
+
+/// method 为 tcp / icmp 时直接测服务器；默认的 proxy 测当前配置且已连接时用主内核，否则用 profile_id 的节点启动临时内核，
+/// 都不可用时退回 TCP 连接测试
+async fn select_latency_probe(app: &AppHandle, state: &AppState, profile_id: &str, method: Option<&str>, startup_wait_ms: u64) -> LatencyProbe {
+    match method {
+        Some("tcp") => return LatencyProbe::TcpConnect,
+        Some("icmp") => return LatencyProbe::Icmp,
+        _ => {}
+    }
+    let is_active = load_profiles_data(state).active_profile_id.as_deref() == Some(profile_id);
+    if is_active && matches!(*state.proxy_state.lock().await, ProxyState::Connected) {
+        return LatencyProbe::ClashApi(9090);
+    }
+    if start_temp_singbox(app, state, profile_id).await {
         // Wait for sing-box to be ready
-        tokio::time::sleep(std::time::Duration::from_millis(300)).await;
-        
-        test_latency_via_clash_api(&tag, TEMP_SINGBOX_PORT).await
+        tokio::time::sleep(std::time::Duration::from_millis(startup_wait_ms)).await;
+        return LatencyProbe::ClashApi(TEMP_SINGBOX_PORT);
     }
+    log::warn!("Latency test kernel unavailable, falling back to TCP connect");
+    LatencyProbe::TcpConnect
 }
 
+async fn test_node_latency(app: &AppHandle, state: &AppState, tag: &str, method: Option<&str>) -> Result<(LatencyProbe, Option<LatencyStats>), String> {
+    let options = LatencyTestOptions::from_settings(state).await;
+    let profile_id = load_profiles_data(state).active_profile_id.unwrap_or_default();
+    let node = load_profile_nodes(state, &profile_id)
+        .into_iter()
+        .find(|n| n.tag.as_deref() == Some(tag))
+        // 节点不在当前配置中时仍按 tag 交给内核测试
+        .unwrap_or_else(|| SingBoxOutbound { tag: Some(tag.to_string()), ..Default::default() });
+    let probe = select_latency_probe(app, state, &profile_id, method, 300).await;
+    Ok((probe, probe.run(&node, &options).await))
+}
+
+/// 批量测试 profile_id（默认当前配置）的全部节点，便于切换前先评估新订阅。每测完一个发送 node:latency，
+/// 结束或取消后发送 node:latency-done；返回已完成的结果，取消时只包含取消前测完的节点，测试方式不支持的节点
+/// 只发送 method 为 unsupported 的事件、不计入结果。
+/// 非当前配置的结果只通过事件和返回值给出，不写入 node_latency，以免同名节点覆盖当前配置的结果；
+/// tcp / icmp 的结果同样只通过事件和返回值给出
 #[tauri::command]
-pub async fn node_test_all(app: AppHandle, state: State<'_, AppState>) -> Result<std::collections::HashMap<String, i64>, String> {
+pub async fn node_test_all(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    method: Option<String>,
+    profile_id: Option<String>,
+) -> Result<std::collections::HashMap<String, i64>, String> {
     let data = load_profiles_data(&state);
-    let profile_id = match data.active_profile_id {
+    let profile_id = match profile_id.or(data.active_profile_id.clone()) {
         Some(id) => id,
         None => return Ok(std::collections::HashMap::new()),
     };
+    if !data.profiles.iter().any(|p| p.id == profile_id) {
+        return Err("Profile not found".to_string());
+    }
+    let is_active = data.active_profile_id.as_deref() == Some(profile_id.as_str());
     
-    let nodes = load_profile_nodes(&state, &profile_id);
-    
-    // Check if main VPN is running
-    let is_vpn_running = {
-        let proxy_state = state.proxy_state.lock().await;
-        matches!(*proxy_state, ProxyState::Connected)
-    };
-    
-    let port = if is_vpn_running {
-        9090
-    } else {
-        // Start temp sing-box if needed
-        let started = start_temp_singbox(&app, &state).await;
-        if !started {
-            return Ok(std::collections::HashMap::new());
-        }
-        tokio::time::sleep(std::time::Duration::from_millis(500)).await;
-        TEMP_SINGBOX_PORT
-    };
+    let nodes: Vec<SingBoxOutbound> = load_profile_nodes(&state, &profile_id).into_iter()
+        .filter(|node| node.tag.is_some())
+        .collect();
+    let probe = select_latency_probe(&app, &state, &profile_id, method.as_deref(), 500).await;
     
-    let mut results = std::collections::HashMap::new();
-    
-    // Test in chunks for concurrency
-    let chunk_size = 5;
-    for chunk in nodes.chunks(chunk_size) {
-        let futures: Vec<_> = chunk.iter()
-            .filter_map(|node| node.tag.clone())
-            .map(|tag| {
-                let tag_clone = tag.clone();
-                async move {
-                    let latency = test_latency_via_clash_api(&tag_clone, port).await.unwrap_or(-1);
-                    (tag_clone, latency)
+    // 新的批量测试开始时取消上一次
+    let cancel = CancellationToken::new();
+    if let Some(previous) = state.latency_test_cancel.lock().await.replace(cancel.clone()) {
+        previous.cancel();
+    }
+
+    // 用信号量限制并发，一个慢节点只占一个名额，不会拖住整批
+    let options = LatencyTestOptions::from_settings(&state).await;
+    let semaphore = Arc::new(tokio::sync::Semaphore::new(options.concurrency));
+    let futures: Vec<_> = nodes.iter()
+        .map(|node| {
+            let semaphore = semaphore.clone();
+            let (app, state, options, cancel, profile_id) = (&app, &state, &options, &cancel, &profile_id);
+            async move {
+                let tag = node.tag.clone().unwrap_or_default();
+                let test = async {
+                    let _permit = semaphore.acquire().await;
+                    probe.run(node, options).await
+                };
+                let stats = tokio::select! {
+                    _ = cancel.cancelled() => return None,
+                    stats = test => stats,
+                };
+                let Some(stats) = stats else {
+                    let _ = app.emit("node:latency", serde_json::json!({
+                        "tag": tag,
+                        "latency": null,
+                        "stats": null,
+                        "method": "unsupported",
+                        "profileId": profile_id,
+                    }));
+                    return None;
+                };
+                let latency = stats.latency();
+                let _ = app.emit("node:latency", serde_json::json!({
+                    "tag": tag,
+                    "latency": latency,
+                    "stats": stats,
+                    "method": probe.method(),
+                    "profileId": profile_id,
+                }));
+                if is_active && probe.is_proxy_delay() {
+                    record_latency(state, tag.clone(), stats).await;
                 }
-            })
-            .collect();
-        
-        let chunk_results = futures::future::join_all(futures).await;
-        for (tag, latency) in chunk_results {
-            results.insert(tag, latency);
-        }
+                Some((tag, latency))
+            }
+        })
+        .collect();
+    let results: std::collections::HashMap<String, i64> = futures::future::join_all(futures).await.into_iter().flatten().collect();
+
+    // 没被取消说明也没有新的测试替换掉它，存着的仍是本次的 token
+    let cancelled = cancel.is_cancelled();
+    if !cancelled {
+        state.latency_test_cancel.lock().await.take();
     }
-    
+    let _ = app.emit("node:latency-done", serde_json::json!({
+        "tested": results.len(),
+        "total": nodes.len(),
+        "cancelled": cancelled,
+        "method": probe.method(),
+        "profileId": profile_id,
+    }));
     Ok(results)
 }
 
-async fn test_latency_via_clash_api(proxy_name: &str, port: u16) -> Result<i64, String> {
+/// 停止进行中的批量延迟测试
+#[tauri::command]
+pub async fn node_test_cancel(state: State<'_, AppState>) -> Result<bool, String> {
+    match state.latency_test_cancel.lock().await.take() {
+        Some(token) => {
+            token.cancel();
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+/// 基于 QUIC/UDP 的协议，或只开了 UDP 的节点
+fn is_udp_outbound(node: &SingBoxOutbound) -> bool {
+    matches!(node.outbound_type.as_deref(), Some("hysteria" | "hysteria2" | "tuic" | "wireguard"))
+        || node.extra.get("network").and_then(|v| v.as_str()) == Some("udp")
+}
+
+/// 解析后只计 TCP 握手的耗时，超时或连接失败为 -1
+async fn test_latency_via_tcp(server: &str, port: u16, timeout_ms: u32) -> i64 {
+    let timeout = std::time::Duration::from_millis(timeout_ms as u64);
+    let Ok(Ok(mut addrs)) = tokio::time::timeout(timeout, tokio::net::lookup_host((server, port))).await else { return -1 };
+    let Some(addr) = addrs.next() else { return -1 };
+    let start = std::time::Instant::now();
+    match tokio::time::timeout(timeout, tokio::net::TcpStream::connect(addr)).await {
+        Ok(Ok(_)) => start.elapsed().as_millis() as i64,
+        _ => -1,
+    }
+}
+
+/// 域名只取第一个 IPv4 地址，失败或超时为 -1
+async fn test_latency_via_icmp(server: &str, timeout_ms: u32) -> i64 {
+    let timeout = std::time::Duration::from_millis(timeout_ms as u64);
+    let Ok(Ok(addrs)) = tokio::time::timeout(timeout, tokio::net::lookup_host((server, 0))).await else { return -1 };
+    let Some(addr) = addrs.filter_map(|a| match a.ip() { std::net::IpAddr::V4(ip) => Some(ip), _ => None }).next() else { return -1 };
+    tokio::task::spawn_blocking(move || crate::icmp::ping(addr, timeout_ms))
+        .await
+        .ok()
+        .flatten()
+        .map_or(-1, |rtt| rtt as i64)
+}
+
+pub(crate) async fn test_latency_via_clash_api(proxy_name: &str, port: u16, options: &LatencyTestOptions) -> Result<i64, String> {
+    // 内核按 timeout 放弃测试，请求本身多留一点余量
     let client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(15))
+        .timeout(std::time::Duration::from_millis(options.timeout_ms as u64 + 2000))
         .build()
         .map_err(|e| e.to_string())?;
     
-    let test_url = "https://www.gstatic.com/generate_204";
     let encoded_name = urlencoding::encode(proxy_name);
     let url = format!(
-        "http://127.0.0.1:{}/proxies/{}/delay?url={}&timeout=10000",
+        "http://127.0.0.1:{}/proxies/{}/delay?url={}&timeout={}",
         port,
         encoded_name,
-        urlencoding::encode(test_url)
+        urlencoding::encode(&options.url),
+        options.timeout_ms
     );
     
     let response = client.get(&url).send().await.map_err(|e| e.to_string())?;
@@ -648,30 +955,10 @@ async fn test_latency_via_clash_api(proxy_name: &str, port: u16) -> Result<i64,
     Ok(-1)
 }
 
-async fn start_temp_singbox(app: &AppHandle, state: &AppState) -> bool {
-    // Check if already running
-    {
-        let mut process = TEMP_SINGBOX_PROCESS.lock().await;
-        if let Some(ref mut child) = *process {
-            // Check if process is still running
-            match child.try_wait() {
-                Ok(None) => {
-                    // Still running, check if API is responsive
-                    if check_clash_api_running(TEMP_SINGBOX_PORT).await {
-                        return true;
-                    }
-                }
-                _ => {
-                    // Process exited
-                    *process = None;
-                }
-            }
-        }
-    }
-    
+async fn start_temp_singbox(app: &AppHandle, state: &AppState, profile_id: &str) -> bool {
     // Get kernel path
-    let kernel_path = match app.path().resource_dir() {
-        Ok(dir) => dir.join("resources").join("libs").join("sing-box.exe"),
+    let kernel_path = match crate::commands::kernel::resolve_kernel_path(app).await {
+        Ok(path) => path,
         Err(_) => return false,
     };
     
@@ -680,15 +967,8 @@ async fn start_temp_singbox(app: &AppHandle, state: &AppState) -> bool {
         return false;
     }
     
-    // Load nodes and generate temp config
-    let data = load_profiles_data(state);
-    let profile_id = match data.active_profile_id {
-        Some(id) => id,
-        None => return false,
-    };
-    
     // 直接读取原始 JSON 节点
-    let nodes_raw = load_profile_nodes_raw(state, &profile_id);
+    let nodes_raw = load_profile_nodes_raw(state, profile_id);
     if nodes_raw.is_empty() {
         log::warn!("No nodes found for latency testing");
         return false;
@@ -705,7 +985,26 @@ async fn start_temp_singbox(app: &AppHandle, state: &AppState) -> bool {
     let config_path = temp_dir.join("config.json");
     
     let config_str = serde_json::to_string_pretty(&config).unwrap_or_default();
-    log::info!("Temp config: {}", config_str);
+
+    // Check if already running with the same nodes
+    {
+        let mut process = TEMP_SINGBOX_PROCESS.lock().await;
+        if let Some(temp) = process.as_mut() {
+            let running = matches!(temp.child.try_wait(), Ok(None));
+            if running && temp.config == config_str && check_clash_api_running(TEMP_SINGBOX_PORT).await {
+                temp.last_used = std::time::Instant::now();
+                return true;
+            }
+            if running {
+                log::info!("Restarting temp sing-box with updated nodes");
+            }
+            if let Some(mut temp) = process.take() {
+                let _ = temp.child.kill().await;
+            }
+        }
+    }
+
+    log::info!("Temp config written to {:?} ({} nodes)", config_path, nodes_raw.len());
     
     if let Err(e) = fs::write(&config_path, &config_str) {
         log::error!("Failed to write temp config: {}", e);
@@ -713,26 +1012,13 @@ async fn start_temp_singbox(app: &AppHandle, state: &AppState) -> bool {
     }
     
     // Start temp sing-box
-    #[cfg(windows)]
-    let result = tokio::process::Command::new(&kernel_path)
-        .args(["run", "-c", config_path.to_str().unwrap()])
-        .current_dir(&temp_dir)
-        .creation_flags(CREATE_NO_WINDOW)
-        .kill_on_drop(true)
-        .spawn();
-
-    #[cfg(not(windows))]
-    let result = tokio::process::Command::new(&kernel_path)
-        .args(["run", "-c", config_path.to_str().unwrap()])
-        .current_dir(&temp_dir)
-        .kill_on_drop(true)
-        .spawn();
-    
-    match result {
+    match spawn_test_kernel(&kernel_path, &config_path, &temp_dir) {
         Ok(child) => {
+            let generation = TEMP_SINGBOX_GENERATION.fetch_add(1, std::sync::atomic::Ordering::Relaxed) + 1;
             let mut process = TEMP_SINGBOX_PROCESS.lock().await;
-            *process = Some(child);
+            *process = Some(TempSingbox { child, config_path, config: config_str, last_used: std::time::Instant::now(), generation });
             log::info!("Started temp sing-box on port {}", TEMP_SINGBOX_PORT);
+            tauri::async_runtime::spawn(stop_temp_singbox_when_idle(generation));
             true
         }
         Err(e) => {
@@ -742,7 +1028,57 @@ async fn start_temp_singbox(app: &AppHandle, state: &AppState) -> bool {
     }
 }
 
-async fn check_clash_api_running(port: u16) -> bool {
+/// 启动测试用的内核，不显示控制台窗口，句柄丢弃时自动结束
+pub(crate) fn spawn_test_kernel(kernel_path: &Path, config_path: &Path, dir: &Path) -> std::io::Result<tokio::process::Child> {
+    let mut command = tokio::process::Command::new(kernel_path);
+    command.arg("run").arg("-c").arg(config_path)
+        .current_dir(dir)
+        .kill_on_drop(true);
+    #[cfg(windows)]
+    command.creation_flags(CREATE_NO_WINDOW);
+    command.spawn()
+}
+
+/// 结束延迟测试用的临时 sing-box 并删除其配置；在主内核启动、切换配置和退出时调用
+pub(crate) async fn stop_temp_singbox() {
+    if let Some(mut temp) = TEMP_SINGBOX_PROCESS.lock().await.take() {
+        let _ = temp.child.kill().await;
+        let _ = fs::remove_file(&temp.config_path);
+        log::info!("Stopped temp sing-box");
+    }
+}
+
+/// 测速开始和结束时刷新空闲计时
+async fn touch_temp_singbox() {
+    if let Some(temp) = TEMP_SINGBOX_PROCESS.lock().await.as_mut() {
+        temp.last_used = std::time::Instant::now();
+    }
+}
+
+/// 临时内核空闲超过 TEMP_SINGBOX_IDLE_TIMEOUT 后结束；generation 对应的内核已退出或被替换时任务随之结束
+async fn stop_temp_singbox_when_idle(generation: u64) {
+    loop {
+        let mut process = TEMP_SINGBOX_PROCESS.lock().await;
+        let idle = match process.as_ref() {
+            Some(temp) if temp.generation == generation => temp.last_used.elapsed(),
+            _ => return,
+        };
+        if idle >= TEMP_SINGBOX_IDLE_TIMEOUT {
+            // 检查和结束在同一次加锁内完成，不会误杀刚替换上的新内核
+            if let Some(mut temp) = process.take() {
+                drop(process);
+                log::info!("Temp sing-box idle for {}s", idle.as_secs());
+                let _ = temp.child.kill().await;
+                let _ = fs::remove_file(&temp.config_path);
+            }
+            return;
+        }
+        drop(process);
+        tokio::time::sleep(TEMP_SINGBOX_IDLE_TIMEOUT - idle).await;
+    }
+}
+
+pub(crate) async fn check_clash_api_running(port: u16) -> bool {
     let client = reqwest::Client::builder()
         .timeout(std::time::Duration::from_millis(500))
         .build();
@@ -786,7 +1122,7 @@ fn generate_temp_config(nodes: &[SingBoxOutbound], api_port: u16) -> serde_json:
     })
 }
 
-fn generate_temp_config_raw(nodes: &[serde_json::Value], api_port: u16) -> serde_json::Value {
+pub(crate) fn generate_temp_config_raw(nodes: &[serde_json::Value], api_port: u16) -> serde_json::Value {
     // 处理节点，移除不合法字段并添加必要配置
     let mut outbounds: Vec<serde_json::Value> = nodes.iter()
         .map(|node| {
@@ -987,6 +1323,8 @@ pub async fn profile_import_content(
         auto_update_interval: auto_update_interval.unwrap_or(0),
         dns_pre_resolve: dns_pre_resolve.unwrap_or(false),
         dns_server,
+        subscription: None,
+        quota_alert: Default::default(),
     };
 
     save_profile_nodes(&state, &profile.id, &nodes)?;
@@ -1002,3 +1340,47 @@ pub async fn profile_import_content(
 
     Ok(profile)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_subscription_userinfo() {
+        let info = parse_subscription_userinfo("upload=1024; download=2048; total=10240; expire=1700000000").unwrap();
+        assert_eq!((info.upload, info.download, info.total, info.expire), (1024, 2048, 10240, Some(1700000000)));
+        assert_eq!(info.remaining(), Some(7168));
+    }
+
+    #[test]
+    fn parses_decimal_and_scientific_values() {
+        let info = parse_subscription_userinfo("upload=1.5e3; download=2048.9; total=1.073741824E10; expire=0").unwrap();
+        assert_eq!((info.upload, info.download, info.total, info.expire), (1500, 2048, 10737418240, None));
+    }
+
+    #[test]
+    fn huge_values_do_not_overflow() {
+        let info = parse_subscription_userinfo("upload=1e30; download=1e30; total=1e30").unwrap();
+        assert_eq!(info.upload, u64::MAX);
+        assert_eq!(info.remaining(), Some(0));
+    }
+
+    #[test]
+    fn ignores_headers_without_known_fields() {
+        assert!(parse_subscription_userinfo("foo=1; bar").is_none());
+        assert!(parse_subscription_userinfo("upload=abc").is_none());
+    }
+
+    #[test]
+    fn tcp_probe_skips_udp_outbounds() {
+        let node = |outbound_type: &str| SingBoxOutbound { outbound_type: Some(outbound_type.to_string()), ..Default::default() };
+        assert!(!LatencyProbe::TcpConnect.supports(&node("hysteria2")));
+        assert!(!LatencyProbe::TcpConnect.supports(&node("tuic")));
+        assert!(LatencyProbe::TcpConnect.supports(&node("vless")));
+        assert!(LatencyProbe::Icmp.supports(&node("hysteria2")));
+
+        let mut udp_only = node("shadowsocks");
+        udp_only.extra.insert("network".to_string(), serde_json::json!("udp"));
+        assert!(!LatencyProbe::TcpConnect.supports(&udp_only));
+    }
+}
@@ -0,0 +1,120 @@
+use tauri::{AppHandle, Emitter, Manager, State};
+use serde::Serialize;
+use std::collections::HashSet;
+use crate::i18n;
+use crate::state::AppState;
+use crate::types::{Profile, QuotaAlert};
+
+/// 定时检查的间隔；订阅更新后会立即检查一次
+const QUOTA_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+
+static QUOTA_CHECK: once_cell::sync::Lazy<tokio::sync::Notify> = once_cell::sync::Lazy::new(tokio::sync::Notify::new);
+
+/// 本次运行中已提醒过的 (配置 id, 类型)，条件解除（续费、重置流量）后移除以便再次提醒
+static NOTIFIED: once_cell::sync::Lazy<std::sync::Mutex<HashSet<(String, &'static str)>>> =
+    once_cell::sync::Lazy::new(|| std::sync::Mutex::new(HashSet::new()));
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuotaWarning {
+    pub profile_id: String,
+    pub profile_name: String,
+    /// quota 流量不足 / expire 即将到期（含已到期）
+    pub kind: &'static str,
+    pub remaining_bytes: Option<u64>,
+    pub expire: Option<u64>,
+    pub days_left: Option<i64>,
+}
+
+impl QuotaWarning {
+    fn hint(&self) -> String {
+        match self.kind {
+            "quota" => format!("{}: {} {}", self.profile_name, i18n::tr("quota.low"), format_bytes(self.remaining_bytes.unwrap_or(0))),
+            _ => format!("{}: {} {}", self.profile_name, i18n::tr("quota.expiring"), self.days_left.unwrap_or(0).max(0)),
+        }
+    }
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const GB: f64 = 1024.0 * 1024.0 * 1024.0;
+    let bytes = bytes as f64;
+    if bytes >= GB { format!("{:.1} GB", bytes / GB) } else { format!("{:.0} MB", bytes / 1024.0 / 1024.0) }
+}
+
+fn profile_warnings(profile: &Profile, now_secs: u64) -> Vec<QuotaWarning> {
+    let Some(info) = profile.subscription.as_ref().filter(|_| profile.enabled && profile.quota_alert.enabled) else {
+        return Vec::new();
+    };
+    let warning = |kind| QuotaWarning {
+        profile_id: profile.id.clone(),
+        profile_name: profile.name.clone(),
+        kind,
+        remaining_bytes: info.remaining(),
+        expire: info.expire,
+        days_left: info.expire.map(|expire| (expire as i64 - now_secs as i64).div_euclid(86400)),
+    };
+    let mut warnings = Vec::new();
+    if info.remaining().is_some_and(|remaining| remaining < profile.quota_alert.remaining_mb.saturating_mul(1024 * 1024)) {
+        warnings.push(warning("quota"));
+    }
+    if info.expire.is_some_and(|expire| expire < now_secs.saturating_add(profile.quota_alert.expire_days as u64 * 86400)) {
+        warnings.push(warning("expire"));
+    }
+    warnings
+}
+
+fn current_warnings(state: &AppState) -> Vec<QuotaWarning> {
+    let now = chrono::Utc::now().timestamp().max(0) as u64;
+    crate::commands::profiles::load_profiles_data(state).profiles.iter()
+        .flat_map(|profile| profile_warnings(profile, now))
+        .collect()
+}
+
+/// 对新出现的提醒发送 profile:quota-warning，托盘提示始终显示当前全部提醒
+fn check_quota(app: &AppHandle) {
+    let warnings = current_warnings(&app.state::<AppState>());
+    let mut notified = NOTIFIED.lock().unwrap_or_else(|e| e.into_inner());
+    notified.retain(|(id, kind)| warnings.iter().any(|w| &w.profile_id == id && w.kind == *kind));
+    for warning in &warnings {
+        if notified.insert((warning.profile_id.clone(), warning.kind)) {
+            log::info!("Subscription warning for {}: {}", warning.profile_name, warning.kind);
+            let _ = app.emit("profile:quota-warning", warning);
+        }
+    }
+    let hint = warnings.iter().map(QuotaWarning::hint).collect::<Vec<_>>().join("\n");
+    crate::tray::set_tray_hint(app, (!hint.is_empty()).then_some(hint.as_str()));
+}
+
+/// 订阅信息变化后让后台任务立即检查
+pub(crate) fn request_quota_check() {
+    QUOTA_CHECK.notify_one();
+}
+
+pub(crate) fn start_quota_monitor(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            check_quota(&app);
+            let _ = tokio::time::timeout(QUOTA_CHECK_INTERVAL, QUOTA_CHECK.notified()).await;
+        }
+    });
+}
+
+/// 当前所有配置的流量 / 到期提醒
+#[tauri::command]
+pub async fn quota_warnings(state: State<'_, AppState>) -> Result<Vec<QuotaWarning>, String> {
+    Ok(current_warnings(&state))
+}
+
+#[tauri::command]
+pub async fn profile_set_quota_alert(state: State<'_, AppState>, id: String, alert: QuotaAlert) -> Result<Profile, String> {
+    alert.validate()?;
+    let mut data = crate::commands::profiles::load_profiles_data(&state);
+    let profile = data.profiles.iter_mut().find(|p| p.id == id)
+        .ok_or("Profile not found")?;
+    profile.quota_alert = alert;
+    let profile = profile.clone();
+    crate::commands::profiles::save_profiles_data(&state, &data)?;
+    *state.profiles_data.lock().await = data;
+    request_quota_check();
+    Ok(profile)
+}
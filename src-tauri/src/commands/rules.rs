@@ -0,0 +1,316 @@
+use tauri::{AppHandle, Manager, State};
+use std::fs;
+use crate::state::AppState;
+use crate::types::{CustomRule, ProxyState, RuleSet};
+
+#[cfg(windows)]
+const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+const CUSTOM_RULE_TYPES: &[&str] = &["domain", "domain_suffix", "domain_keyword", "domain_regex", "domain_wildcard", "ip_cidr", "port", "process_name", "process_path"];
+
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct RunningProcess {
+    pub name: String,
+    pub path: Option<String>,
+}
+
+pub(crate) fn load_custom_rules(state: &AppState) -> Vec<CustomRule> {
+    fs::read_to_string(state.custom_rules_file())
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save_custom_rules(state: &AppState, rules: &[CustomRule]) -> Result<(), String> {
+    fs::create_dir_all(state.workspace_dir()).map_err(|e| e.to_string())?;
+    let content = serde_json::to_string_pretty(rules).map_err(|e| e.to_string())?;
+    fs::write(state.custom_rules_file(), content).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// 通配符转换为 domain_regex："*" 匹配任意字符，"?" 匹配单个字符
+pub(crate) fn wildcard_to_regex(pattern: &str) -> String {
+    let mut regex = String::from("^");
+    for c in pattern.chars() {
+        match c {
+            '*' => regex.push_str(".*"),
+            '?' => regex.push('.'),
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex.push('$');
+    regex
+}
+
+/// 例外域名统一小写、去掉前导的 "." 和 "*."，并去重
+pub(crate) fn normalize_exceptions(exceptions: &mut Vec<String>) {
+    let mut seen = std::collections::HashSet::new();
+    exceptions.retain_mut(|domain| {
+        *domain = domain.trim().trim_start_matches("*.").trim_start_matches('.').to_lowercase();
+        !domain.is_empty() && seen.insert(domain.clone())
+    });
+}
+
+/// 校验规则类型和取值，顺便去掉空白和空项
+fn normalize_rule(mut rule: CustomRule) -> Result<CustomRule, String> {
+    if !CUSTOM_RULE_TYPES.contains(&rule.rule_type.as_str()) {
+        return Err(format!("Unsupported rule type: {}", rule.rule_type));
+    }
+    rule.values = rule.values.iter()
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+        .collect();
+    if rule.values.is_empty() {
+        return Err("Rule has no values".to_string());
+    }
+    if let Some(ref schedule) = rule.schedule {
+        schedule.validate()?;
+    }
+    normalize_exceptions(&mut rule.exceptions);
+    for value in &rule.values {
+        match rule.rule_type.as_str() {
+            "port" => {
+                value.parse::<u16>().map_err(|_| format!("Invalid port: {}", value))?;
+            }
+            "domain_regex" => {
+                regex::Regex::new(value).map_err(|e| format!("Invalid regex {}: {}", value, e))?;
+            }
+            "domain_wildcard" => {
+                regex::Regex::new(&wildcard_to_regex(value)).map_err(|e| format!("Invalid wildcard {}: {}", value, e))?;
+            }
+            "ip_cidr" => {
+                let (ip, prefix) = value.split_once('/').map(|(ip, p)| (ip, Some(p))).unwrap_or((value, None));
+                let ip: std::net::IpAddr = ip.parse().map_err(|_| format!("Invalid IP CIDR: {}", value))?;
+                let max = if ip.is_ipv4() { 32 } else { 128 };
+                if let Some(p) = prefix {
+                    p.parse::<u8>().ok().filter(|p| *p <= max).ok_or_else(|| format!("Invalid IP CIDR: {}", value))?;
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(rule)
+}
+
+#[tauri::command]
+pub async fn rule_list(state: State<'_, AppState>) -> Result<Vec<CustomRule>, String> {
+    Ok(load_custom_rules(&state))
+}
+
+#[tauri::command]
+pub async fn rule_add(state: State<'_, AppState>, rule: CustomRule) -> Result<CustomRule, String> {
+    let mut rule = normalize_rule(rule)?;
+    rule.id = uuid::Uuid::new_v4().to_string();
+    
+    let mut rules = load_custom_rules(&state);
+    rules.push(rule.clone());
+    save_custom_rules(&state, &rules)?;
+    Ok(rule)
+}
+
+#[tauri::command]
+pub async fn rule_update(state: State<'_, AppState>, rule: CustomRule) -> Result<CustomRule, String> {
+    let rule = normalize_rule(rule)?;
+    
+    let mut rules = load_custom_rules(&state);
+    let existing = rules.iter_mut()
+        .find(|r| r.id == rule.id)
+        .ok_or("Rule not found")?;
+    *existing = rule.clone();
+    save_custom_rules(&state, &rules)?;
+    Ok(rule)
+}
+
+#[tauri::command]
+pub async fn rule_delete(state: State<'_, AppState>, id: String) -> Result<(), String> {
+    let mut rules = load_custom_rules(&state);
+    let before = rules.len();
+    rules.retain(|r| r.id != id);
+    if rules.len() == before {
+        return Err("Rule not found".to_string());
+    }
+    save_custom_rules(&state, &rules)
+}
+
+/// 当前时间段内生效的规则和规则集 ID，用于判断是否需要重新生成配置
+fn active_scheduled_ids(state: &AppState, rulesets: &[RuleSet]) -> Vec<String> {
+    let now = chrono::Local::now();
+    let scheduled_rules = load_custom_rules(state).into_iter()
+        .filter(|r| r.enabled)
+        .filter_map(|r| r.schedule.map(|s| (r.id, s)));
+    let scheduled_rulesets = rulesets.iter()
+        .filter(|r| r.enabled)
+        .filter_map(|r| r.schedule.clone().map(|s| (r.id.clone(), s)));
+    scheduled_rules.chain(scheduled_rulesets)
+        .filter(|(_, schedule)| schedule.is_active_at(now))
+        .map(|(id, _)| id)
+        .collect()
+}
+
+/// 每分钟检查一次时间段规则，生效集合变化且核心运行中时重启核心以应用新规则
+pub fn start_rule_scheduler(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut last_active: Option<Vec<String>> = None;
+        loop {
+            let state = app.state::<AppState>();
+            let rulesets = state.rulesets.lock().await.clone();
+            let active = active_scheduled_ids(&state, &rulesets);
+            
+            if last_active.as_ref().is_some_and(|last| last != &active)
+                && matches!(*state.proxy_state.lock().await, ProxyState::Connected)
+            {
+                log::info!("Scheduled rules changed, restarting core");
+                if let Err(e) = crate::commands::singbox_restart(app.clone(), state.clone()).await {
+                    log::error!("Failed to apply scheduled rules: {}", e);
+                }
+            }
+            last_active = Some(active);
+            
+            tokio::time::sleep(std::time::Duration::from_secs(60)).await;
+        }
+    });
+}
+
+/// Clash 规则类型到自定义规则类型的映射
+fn clash_rule_type(clash_type: &str) -> Option<&'static str> {
+    match clash_type {
+        "DOMAIN" => Some("domain"),
+        "DOMAIN-SUFFIX" => Some("domain_suffix"),
+        "DOMAIN-KEYWORD" => Some("domain_keyword"),
+        "DOMAIN-REGEX" => Some("domain_regex"),
+        "IP-CIDR" | "IP-CIDR6" => Some("ip_cidr"),
+        "DST-PORT" => Some("port"),
+        "PROCESS-NAME" => Some("process_name"),
+        "PROCESS-PATH" => Some("process_path"),
+        _ => None,
+    }
+}
+
+/// Clash 策略名映射为出站模式，未知的策略组统一走代理
+fn clash_outbound(target: &str) -> String {
+    match target.to_uppercase().as_str() {
+        "DIRECT" => "direct".to_string(),
+        "REJECT" | "REJECT-DROP" | "REJECT-TINYGIF" => "block".to_string(),
+        _ => "proxy".to_string(),
+    }
+}
+
+/// 导入 Clash 格式的规则文本（粘贴内容或文件），相邻且类型、出站相同的行合并为一条自定义规则
+#[tauri::command]
+pub async fn rule_import_clash(
+    state: State<'_, AppState>,
+    content: Option<String>,
+    path: Option<String>,
+    default_outbound: Option<String>,
+) -> Result<serde_json::Value, String> {
+    let content = match (content, path) {
+        (Some(content), _) => content,
+        (None, Some(path)) => fs::read_to_string(&path).map_err(|e| e.to_string())?,
+        (None, None) => return Err("No rules to import".to_string()),
+    };
+    // rule-provider 的 payload 没有策略字段，使用默认出站
+    let default_outbound = default_outbound.unwrap_or_else(|| "proxy".to_string());
+    
+    let mut imported: Vec<CustomRule> = Vec::new();
+    let mut skipped = Vec::new();
+    for line in content.lines() {
+        // 兼容 YAML 列表写法："  - DOMAIN-SUFFIX,google.com,PROXY"
+        let line = line.trim().trim_start_matches('-').trim().trim_matches(|c| c == '\'' || c == '"');
+        if line.is_empty() || line.starts_with('#') || line.ends_with(':') {
+            continue;
+        }
+        
+        let parts: Vec<&str> = line.split(',').map(|p| p.trim()).collect();
+        let rule_type = match parts.first().and_then(|t| clash_rule_type(&t.to_uppercase())) {
+            Some(rule_type) if parts.len() >= 2 => rule_type,
+            _ => {
+                skipped.push(line.to_string());
+                continue;
+            }
+        };
+        let outbound_mode = match parts.get(2) {
+            // 第三段可能是 no-resolve 之类的附加参数
+            Some(target) if !target.eq_ignore_ascii_case("no-resolve") => clash_outbound(target),
+            _ => default_outbound.clone(),
+        };
+        let value = parts[1].to_string();
+        
+        match imported.last_mut() {
+            Some(last) if last.rule_type == rule_type && last.outbound_mode == outbound_mode => last.values.push(value),
+            _ => imported.push(CustomRule {
+                id: String::new(),
+                rule_type: rule_type.to_string(),
+                values: vec![value],
+                outbound_mode,
+                outbound_value: None,
+                enabled: true,
+                schedule: None,
+                invert: false,
+                exceptions: Vec::new(),
+            }),
+        }
+    }
+    
+    let mut rules = load_custom_rules(&state);
+    let mut added = 0;
+    for rule in imported {
+        match normalize_rule(rule) {
+            Ok(mut rule) => {
+                rule.id = uuid::Uuid::new_v4().to_string();
+                added += rule.values.len();
+                rules.push(rule);
+            }
+            Err(e) => skipped.push(e),
+        }
+    }
+    save_custom_rules(&state, &rules)?;
+    
+    log::info!("Imported {} Clash rule entries, skipped {}", added, skipped.len());
+    Ok(serde_json::json!({
+        "success": true,
+        "imported": added,
+        "skipped": skipped,
+    }))
+}
+
+/// 列出正在运行的进程（按进程名去重），供选择按应用分流的目标
+#[tauri::command]
+pub async fn process_list() -> Result<Vec<RunningProcess>, String> {
+    #[cfg(windows)]
+    {
+        let output = tokio::process::Command::new("powershell")
+            .args(["-NoProfile", "-NonInteractive", "-Command", "Get-Process | Select-Object Name,Path | ConvertTo-Json -Compress"])
+            .creation_flags(CREATE_NO_WINDOW)
+            .output()
+            .await
+            .map_err(|e| e.to_string())?;
+        if !output.status.success() {
+            return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+        }
+
+        // 只有一个进程时 ConvertTo-Json 输出单个对象而不是数组
+        let value: serde_json::Value = serde_json::from_slice(&output.stdout).map_err(|e| e.to_string())?;
+        let items = match value {
+            serde_json::Value::Array(items) => items,
+            other => vec![other],
+        };
+
+        let mut processes: Vec<RunningProcess> = Vec::new();
+        for item in items {
+            let name = match item.get("Name").and_then(|n| n.as_str()) {
+                Some(name) => format!("{}.exe", name),
+                None => continue,
+            };
+            if processes.iter().any(|p| p.name.eq_ignore_ascii_case(&name)) {
+                continue;
+            }
+            let path = item.get("Path").and_then(|p| p.as_str()).map(|p| p.to_string());
+            processes.push(RunningProcess { name, path });
+        }
+        processes.sort_by_key(|p| p.name.to_lowercase());
+        Ok(processes)
+    }
+
+    #[cfg(not(windows))]
+    Ok(Vec::new())
+}
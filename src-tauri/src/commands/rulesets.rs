@@ -1,15 +1,20 @@
-use tauri::State;
+use tauri::{AppHandle, Emitter, Manager, State};
 use std::fs;
+use std::path::Path;
+use crate::migrate::{self, DataFile};
 use crate::state::AppState;
-use crate::types::RuleSet;
-
-// GitHub 镜像列表
-const GITHUB_MIRRORS: &[&str] = &[
-    "https://raw.githubusercontent.com/",  // 原始地址
-    "https://mirror.ghproxy.com/https://raw.githubusercontent.com/",  // ghproxy
-    "https://raw.gitmirror.com/",  // gitmirror
-    "https://raw.fastgit.org/",  // fastgit (可能失效)
-];
+use crate::types::{ProxyState, RuleSet};
+
+#[cfg(windows)]
+const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+const BUNDLE_RULESETS_FILE: &str = "rulesets.json";
+const BUNDLE_CACHE_DIR: &str = "rulesets";
+const BUNDLE_MAX_FILE_SIZE: u64 = 64 * 1024 * 1024;
+
+/// 规则集可选的 DNS 服务器，与 generate_config 中的 DNS server tag 对应
+pub(crate) const RULESET_DNS_SERVERS: &[&str] = &["dns-local", "dns-remote", "dns-fakeip"];
+const RULESET_UPDATE_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(600);
 
 fn get_default_rulesets() -> Vec<RuleSet> {
     vec![
@@ -24,6 +29,17 @@ fn get_default_rulesets() -> Vec<RuleSet> {
             outbound_value: None,
             enabled: false,
             is_built_in: true,
+            update_interval: 1440,
+            last_updated: None,
+            etag: None,
+            etag_url: None,
+            last_error: None,
+            file_size: None,
+            source_format: None,
+            schedule: None,
+            dns_server: None,
+            invert: false,
+            exceptions: Vec::new(),
         },
         RuleSet {
             id: "2".to_string(),
@@ -36,6 +52,17 @@ fn get_default_rulesets() -> Vec<RuleSet> {
             outbound_value: None,
             enabled: false,
             is_built_in: true,
+            update_interval: 1440,
+            last_updated: None,
+            etag: None,
+            etag_url: None,
+            last_error: None,
+            file_size: None,
+            source_format: None,
+            schedule: None,
+            dns_server: None,
+            invert: false,
+            exceptions: Vec::new(),
         },
         RuleSet {
             id: "3".to_string(),
@@ -48,6 +75,17 @@ fn get_default_rulesets() -> Vec<RuleSet> {
             outbound_value: None,
             enabled: false,
             is_built_in: true,
+            update_interval: 1440,
+            last_updated: None,
+            etag: None,
+            etag_url: None,
+            last_error: None,
+            file_size: None,
+            source_format: None,
+            schedule: None,
+            dns_server: None,
+            invert: false,
+            exceptions: Vec::new(),
         },
         RuleSet {
             id: "4".to_string(),
@@ -60,45 +98,71 @@ fn get_default_rulesets() -> Vec<RuleSet> {
             outbound_value: None,
             enabled: false,
             is_built_in: true,
+            update_interval: 1440,
+            last_updated: None,
+            etag: None,
+            etag_url: None,
+            last_error: None,
+            file_size: None,
+            source_format: None,
+            schedule: None,
+            dns_server: None,
+            invert: false,
+            exceptions: Vec::new(),
         },
     ]
 }
 
-fn load_rulesets(state: &AppState) -> Vec<RuleSet> {
-    let file = state.rulesets_file();
-    if file.exists() {
-        if let Ok(content) = fs::read_to_string(&file) {
-            if let Ok(rulesets) = serde_json::from_str(&content) {
-                return rulesets;
-            }
-        }
-    }
-    get_default_rulesets()
+pub(crate) fn load_rulesets(state: &AppState) -> Vec<RuleSet> {
+    migrate::load(&state.rulesets_file(), DataFile::Rulesets).unwrap_or_else(get_default_rulesets)
 }
 
 fn save_rulesets(state: &AppState, rulesets: &[RuleSet]) -> Result<(), String> {
-    fs::create_dir_all(&state.data_dir).map_err(|e| e.to_string())?;
-    let content = serde_json::to_string_pretty(rulesets).map_err(|e| e.to_string())?;
-    fs::write(state.rulesets_file(), content).map_err(|e| e.to_string())?;
-    Ok(())
+    fs::create_dir_all(state.workspace_dir()).map_err(|e| e.to_string())?;
+    migrate::save(&state.rulesets_file(), DataFile::Rulesets, rulesets, false)
+}
+
+/// 持有 state.rulesets 的锁完成读取、修改和保存，后台更新与界面编辑不会用旧副本互相覆盖
+async fn modify_rulesets<T>(state: &AppState, modify: impl FnOnce(&mut Vec<RuleSet>) -> Result<T, String>) -> Result<T, String> {
+    let mut current = state.rulesets.lock().await;
+    let mut rulesets = load_rulesets(state);
+    let result = modify(&mut rulesets)?;
+    save_rulesets(state, &rulesets)?;
+    *current = rulesets;
+    Ok(result)
 }
 
 #[tauri::command]
 pub async fn ruleset_list(state: State<'_, AppState>) -> Result<Vec<RuleSet>, String> {
-    let rulesets = load_rulesets(&state);
+    let mut rulesets = load_rulesets(&state);
+    for rs in &mut rulesets {
+        rs.file_size = fs::metadata(state.ruleset_cache_file(&rs.tag, &rs.format)).ok().map(|m| m.len());
+    }
     *state.rulesets.lock().await = rulesets.clone();
     Ok(rulesets)
 }
 
 #[tauri::command]
-pub async fn ruleset_save(state: State<'_, AppState>, rulesets: Vec<RuleSet>) -> Result<(), String> {
+pub async fn ruleset_save(state: State<'_, AppState>, mut rulesets: Vec<RuleSet>) -> Result<(), String> {
+    for rs in &mut rulesets {
+        crate::commands::rules::normalize_exceptions(&mut rs.exceptions);
+        if let Some(ref schedule) = rs.schedule {
+            schedule.validate()?;
+        }
+        if let Some(ref server) = rs.dns_server {
+            if !RULESET_DNS_SERVERS.contains(&server.as_str()) {
+                return Err(format!("Unknown DNS server for ruleset {}: {}", rs.tag, server));
+            }
+        }
+    }
+    let mut current = state.rulesets.lock().await;
     save_rulesets(&state, &rulesets)?;
-    *state.rulesets.lock().await = rulesets;
+    *current = rulesets;
     Ok(())
 }
 
 #[tauri::command]
-pub async fn ruleset_download(state: State<'_, AppState>, ruleset: RuleSet) -> Result<serde_json::Value, String> {
+pub async fn ruleset_download(app: AppHandle, state: State<'_, AppState>, ruleset: RuleSet, force: Option<bool>) -> Result<serde_json::Value, String> {
     if ruleset.rule_type != "remote" {
         return Ok(serde_json::json!({ "success": true, "cached": true }));
     }
@@ -106,72 +170,301 @@ pub async fn ruleset_download(state: State<'_, AppState>, ruleset: RuleSet) -> R
     let cache_dir = state.rulesets_cache_dir();
     fs::create_dir_all(&cache_dir).map_err(|e| e.to_string())?;
     
-    let cache_file = cache_dir.join(format!("{}.srs", ruleset.tag));
+    let cache_file = state.ruleset_cache_file(&ruleset.tag, &ruleset.format);
     
-    if cache_file.exists() {
+    // force 时忽略已有缓存重新下载，校验通过后才替换旧文件
+    if cache_file.exists() && !force.unwrap_or(false) {
         return Ok(serde_json::json!({ "success": true, "cached": true }));
     }
 
-    let original_url = ruleset.url.ok_or("No URL for ruleset")?;
+    let mirrors = state.settings.lock().await.github_mirrors.clone();
+    let clients = crate::commands::kernel::build_http_clients(&state, 30).await?;
+    let outcome = match fetch_ruleset(&clients, &ruleset, None, &mirrors).await {
+        Ok(outcome) => outcome,
+        Err(e) => {
+            record_ruleset_error(&state, &ruleset.id, &e).await?;
+            return Err(e);
+        }
+    };
+    match outcome {
+        FetchOutcome::Downloaded { bytes, etag, url } => {
+            write_ruleset_cache(&cache_file, &bytes)?;
+            record_ruleset_update(&state, &ruleset.id, Some((url.as_str(), etag))).await?;
+            let mut result = serde_json::json!({ "success": true, "cached": false, "url": url });
+            if ruleset.format == "source" {
+                result["compiled"] = serde_json::Value::Bool(compile_source_ruleset(&app, &ruleset.tag).await.is_ok());
+            }
+            Ok(result)
+        }
+        FetchOutcome::NotModified => Ok(serde_json::json!({ "success": true, "cached": true })),
+    }
+}
+
+enum FetchOutcome {
+    NotModified,
+    Downloaded { bytes: Vec<u8>, etag: Option<String>, url: String },
+}
+
+/// 尝试下载的 URL 列表：GitHub 地址还原为 raw 地址后展开镜像
+fn ruleset_urls(ruleset: &RuleSet, mirrors: &[String]) -> Result<Vec<String>, String> {
+    let original_url = ruleset.url.clone().ok_or("No URL for ruleset")?;
     
     // 提取 GitHub 路径（如果是 GitHub URL）
-    let github_path = extract_github_path(&original_url);
-    
-    // 创建代理客户端（使用本地 VPN 代理）
-    let proxy_client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(30))
-        .proxy(reqwest::Proxy::all("http://127.0.0.1:7890").ok().unwrap())
-        .build()
-        .ok();
-    
-    // 创建直连客户端
-    let direct_client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(30))
-        .build()
-        .map_err(|e| e.to_string())?;
-    
-    // 尝试下载的 URL 列表
-    let urls_to_try: Vec<String> = if let Some(path) = &github_path {
-        // 如果是 GitHub 地址，尝试多个镜像
-        GITHUB_MIRRORS.iter().map(|mirror| format!("{}{}", mirror, path)).collect()
-    } else {
+    match extract_github_path(&original_url) {
+        Some(path) => Ok(crate::github::mirror_urls(&format!("https://raw.githubusercontent.com/{}", path), mirrors)),
         // 非 GitHub 地址，直接使用原始 URL
-        vec![original_url.clone()]
-    };
+        None => Ok(vec![original_url]),
+    }
+}
+
+/// 下载远程规则集：GitHub 地址依次尝试镜像，每个地址按 clients 的顺序先走代理再回退直连。
+/// etag 只发给当初返回它的地址，其他镜像的 ETag 不可比
+async fn fetch_ruleset(clients: &[(&str, reqwest::Client)], ruleset: &RuleSet, etag: Option<&str>, mirrors: &[String]) -> Result<FetchOutcome, String> {
+    let urls_to_try = ruleset_urls(ruleset, mirrors)?;
     
     let mut last_error = String::new();
     
     for url in &urls_to_try {
-        // 1. 先尝试代理下载
-        if let Some(client) = &proxy_client {
-            match download_and_verify(client, url).await {
-                Ok(bytes) => {
-                    fs::write(&cache_file, bytes).map_err(|e| e.to_string())?;
-                    log::info!("Ruleset downloaded via proxy: {}", ruleset.tag);
-                    return Ok(serde_json::json!({ "success": true, "cached": false, "url": url }));
+        let url_etag = etag.filter(|_| ruleset.etag_url.as_deref() == Some(url.as_str()));
+        for (via, client) in clients {
+            match download_and_verify(client, url, url_etag, ruleset.format == "source").await {
+                Ok(Some((bytes, etag))) => {
+                    // 拦截列表先转换为 source JSON
+                    let bytes = if ruleset.source_format.as_deref() == Some("blocklist") {
+                        convert_blocklist(&bytes)
+                    } else {
+                        Ok(bytes)
+                    };
+                    let bytes = match bytes {
+                        Ok(bytes) => bytes,
+                        Err(e) => {
+                            log::warn!("Invalid blocklist from {}: {}", url, e);
+                            last_error = e;
+                            continue;
+                        }
+                    };
+                    if let Err(e) = validate_ruleset_content(&bytes, &ruleset.format) {
+                        log::warn!("Invalid ruleset from {}: {}", url, e);
+                        last_error = e;
+                        continue;
+                    }
+                    log::info!("Ruleset downloaded via {}: {}", via, ruleset.tag);
+                    return Ok(FetchOutcome::Downloaded { bytes, etag, url: url.clone() });
+                }
+                Ok(None) => {
+                    log::info!("Ruleset not modified: {}", ruleset.tag);
+                    return Ok(FetchOutcome::NotModified);
                 }
                 Err(e) => {
-                    log::warn!("Proxy download failed for {}: {}", url, e);
+                    log::warn!("{} download failed for {}: {}", via, url, e);
                     last_error = e;
                 }
             }
         }
-        
-        // 2. 回退到直连
-        match download_and_verify(&direct_client, url).await {
-            Ok(bytes) => {
-                fs::write(&cache_file, bytes).map_err(|e| e.to_string())?;
-                log::info!("Ruleset downloaded via direct: {}", ruleset.tag);
-                return Ok(serde_json::json!({ "success": true, "cached": false, "url": url }));
+    }
+    
+    Err(format!("All download attempts failed: {}", last_error))
+}
+
+/// 先写临时文件再替换，避免核心读到写了一半的规则集
+fn write_ruleset_cache(cache_file: &Path, bytes: &[u8]) -> Result<(), String> {
+    let tmp_file = cache_file.with_extension("tmp");
+    fs::write(&tmp_file, bytes).map_err(|e| e.to_string())?;
+    fs::rename(&tmp_file, cache_file).map_err(|e| {
+        let _ = fs::remove_file(&tmp_file);
+        e.to_string()
+    })
+}
+
+/// 记录下载时间；downloaded 为 (下载地址, ETag)，重新下载时连同来源地址一起替换 ETag，未修改时为 None
+async fn record_ruleset_update(state: &AppState, id: &str, downloaded: Option<(&str, Option<String>)>) -> Result<(), String> {
+    modify_rulesets(state, |rulesets| {
+        if let Some(rs) = rulesets.iter_mut().find(|r| r.id == id) {
+            rs.last_updated = Some(chrono::Utc::now().timestamp_millis() as u64);
+            rs.last_error = None;
+            if let Some((url, etag)) = downloaded {
+                rs.etag_url = etag.as_ref().map(|_| url.to_string());
+                rs.etag = etag;
+            }
+        }
+        Ok(())
+    }).await
+}
+
+/// 记录最近一次下载失败的原因，下次成功时清除
+async fn record_ruleset_error(state: &AppState, id: &str, error: &str) -> Result<(), String> {
+    modify_rulesets(state, |rulesets| {
+        if let Some(rs) = rulesets.iter_mut().find(|r| r.id == id) {
+            rs.last_error = Some(error.to_string());
+        }
+        Ok(())
+    }).await
+}
+
+/// 只发 HEAD 请求检查远程规则集是否有更新：优先比较同一地址的 ETag，没有可比的 ETag 时比较文件大小
+#[tauri::command]
+pub async fn ruleset_check_updates(state: State<'_, AppState>) -> Result<Vec<serde_json::Value>, String> {
+    let mirrors = state.settings.lock().await.github_mirrors.clone();
+    let clients = crate::commands::kernel::build_http_clients(&state, 30).await?;
+    let remote: Vec<RuleSet> = load_rulesets(&state).into_iter()
+        .filter(|r| r.rule_type == "remote" && r.url.is_some())
+        .collect();
+    
+    let checks = remote.iter().map(|rs| {
+        let clients = &clients;
+        let mirrors = &mirrors;
+        let cache_file = state.ruleset_cache_file(&rs.tag, &rs.format);
+        async move {
+            let local_size = fs::metadata(&cache_file).ok().map(|m| m.len());
+            let mut result = serde_json::json!({ "id": rs.id, "tag": rs.tag, "cached": local_size.is_some() });
+            let urls = match ruleset_urls(rs, mirrors) {
+                Ok(urls) => urls,
+                Err(e) => {
+                    result["error"] = serde_json::Value::String(e);
+                    return result;
+                }
+            };
+            
+            let mut last_error = String::new();
+            for url in &urls {
+                // ETag 只与当初返回它的地址比较，换了镜像时退回比较大小
+                let etag = rs.etag.as_ref().filter(|_| rs.etag_url.as_deref() == Some(url.as_str()));
+                for (_, client) in clients {
+                    let mut request = client.head(url);
+                    if let Some(etag) = etag {
+                        request = request.header(reqwest::header::IF_NONE_MATCH, etag);
+                    }
+                    let resp = match request.send().await {
+                        Ok(resp) => resp,
+                        Err(e) => {
+                            last_error = e.to_string();
+                            continue;
+                        }
+                    };
+                    if resp.status() == reqwest::StatusCode::NOT_MODIFIED {
+                        result["hasUpdate"] = serde_json::Value::Bool(local_size.is_none());
+                        return result;
+                    }
+                    if !resp.status().is_success() {
+                        last_error = format!("HTTP {}", resp.status());
+                        continue;
+                    }
+                    
+                    let remote_etag = resp.headers().get(reqwest::header::ETAG).and_then(|v| v.to_str().ok()).map(|v| v.to_string());
+                    let has_update = match (etag, &remote_etag) {
+                        _ if local_size.is_none() => true,
+                        (Some(local), Some(remote)) => local != remote,
+                        // 拦截列表缓存的是转换后的内容，大小不可比
+                        _ if rs.source_format.is_some() => false,
+                        _ => resp.content_length().zip(local_size).map(|(remote, local)| remote != local).unwrap_or(false),
+                    };
+                    result["hasUpdate"] = serde_json::Value::Bool(has_update);
+                    result["remoteEtag"] = serde_json::json!(remote_etag);
+                    return result;
+                }
             }
+            result["error"] = serde_json::Value::String(last_error);
+            result
+        }
+    });
+    
+    Ok(futures::future::join_all(checks).await)
+}
+
+/// 后台定期更新启用的远程规则集
+pub fn start_ruleset_auto_update(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        // 等启动流程结束后再开始检查
+        tokio::time::sleep(std::time::Duration::from_secs(60)).await;
+        loop {
+            match update_due_rulesets(&app).await {
+                Ok(true) => reload_rulesets_in_core(&app).await,
+                Ok(false) => {}
+                Err(e) => log::warn!("Ruleset auto update failed: {}", e),
+            }
+            tokio::time::sleep(RULESET_UPDATE_CHECK_INTERVAL).await;
+        }
+    });
+}
+
+/// 更新到期的规则集，返回是否有文件发生变化
+async fn update_due_rulesets(app: &AppHandle) -> Result<bool, String> {
+    let state = app.state::<AppState>();
+    // 内核自行更新远程规则集时只需下载要在本地转换的拦截列表
+    let remote_by_kernel = state.settings.lock().await.remote_rule_sets;
+    let now = chrono::Utc::now().timestamp_millis() as u64;
+    
+    let due: Vec<RuleSet> = load_rulesets(&state).into_iter()
+        .filter(|r| r.enabled && r.rule_type == "remote" && r.update_interval > 0)
+        .filter(|r| !remote_by_kernel || r.source_format.is_some())
+        .filter(|r| r.last_updated.map(|t| now.saturating_sub(t) >= r.update_interval as u64 * 60_000).unwrap_or(true))
+        .collect();
+    if due.is_empty() {
+        return Ok(false);
+    }
+    
+    fs::create_dir_all(state.rulesets_cache_dir()).map_err(|e| e.to_string())?;
+    let mirrors = state.settings.lock().await.github_mirrors.clone();
+    let clients = crate::commands::kernel::build_http_clients(&state, 30).await?;
+    
+    let mut updated_tags = Vec::new();
+    for rs in &due {
+        let cache_file = state.ruleset_cache_file(&rs.tag, &rs.format);
+        // 缓存文件丢失时不带 ETag，强制重新下载
+        let etag = if cache_file.exists() { rs.etag.as_deref() } else { None };
+        match fetch_ruleset(&clients, rs, etag, &mirrors).await {
+            Ok(FetchOutcome::Downloaded { bytes, etag, url }) => {
+                let unchanged = fs::read(&cache_file).map(|old| old == bytes).unwrap_or(false);
+                if !unchanged {
+                    write_ruleset_cache(&cache_file, &bytes)?;
+                    if rs.format == "source" {
+                        let _ = compile_source_ruleset(app, &rs.tag).await;
+                    }
+                    updated_tags.push(rs.tag.clone());
+                }
+                record_ruleset_update(&state, &rs.id, Some((url.as_str(), etag))).await?;
+            }
+            Ok(FetchOutcome::NotModified) => record_ruleset_update(&state, &rs.id, None).await?,
             Err(e) => {
-                log::warn!("Direct download failed for {}: {}", url, e);
-                last_error = e;
+                log::warn!("Failed to update ruleset {}: {}", rs.tag, e);
+                record_ruleset_error(&state, &rs.id, &e).await?;
             }
         }
     }
     
-    Err(format!("All download attempts failed: {}", last_error))
+    if updated_tags.is_empty() {
+        return Ok(false);
+    }
+    log::info!("Rulesets updated: {:?}", updated_tags);
+    let _ = app.emit("ruleset:updated", &updated_tags);
+    Ok(true)
+}
+
+/// sing-box 1.10+ 会自动重新加载修改过的本地规则集，旧版本需要重启核心
+async fn reload_rulesets_in_core(app: &AppHandle) {
+    let state = app.state::<AppState>();
+    if !matches!(*state.proxy_state.lock().await, ProxyState::Connected) {
+        return;
+    }
+    
+    let version = match crate::commands::kernel::resolve_kernel_path(app).await {
+        Ok(path) => crate::commands::kernel::probe_kernel_version(&path).await.ok().flatten(),
+        Err(_) => None,
+    };
+    let auto_reload = version
+        .map(|v| {
+            let mut parts = v.version.split('.').map(|p| p.parse::<u32>().unwrap_or(0));
+            (parts.next().unwrap_or(0), parts.next().unwrap_or(0)) >= (1, 10)
+        })
+        .unwrap_or(false);
+    if auto_reload {
+        return;
+    }
+    
+    log::info!("Restarting core to load updated rulesets");
+    if let Err(e) = crate::commands::singbox_restart(app.clone(), state).await {
+        log::error!("Failed to restart core after ruleset update: {}", e);
+    }
 }
 
 /// 从 GitHub URL 提取路径部分
@@ -201,17 +494,29 @@ fn extract_github_path(url: &str) -> Option<String> {
     None
 }
 
-/// 下载并验证文件
-async fn download_and_verify(client: &reqwest::Client, url: &str) -> Result<Vec<u8>, String> {
-    let response = client.get(url)
+/// 下载并验证文件，带 ETag 请求且未修改时返回 None；source 格式规则集本身就是 JSON
+async fn download_and_verify(client: &reqwest::Client, url: &str, etag: Option<&str>, allow_json: bool) -> Result<Option<(Vec<u8>, Option<String>)>, String> {
+    let mut request = client.get(url);
+    if let Some(etag) = etag {
+        request = request.header(reqwest::header::IF_NONE_MATCH, etag);
+    }
+    let response = request
         .send()
         .await
         .map_err(|e| format!("Request failed: {}", e))?;
     
+    if response.status() == reqwest::StatusCode::NOT_MODIFIED {
+        return Ok(None);
+    }
     if !response.status().is_success() {
         return Err(format!("HTTP {}", response.status()));
     }
     
+    let etag = response.headers()
+        .get(reqwest::header::ETAG)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.to_string());
+    
     let bytes = response.bytes()
         .await
         .map_err(|e| format!("Read body failed: {}", e))?
@@ -231,74 +536,809 @@ async fn download_and_verify(client: &reqwest::Client, url: &str) -> Result<Vec<
     }
     
     // 检查是否是 JSON 错误
-    if header.trim().starts_with('{') {
+    if !allow_json && header.trim().starts_with('{') {
         return Err("Received JSON error response".to_string());
     }
     
-    Ok(bytes)
+    Ok(Some((bytes, etag)))
 }
 
 #[tauri::command]
-pub async fn ruleset_is_cached(state: State<'_, AppState>, tag: String) -> Result<bool, String> {
-    let cache_file = state.rulesets_cache_dir().join(format!("{}.srs", tag));
+pub async fn ruleset_is_cached(state: State<'_, AppState>, tag: String, format: Option<String>) -> Result<bool, String> {
+    let cache_file = state.ruleset_cache_file(&tag, format.as_deref().unwrap_or("binary"));
     Ok(cache_file.exists())
 }
 
-/// 从 GitHub API 获取规则集仓库列表（代理优先 + 直连回退）
+/// 删除非内置规则集并清理缓存文件；仍启用（路由规则仍引用）的规则集需先停用
 #[tauri::command]
-pub async fn ruleset_fetch_hub() -> Result<serde_json::Value, String> {
-    let url = "https://api.github.com/repos/SagerNet/sing-geosite/git/trees/rule-set?recursive=1";
-    
-    // 创建代理客户端
-    let proxy_client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(15))
-        .proxy(reqwest::Proxy::all("http://127.0.0.1:7890").ok().unwrap())
-        .build()
-        .ok();
-    
-    // 创建直连客户端
-    let direct_client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(15))
-        .build()
-        .map_err(|e| e.to_string())?;
+pub async fn ruleset_delete(state: State<'_, AppState>, id: String) -> Result<(), String> {
+    let ruleset = modify_rulesets(&state, |rulesets| {
+        let ruleset = rulesets.iter()
+            .find(|r| r.id == id)
+            .cloned()
+            .ok_or("Ruleset not found")?;
+        
+        if ruleset.is_built_in {
+            return Err("Built-in rulesets cannot be deleted".to_string());
+        }
+        if ruleset.enabled {
+            return Err(format!("Ruleset {} is still used by route rules, disable it first", ruleset.tag));
+        }
+        
+        rulesets.retain(|r| r.id != id);
+        Ok(ruleset)
+    }).await?;
     
-    // 1. 先尝试代理
-    if let Some(client) = &proxy_client {
-        match client.get(url)
-            .header("User-Agent", "KunBox-Windows-App")
-            .send()
-            .await 
-        {
-            Ok(resp) if resp.status().is_success() => {
-                if let Ok(data) = resp.json::<serde_json::Value>().await {
-                    log::info!("Fetched hub via proxy");
-                    return Ok(data);
+    // 同时清理 source 编译产物和残留的临时文件
+    for ext in ["srs", "json", "tmp", "srs.tmp"] {
+        let file = state.rulesets_cache_dir().join(format!("{}.{}", ruleset.tag, ext));
+        if file.exists() {
+            if let Err(e) = fs::remove_file(&file) {
+                log::warn!("Failed to remove ruleset cache {:?}: {}", file, e);
+            }
+        }
+    }
+    
+    log::info!("Deleted ruleset {}", ruleset.tag);
+    Ok(())
+}
+
+/// 导入 hosts 或 AdGuard 格式的拦截列表：转换为域名规则集、编译并注册为 block 规则
+#[tauri::command]
+pub async fn ruleset_import_blocklist(app: AppHandle, state: State<'_, AppState>, url: String, name: Option<String>) -> Result<RuleSet, String> {
+    if !url.starts_with("https://") && !url.starts_with("http://") {
+        return Err(format!("Invalid blocklist URL: {}", url));
+    }
+    let name = name.filter(|n| !n.trim().is_empty()).unwrap_or_else(|| "blocklist".to_string());
+    let tag = unique_ruleset_tag(&load_rulesets(&state), &name);
+    
+    let ruleset = RuleSet {
+        id: uuid::Uuid::new_v4().to_string(),
+        tag,
+        name,
+        rule_type: "remote".to_string(),
+        format: "source".to_string(),
+        url: Some(url),
+        outbound_mode: "block".to_string(),
+        outbound_value: None,
+        enabled: true,
+        is_built_in: false,
+        update_interval: 1440,
+        last_updated: None,
+        etag: None,
+        etag_url: None,
+        last_error: None,
+        file_size: None,
+        source_format: Some("blocklist".to_string()),
+        schedule: None,
+        dns_server: None,
+        invert: false,
+        exceptions: Vec::new(),
+    };
+    // 先登记再下载，下载时才能记下 ETag 和更新时间
+    modify_rulesets(&state, |rulesets| {
+        rulesets.push(ruleset.clone());
+        Ok(())
+    }).await?;
+    
+    if let Err(e) = ruleset_download(app, state.clone(), ruleset.clone(), Some(true)).await {
+        modify_rulesets(&state, |rulesets| {
+            rulesets.retain(|r| r.id != ruleset.id);
+            Ok(())
+        }).await?;
+        return Err(e);
+    }
+    
+    log::info!("Imported blocklist {}", ruleset.tag);
+    Ok(load_rulesets(&state).into_iter().find(|r| r.id == ruleset.id).unwrap_or(ruleset))
+}
+
+/// 解析 hosts（"0.0.0.0 example.com"）、AdGuard（"||example.com^"）和纯域名列表，生成 source 规则集
+fn convert_blocklist(bytes: &[u8]) -> Result<Vec<u8>, String> {
+    const IGNORED_HOSTS: &[&str] = &["localhost", "localhost.localdomain", "local", "broadcasthost", "0.0.0.0", "ip6-localhost", "ip6-loopback"];
+    let is_domain = |d: &str| {
+        d.contains('.') && !IGNORED_HOSTS.contains(&d)
+            && d.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_'))
+    };
+    
+    let text = String::from_utf8_lossy(bytes);
+    let mut domains = std::collections::BTreeSet::new();
+    let mut suffixes = std::collections::BTreeSet::new();
+    for line in text.lines() {
+        // AdGuard 的元素隐藏等页面规则（example.com##.ad）和带 $ 修饰符的规则都不是整站拦截，直接跳过
+        if ["##", "#@#", "#?#", "#$#", "$"].iter().any(|marker| line.contains(marker)) {
+            continue;
+        }
+        // # 只在行首或空白之后才是注释
+        let line = line.char_indices()
+            .find(|&(i, c)| c == '#' && (i == 0 || line[..i].ends_with(char::is_whitespace)))
+            .map_or(line, |(i, _)| &line[..i])
+            .trim();
+        // 跳过注释、列表头和白名单规则
+        if line.is_empty() || line.starts_with('!') || line.starts_with('[') || line.starts_with("@@") {
+            continue;
+        }
+        
+        if let Some(rule) = line.strip_prefix("||") {
+            // 只支持纯域名规则，带路径或修饰符的跳过
+            let domain = rule.strip_suffix('^').unwrap_or(rule).to_lowercase();
+            if is_domain(&domain) {
+                suffixes.insert(domain);
+            }
+            continue;
+        }
+        
+        let mut parts = line.split_whitespace();
+        let first = parts.next().unwrap_or_default();
+        if first.parse::<std::net::IpAddr>().is_ok() {
+            for host in parts {
+                let host = host.to_lowercase();
+                if is_domain(&host) {
+                    domains.insert(host);
                 }
             }
-            Ok(resp) => {
-                log::warn!("Proxy request failed with status: {}", resp.status());
+        } else if parts.next().is_none() && is_domain(&first.to_lowercase()) {
+            domains.insert(first.to_lowercase());
+        }
+    }
+    
+    if domains.is_empty() && suffixes.is_empty() {
+        return Err("No domains found in blocklist".to_string());
+    }
+    
+    let mut rules = Vec::new();
+    if !domains.is_empty() {
+        rules.push(serde_json::json!({ "domain": domains }));
+    }
+    if !suffixes.is_empty() {
+        rules.push(serde_json::json!({ "domain_suffix": suffixes }));
+    }
+    serde_json::to_vec(&serde_json::json!({ "version": 1, "rules": rules })).map_err(|e| e.to_string())
+}
+
+/// 按国家/地区代码一键生成 geosite-{cc} 与 geoip-{cc} 规则集：下载、设置出站并启用
+#[tauri::command]
+pub async fn ruleset_add_country(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    country_code: String,
+    outbound_mode: String,
+    outbound_value: Option<String>,
+) -> Result<serde_json::Value, String> {
+    let cc = country_code.trim().to_lowercase();
+    if cc.is_empty() || !cc.chars().all(|c| c.is_ascii_alphabetic()) {
+        return Err(format!("Invalid country code: {}", country_code));
+    }
+    
+    let candidates = [
+        (format!("geosite-{}", cc), format!("{} 网站", cc.to_uppercase()), "sing-geosite"),
+        (format!("geoip-{}", cc), format!("{} IP", cc.to_uppercase()), "sing-geoip"),
+    ];
+    
+    let mut added = Vec::new();
+    let mut errors = Vec::new();
+    for (tag, name, repo) in candidates {
+        match enable_sagernet_ruleset(&app, &state, &tag, &name, repo, &outbound_mode, outbound_value.clone()).await {
+            Ok(ruleset) => added.push(ruleset),
+            Err(e) => errors.push(serde_json::json!({ "tag": tag, "error": e })),
+        }
+    }
+    
+    Ok(serde_json::json!({
+        "success": !added.is_empty(),
+        "rulesets": added,
+        "errors": errors,
+    }))
+}
+
+/// 规则集在仓库中的下载地址；repo 与 HUB_SOURCES 的 id 一致，MetaCubeX 的 tag 形如 geoip-netflix，对应 geo/geoip/netflix.srs
+fn preset_ruleset_url(repo: &str, tag: &str) -> String {
+    match (repo, tag.split_once('-')) {
+        ("metacubex", Some((kind, name))) => format!("https://raw.githubusercontent.com/MetaCubeX/meta-rules-dat/sing/geo/{}/{}.srs", kind, name),
+        _ => format!("https://raw.githubusercontent.com/SagerNet/{}/rule-set/{}.srs", repo, tag),
+    }
+}
+
+/// 下载规则集仓库（SagerNet 或 MetaCubeX）中的规则集并启用，已存在的条目只更新出站；下载失败（如不存在的 tag）时不添加条目
+async fn enable_sagernet_ruleset(
+    app: &AppHandle,
+    state: &State<'_, AppState>,
+    tag: &str,
+    name: &str,
+    repo: &str,
+    outbound_mode: &str,
+    outbound_value: Option<String>,
+) -> Result<RuleSet, String> {
+    let existing = load_rulesets(state).into_iter().find(|r| r.tag == tag);
+    let is_new = existing.is_none();
+    let ruleset = match existing {
+        Some(existing) => existing,
+        None => RuleSet {
+            id: uuid::Uuid::new_v4().to_string(),
+            tag: tag.to_string(),
+            name: name.to_string(),
+            rule_type: "remote".to_string(),
+            format: "binary".to_string(),
+            url: Some(preset_ruleset_url(repo, tag)),
+            outbound_mode: outbound_mode.to_string(),
+            outbound_value: outbound_value.clone(),
+            enabled: false,
+            is_built_in: false,
+            update_interval: 1440,
+            last_updated: None,
+            etag: None,
+            etag_url: None,
+            last_error: None,
+            file_size: None,
+            source_format: None,
+            schedule: None,
+            dns_server: None,
+            invert: false,
+            exceptions: Vec::new(),
+        },
+    };
+    
+    // 新条目先以停用状态登记，下载时才能记下 ETag 和更新时间
+    if is_new {
+        modify_rulesets(state, |rulesets| {
+            rulesets.push(ruleset.clone());
+            Ok(())
+        }).await?;
+    }
+    if let Err(e) = ruleset_download(app.clone(), state.clone(), ruleset.clone(), None).await {
+        log::warn!("Failed to download {}: {}", tag, e);
+        if is_new {
+            modify_rulesets(state, |rulesets| {
+                rulesets.retain(|r| r.id != ruleset.id);
+                Ok(())
+            }).await?;
+        }
+        return Err(e);
+    }
+    
+    // 下载过程中会写入 etag 等信息，重新读取后再修改
+    modify_rulesets(state, |rulesets| {
+        let mut ruleset = rulesets.iter().find(|r| r.tag == tag).cloned().unwrap_or(ruleset);
+        ruleset.outbound_mode = outbound_mode.to_string();
+        ruleset.outbound_value = outbound_value;
+        ruleset.enabled = true;
+        ruleset.last_updated = Some(chrono::Utc::now().timestamp_millis() as u64);
+        match rulesets.iter_mut().find(|r| r.tag == tag) {
+            Some(existing) => *existing = ruleset.clone(),
+            None => rulesets.push(ruleset.clone()),
+        }
+        Ok(ruleset)
+    }).await
+}
+
+struct RulesetPreset {
+    id: &'static str,
+    name: &'static str,
+    description: &'static str,
+    /// (tag, 名称, 仓库（sing-geosite / sing-geoip / metacubex）, 出站模式)
+    rulesets: &'static [(&'static str, &'static str, &'static str, &'static str)],
+}
+
+/// 一键规则包，按顺序写入规则集列表（顺序即匹配优先级）
+const RULESET_PRESETS: &[RulesetPreset] = &[
+    RulesetPreset {
+        id: "china-direct-ads",
+        name: "国内直连 + 广告拦截",
+        description: "拦截广告，私有地址和中国网站、IP 直连",
+        rulesets: &[
+            ("geosite-category-ads-all", "广告拦截", "sing-geosite", "block"),
+            ("geosite-private", "私有地址", "sing-geosite", "direct"),
+            ("geosite-cn", "中国网站", "sing-geosite", "direct"),
+            ("geoip-cn", "中国 IP", "sing-geoip", "direct"),
+        ],
+    },
+    RulesetPreset {
+        id: "streaming-proxy",
+        name: "流媒体走代理",
+        description: "Netflix、YouTube、Disney+、Spotify 走代理",
+        rulesets: &[
+            ("geosite-netflix", "Netflix", "sing-geosite", "proxy"),
+            // SagerNet 的 sing-geoip 只有国家/地区代码，Netflix IP 取自 MetaCubeX
+            ("geoip-netflix", "Netflix IP", "metacubex", "proxy"),
+            ("geosite-youtube", "YouTube", "sing-geosite", "proxy"),
+            ("geosite-disney", "Disney+", "sing-geosite", "proxy"),
+            ("geosite-spotify", "Spotify", "sing-geosite", "proxy"),
+        ],
+    },
+    RulesetPreset {
+        id: "gaming-direct",
+        name: "游戏低延迟直连",
+        description: "国内游戏和 Steam 国内下载直连",
+        rulesets: &[
+            ("geosite-category-games@cn", "国内游戏", "sing-geosite", "direct"),
+            ("geosite-steam@cn", "Steam 国内", "sing-geosite", "direct"),
+        ],
+    },
+];
+
+#[tauri::command]
+pub async fn ruleset_list_presets() -> Result<Vec<serde_json::Value>, String> {
+    Ok(RULESET_PRESETS.iter().map(|preset| serde_json::json!({
+        "id": preset.id,
+        "name": preset.name,
+        "description": preset.description,
+        "rulesets": preset.rulesets.iter()
+            .map(|(tag, name, _, outbound_mode)| serde_json::json!({ "tag": tag, "name": name, "outboundMode": outbound_mode }))
+            .collect::<Vec<_>>(),
+    })).collect())
+}
+
+/// 应用规则包：逐个下载并启用，单个失败不影响其余规则集
+#[tauri::command]
+pub async fn ruleset_apply_preset(app: AppHandle, state: State<'_, AppState>, preset_id: String) -> Result<serde_json::Value, String> {
+    let preset = RULESET_PRESETS.iter()
+        .find(|p| p.id == preset_id)
+        .ok_or_else(|| format!("Unknown preset: {}", preset_id))?;
+    
+    let mut added = Vec::new();
+    let mut errors = Vec::new();
+    for (tag, name, repo, outbound_mode) in preset.rulesets {
+        match enable_sagernet_ruleset(&app, &state, tag, name, repo, outbound_mode, None).await {
+            Ok(ruleset) => added.push(ruleset),
+            Err(e) => errors.push(serde_json::json!({ "tag": tag, "error": e })),
+        }
+    }
+    
+    Ok(serde_json::json!({
+        "success": !added.is_empty(),
+        "rulesets": added,
+        "errors": errors,
+    }))
+}
+
+/// 导入本地规则集文件（.srs 二进制或 .json source），复制到缓存目录并注册为 local 规则集
+#[tauri::command]
+pub async fn ruleset_import_file(app: AppHandle, state: State<'_, AppState>, path: String, name: Option<String>) -> Result<RuleSet, String> {
+    let source_path = Path::new(&path);
+    let ext = source_path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+        .unwrap_or_default();
+    let format = match ext.as_str() {
+        "srs" => "binary",
+        "json" => "source",
+        _ => return Err("Only .srs and .json rulesets are supported".to_string()),
+    };
+    
+    let bytes = fs::read(source_path).map_err(|e| e.to_string())?;
+    validate_ruleset_content(&bytes, format)?;
+    
+    let stem = source_path.file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("ruleset");
+    
+    let mut rulesets = load_rulesets(&state);
+    let tag = unique_ruleset_tag(&rulesets, stem);
+    
+    fs::create_dir_all(state.rulesets_cache_dir()).map_err(|e| e.to_string())?;
+    fs::write(state.ruleset_cache_file(&tag, format), bytes).map_err(|e| e.to_string())?;
+    if format == "source" {
+        let _ = compile_source_ruleset(&app, &tag).await;
+    }
+    
+    let ruleset = RuleSet {
+        id: uuid::Uuid::new_v4().to_string(),
+        tag: tag.clone(),
+        name: name.filter(|n| !n.trim().is_empty()).unwrap_or_else(|| stem.to_string()),
+        rule_type: "local".to_string(),
+        format: format.to_string(),
+        url: None,
+        outbound_mode: "proxy".to_string(),
+        outbound_value: None,
+        enabled: true,
+        is_built_in: false,
+        update_interval: 0,
+        last_updated: Some(chrono::Utc::now().timestamp_millis() as u64),
+        etag: None,
+        etag_url: None,
+        last_error: None,
+        file_size: None,
+        source_format: None,
+        schedule: None,
+        dns_server: None,
+        invert: false,
+        exceptions: Vec::new(),
+    };
+    rulesets.push(ruleset.clone());
+    save_rulesets(&state, &rulesets)?;
+    *state.rulesets.lock().await = rulesets;
+    
+    log::info!("Imported local ruleset {} from {}", tag, path);
+    Ok(ruleset)
+}
+
+/// 用当前内核执行 `sing-box rule-set compile`，把缓存中的 source JSON 编译为 .srs
+async fn compile_source_ruleset(app: &AppHandle, tag: &str) -> Result<(), String> {
+    let state = app.state::<AppState>();
+    let source_file = state.ruleset_cache_file(tag, "source");
+    let output_file = state.ruleset_cache_file(tag, "binary");
+    let tmp_file = output_file.with_extension("srs.tmp");
+    
+    let kernel_path = crate::commands::kernel::resolve_kernel_path(app).await?;
+    if !kernel_path.exists() {
+        return Err("Kernel not installed, cannot compile ruleset".to_string());
+    }
+    
+    let mut cmd = tokio::process::Command::new(&kernel_path);
+    cmd.args(["rule-set", "compile", "--output"])
+        .arg(&tmp_file)
+        .arg(&source_file);
+    #[cfg(windows)]
+    cmd.creation_flags(CREATE_NO_WINDOW);
+    
+    let output = cmd.output().await.map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        let _ = fs::remove_file(&tmp_file);
+        let err = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        log::warn!("Failed to compile ruleset {}: {}", tag, err);
+        return Err(format!("Failed to compile ruleset: {}", err));
+    }
+    
+    fs::rename(&tmp_file, &output_file).map_err(|e| e.to_string())?;
+    log::info!("Compiled source ruleset {}", tag);
+    Ok(())
+}
+
+/// 查看规则集内容：source 直接解析 JSON，.srs 先用内核反编译，返回各字段的条目和数量
+#[tauri::command]
+pub async fn ruleset_inspect(app: AppHandle, state: State<'_, AppState>, tag: String, limit: Option<usize>) -> Result<serde_json::Value, String> {
+    let source_file = state.ruleset_cache_file(&tag, "source");
+    let binary_file = state.ruleset_cache_file(&tag, "binary");
+    
+    let content = if source_file.exists() {
+        fs::read_to_string(&source_file).map_err(|e| e.to_string())?
+    } else if binary_file.exists() {
+        decompile_ruleset(&app, &binary_file).await?
+    } else {
+        return Err(format!("Ruleset {} is not cached", tag));
+    };
+    let value: serde_json::Value = serde_json::from_str(&content)
+        .map_err(|e| format!("Invalid ruleset JSON: {}", e))?;
+    
+    let mut fields: std::collections::BTreeMap<String, Vec<serde_json::Value>> = std::collections::BTreeMap::new();
+    for rule in value.get("rules").and_then(|r| r.as_array()).into_iter().flatten() {
+        collect_rule_items(rule, &mut fields);
+    }
+    
+    let limit = limit.unwrap_or(200);
+    let counts: serde_json::Map<String, serde_json::Value> = fields.iter()
+        .map(|(k, v)| (k.clone(), serde_json::Value::from(v.len())))
+        .collect();
+    let entries: serde_json::Map<String, serde_json::Value> = fields.into_iter()
+        .map(|(k, v)| (k, serde_json::Value::Array(v.into_iter().take(limit).collect())))
+        .collect();
+    let total: usize = counts.values().filter_map(|v| v.as_u64()).sum::<u64>() as usize;
+    
+    Ok(serde_json::json!({
+        "tag": tag,
+        "version": value.get("version"),
+        "total": total,
+        "counts": counts,
+        "entries": entries,
+    }))
+}
+
+/// 汇总规则中的匹配项，logical 规则递归展开
+fn collect_rule_items(rule: &serde_json::Value, fields: &mut std::collections::BTreeMap<String, Vec<serde_json::Value>>) {
+    let obj = match rule.as_object() {
+        Some(obj) => obj,
+        None => return,
+    };
+    for (key, value) in obj {
+        match (key.as_str(), value) {
+            ("rules", serde_json::Value::Array(rules)) => {
+                for sub in rules {
+                    collect_rule_items(sub, fields);
+                }
             }
-            Err(e) => {
-                log::warn!("Proxy request error: {}", e);
+            ("type" | "mode" | "invert", _) => {}
+            (_, serde_json::Value::Array(items)) => fields.entry(key.clone()).or_default().extend(items.iter().cloned()),
+            (_, item) => fields.entry(key.clone()).or_default().push(item.clone()),
+        }
+    }
+}
+
+async fn decompile_ruleset(app: &AppHandle, binary_file: &Path) -> Result<String, String> {
+    let kernel_path = crate::commands::kernel::resolve_kernel_path(app).await?;
+    if !kernel_path.exists() {
+        return Err("Kernel not installed, cannot decompile ruleset".to_string());
+    }
+    let output_file = binary_file.with_extension("decompiled.json");
+    
+    let mut cmd = tokio::process::Command::new(&kernel_path);
+    cmd.args(["rule-set", "decompile", "--output"])
+        .arg(&output_file)
+        .arg(binary_file);
+    #[cfg(windows)]
+    cmd.creation_flags(CREATE_NO_WINDOW);
+    
+    let output = cmd.output().await.map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        let _ = fs::remove_file(&output_file);
+        return Err(format!("Failed to decompile ruleset: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    
+    let content = fs::read_to_string(&output_file).map_err(|e| e.to_string());
+    let _ = fs::remove_file(&output_file);
+    content
+}
+
+/// 导出规则集配置和缓存文件为单个 zip，便于在其他机器上复用
+#[tauri::command]
+pub async fn ruleset_export_bundle(state: State<'_, AppState>, path: String) -> Result<serde_json::Value, String> {
+    use std::io::Write;
+    
+    let rulesets = load_rulesets(&state);
+    let file = fs::File::create(&path).map_err(|e| e.to_string())?;
+    let mut zip = zip::ZipWriter::new(file);
+    let options = zip::write::SimpleFileOptions::default();
+    
+    zip.start_file(BUNDLE_RULESETS_FILE, options).map_err(|e| e.to_string())?;
+    zip.write_all(serde_json::to_string_pretty(&rulesets).map_err(|e| e.to_string())?.as_bytes())
+        .map_err(|e| e.to_string())?;
+    
+    let mut files = 0;
+    for rs in &rulesets {
+        for format in ["binary", "source"] {
+            let cache_file = state.ruleset_cache_file(&rs.tag, format);
+            let Some(file_name) = cache_file.file_name().and_then(|n| n.to_str()) else { continue };
+            if let Ok(bytes) = fs::read(&cache_file) {
+                zip.start_file(format!("{}/{}", BUNDLE_CACHE_DIR, file_name), options).map_err(|e| e.to_string())?;
+                zip.write_all(&bytes).map_err(|e| e.to_string())?;
+                files += 1;
             }
         }
     }
+    zip.finish().map_err(|e| e.to_string())?;
     
-    // 2. 回退到直连
-    let resp = direct_client.get(url)
-        .header("User-Agent", "KunBox-Windows-App")
-        .send()
-        .await
-        .map_err(|e| format!("Request failed: {}", e))?;
+    log::info!("Exported {} rulesets ({} cached files) to {}", rulesets.len(), files, path);
+    Ok(serde_json::json!({ "success": true, "rulesets": rulesets.len(), "files": files }))
+}
+
+/// 导入规则集 zip：同名 tag 默认保留本机配置，replace 为 true 时覆盖
+#[tauri::command]
+pub async fn ruleset_import_bundle(state: State<'_, AppState>, path: String, replace: Option<bool>) -> Result<serde_json::Value, String> {
+    use std::io::Read;
+    
+    let file = fs::File::open(&path).map_err(|e| e.to_string())?;
+    let mut archive = zip::ZipArchive::new(file).map_err(|e| e.to_string())?;
+    
+    let incoming: Vec<RuleSet> = {
+        let mut entry = archive.by_name(BUNDLE_RULESETS_FILE).map_err(|_| "Not a ruleset bundle".to_string())?;
+        let mut content = String::new();
+        entry.read_to_string(&mut content).map_err(|e| e.to_string())?;
+        serde_json::from_str(&content).map_err(|e| format!("Invalid rulesets.json: {}", e))?
+    };
     
-    if !resp.status().is_success() {
-        return Err(format!("HTTP {}", resp.status()));
+    let replace = replace.unwrap_or(false);
+    let mut rulesets = load_rulesets(&state);
+    // 压缩包中的 tag -> 本机使用的 tag；tag 会拼进缓存路径，不合法的换成安全的新 tag
+    let mut imported_tags = std::collections::HashMap::new();
+    for rs in incoming {
+        let bundle_tag = rs.tag.clone();
+        if !is_valid_ruleset_tag(&bundle_tag) {
+            let tag = unique_ruleset_tag(&rulesets, &bundle_tag);
+            log::warn!("Renamed invalid ruleset tag {:?} from bundle to {}", bundle_tag, tag);
+            rulesets.push(RuleSet { id: uuid::Uuid::new_v4().to_string(), tag: tag.clone(), ..rs });
+            imported_tags.insert(bundle_tag, tag);
+            continue;
+        }
+        match rulesets.iter().position(|r| r.tag == rs.tag) {
+            Some(_) if !replace => continue,
+            Some(idx) => rulesets[idx] = RuleSet { id: rulesets[idx].id.clone(), ..rs },
+            None => rulesets.push(RuleSet { id: uuid::Uuid::new_v4().to_string(), ..rs }),
+        }
+        imported_tags.insert(bundle_tag.clone(), bundle_tag);
     }
     
-    let data = resp.json::<serde_json::Value>()
-        .await
-        .map_err(|e| format!("Parse error: {}", e))?;
+    let cache_dir = state.rulesets_cache_dir();
+    fs::create_dir_all(&cache_dir).map_err(|e| e.to_string())?;
+    let mut files = 0;
+    for i in 0..archive.len() {
+        let entry = archive.by_index(i).map_err(|e| e.to_string())?;
+        // 只接受 rulesets/ 下属于已导入 tag 的 .srs / .json 文件，防止路径穿越
+        let Some(entry_path) = entry.enclosed_name() else { continue };
+        if !entry.is_file() || entry_path.parent() != Some(Path::new(BUNDLE_CACHE_DIR)) || entry.size() > BUNDLE_MAX_FILE_SIZE {
+            continue;
+        }
+        let Some(file_name) = entry_path.file_name().and_then(|n| n.to_str()).map(|n| n.to_string()) else { continue };
+        let Some((bundle_tag, ext)) = file_name.rsplit_once('.') else { continue };
+        let format = match ext {
+            "srs" => "binary",
+            "json" => "source",
+            _ => continue,
+        };
+        let Some(tag) = imported_tags.get(bundle_tag) else { continue };
+        
+        // 大小头可能不实，多读一个字节判断是否超限，而不是静默截断
+        let mut bytes = Vec::new();
+        entry.take(BUNDLE_MAX_FILE_SIZE + 1).read_to_end(&mut bytes).map_err(|e| e.to_string())?;
+        if bytes.len() as u64 > BUNDLE_MAX_FILE_SIZE {
+            return Err(format!("Cached ruleset {} in bundle is too large", file_name));
+        }
+        validate_ruleset_content(&bytes, format).map_err(|e| format!("Invalid cached ruleset {} in bundle: {}", file_name, e))?;
+        write_ruleset_cache(&state.ruleset_cache_file(tag, format), &bytes)?;
+        files += 1;
+    }
+    
+    save_rulesets(&state, &rulesets)?;
+    *state.rulesets.lock().await = rulesets;
+    
+    log::info!("Imported {} rulesets ({} cached files) from {}", imported_tags.len(), files, path);
+    Ok(serde_json::json!({ "success": true, "rulesets": imported_tags.len(), "files": files }))
+}
+
+/// 校验规则集内容：binary 以 "SRS" 魔数开头，source 需为包含 rules 数组的 JSON
+fn validate_ruleset_content(bytes: &[u8], format: &str) -> Result<(), String> {
+    if format == "binary" {
+        if !bytes.starts_with(b"SRS") {
+            return Err("Not a valid sing-box binary ruleset (.srs)".to_string());
+        }
+        return Ok(());
+    }
+    let value: serde_json::Value = serde_json::from_slice(bytes)
+        .map_err(|e| format!("Invalid ruleset JSON: {}", e))?;
+    if !value.get("rules").map(|r| r.is_array()).unwrap_or(false) {
+        return Err("Ruleset JSON has no rules array".to_string());
+    }
+    Ok(())
+}
+
+/// tag 会作为缓存文件名，只允许小写字母、数字、- 和 _，以及 SagerNet 命名中的 @ 和 !（如 geosite-steam@cn）
+fn is_valid_ruleset_tag(tag: &str) -> bool {
+    !tag.is_empty() && tag.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '-' | '_' | '@' | '!'))
+}
+
+/// 由文件名生成 tag，与已有规则集重名时追加序号
+fn unique_ruleset_tag(rulesets: &[RuleSet], stem: &str) -> String {
+    let base: String = stem.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c.to_ascii_lowercase() } else { '-' })
+        .collect();
+    let base = if base.trim_matches('-').is_empty() { "local-ruleset".to_string() } else { base };
+    
+    let mut tag = base.clone();
+    let mut index = 2;
+    while rulesets.iter().any(|r| r.tag == tag) {
+        tag = format!("{}-{}", base, index);
+        index += 1;
+    }
+    tag
+}
+
+struct HubSource {
+    id: &'static str,
+    repo: &'static str,
+    branch: &'static str,
+    /// 规则集文件所在目录前缀
+    prefix: &'static str,
+}
+
+// 规则集仓库：SagerNet 官方 geosite/geoip，以及 MetaCubeX 的 meta-rules-dat
+const HUB_SOURCES: &[HubSource] = &[
+    HubSource { id: "sing-geosite", repo: "SagerNet/sing-geosite", branch: "rule-set", prefix: "" },
+    HubSource { id: "sing-geoip", repo: "SagerNet/sing-geoip", branch: "rule-set", prefix: "" },
+    HubSource { id: "metacubex", repo: "MetaCubeX/meta-rules-dat", branch: "sing", prefix: "geo/" },
+];
+
+/// 获取规则集仓库列表，支持按来源、分类（geosite / geoip 等）过滤和分页
+#[tauri::command]
+pub async fn ruleset_fetch_hub(
+    state: State<'_, AppState>,
+    source: Option<String>,
+    category: Option<String>,
+    page: Option<usize>,
+    page_size: Option<usize>,
+) -> Result<serde_json::Value, String> {
+    let source_id = source.unwrap_or_else(|| "sing-geosite".to_string());
+    let hub = HUB_SOURCES.iter()
+        .find(|h| h.id == source_id)
+        .ok_or_else(|| format!("Unknown ruleset hub: {}", source_id))?;
+    let url = format!("https://api.github.com/repos/{}/git/trees/{}?recursive=1", hub.repo, hub.branch);
+    let token = state.settings.lock().await.github_token.clone();
+    
+    let clients = crate::commands::kernel::build_http_clients(&state, 15).await?;
+    let data = fetch_hub_tree(&clients, &url, token).await?;
+    let base_url = format!("https://raw.githubusercontent.com/{}/{}", hub.repo, hub.branch);
+    
+    let mut entries: Vec<serde_json::Value> = Vec::new();
+    for item in data.get("tree").and_then(|t| t.as_array()).into_iter().flatten() {
+        let path = match item.get("path").and_then(|p| p.as_str()) {
+            Some(path) => path,
+            None => continue,
+        };
+        if item.get("type").and_then(|t| t.as_str()) != Some("blob") || !path.ends_with(".srs") || !path.starts_with(hub.prefix) {
+            continue;
+        }
+        
+        let relative = &path[hub.prefix.len()..];
+        let (dir, file) = relative.rsplit_once('/').unwrap_or(("", relative));
+        let name = file.trim_end_matches(".srs");
+        // 官方仓库按文件名前缀区分，MetaCubeX 按目录区分
+        let entry_category = if !dir.is_empty() {
+            dir.to_string()
+        } else if name.starts_with("geoip-") {
+            "geoip".to_string()
+        } else {
+            "geosite".to_string()
+        };
+        if category.as_deref().is_some_and(|c| c != entry_category) {
+            continue;
+        }
+        
+        entries.push(serde_json::json!({
+            "type": "blob",
+            "path": path,
+            "name": name,
+            "category": entry_category,
+            "url": format!("{}/{}", base_url, path),
+        }));
+    }
     
-    log::info!("Fetched hub via direct");
-    Ok(data)
+    let total = entries.len();
+    let (page, page_size) = match (page, page_size) {
+        (Some(page), Some(size)) if size > 0 => (page.max(1), size),
+        _ => (1, total.max(1)),
+    };
+    let tree: Vec<serde_json::Value> = entries.into_iter()
+        .skip((page - 1) * page_size)
+        .take(page_size)
+        .collect();
+    
+    Ok(serde_json::json!({
+        "source": hub.id,
+        "baseUrl": base_url,
+        "tree": tree,
+        "total": total,
+        "page": page,
+        "pageSize": page_size,
+        "truncated": data.get("truncated").and_then(|t| t.as_bool()).unwrap_or(false),
+    }))
+}
+
+/// 从 GitHub API 获取仓库文件树（代理优先 + 直连回退）
+async fn fetch_hub_tree(clients: &[(&str, reqwest::Client)], url: &str, token: Option<String>) -> Result<serde_json::Value, String> {
+    let mut last_error = String::new();
+    for (via, client) in clients {
+        match crate::github::api_get(client, url, token.as_deref()).send().await {
+            Ok(resp) if resp.status().is_success() => match resp.json::<serde_json::Value>().await {
+                Ok(data) => {
+                    log::info!("Fetched hub via {}", via);
+                    return Ok(data);
+                }
+                Err(e) => last_error = format!("Parse error: {}", e),
+            },
+            Ok(resp) => last_error = format!("HTTP {}", resp.status()),
+            Err(e) => last_error = format!("Request failed: {}", e),
+        }
+        log::warn!("Hub request via {} failed: {}", via, last_error);
+    }
+    Err(last_error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ruleset_tags_allow_sagernet_naming() {
+        assert!(is_valid_ruleset_tag("geosite-steam@cn"));
+        assert!(is_valid_ruleset_tag("geosite-geolocation-!cn"));
+        assert!(!is_valid_ruleset_tag("../geosite-cn"));
+        assert!(!is_valid_ruleset_tag("GeoSite"));
+    }
+
+    #[test]
+    fn blocklist_skips_cosmetic_and_modifier_rules() {
+        let list = b"! AdGuard\n||ads.example.com^\nexample.com##.ad-banner\nexample.org#@#.x\nshop.example#?#div\n||tracker.example^$third-party\n0.0.0.0 bad.example # hosts comment\n# comment\nplain.example\n";
+        let converted: serde_json::Value = serde_json::from_slice(&convert_blocklist(list).unwrap()).unwrap();
+        assert_eq!(converted["rules"], serde_json::json!([
+            { "domain": ["bad.example", "plain.example"] },
+            { "domain_suffix": ["ads.example.com"] },
+        ]));
+    }
 }
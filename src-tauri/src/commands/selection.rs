@@ -0,0 +1,94 @@
+use tauri::{AppHandle, Emitter, Manager};
+use serde::{Deserialize, Serialize};
+use crate::state::AppState;
+use crate::types::ProxyState;
+
+/// 返回的排名默认包含的节点数
+const DEFAULT_TOP: usize = 5;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeSelectCriteria {
+    /// 只在节点名匹配该正则的节点中选择，不区分大小写，如 "香港|HK"
+    #[serde(default)]
+    pub region: Option<String>,
+    /// 结果中附带的前 N 名
+    #[serde(default)]
+    pub top: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RankedNode {
+    pub tag: String,
+    pub latency: i64,
+    /// 稳定性评分，没有历史记录时为 None
+    pub score: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeSelection {
+    pub selected: String,
+    pub latency: i64,
+    /// 按等效延迟从低到高的前 N 名，第一个就是 selected
+    pub ranking: Vec<RankedNode>,
+}
+
+/// 当前配置中最近一次测试可用的节点，按延迟历史的等效延迟从低到高排列，
+/// 只快过一次但经常超时或波动大的节点排在稳定的节点之后；没测过或最近一次超时的不参与
+pub(crate) async fn rank_nodes(state: &AppState, region: Option<&regex::Regex>) -> Vec<RankedNode> {
+    let Some(profile_id) = crate::commands::profiles::load_profiles_data(state).active_profile_id else {
+        return Vec::new();
+    };
+    let latencies = state.node_latency.lock().await.clone();
+    let scores = crate::commands::profiles::node_scores(state).await;
+    let mut ranked: Vec<(i64, RankedNode)> = crate::commands::profiles::load_profile_nodes(state, &profile_id).into_iter()
+        .filter_map(|node| node.tag)
+        .filter(|tag| region.map_or(true, |re| re.is_match(tag)))
+        .filter_map(|tag| {
+            let latency = latencies.get(&tag).copied().filter(|latency| *latency >= 0)?;
+            let score = scores.get(&tag);
+            let key = score.map_or(latency, |score| score.effective_latency());
+            Some((key, RankedNode { score: score.map(|s| s.score), tag, latency }))
+        })
+        .collect();
+    ranked.sort_by_key(|(key, _)| *key);
+    ranked.into_iter().map(|(_, node)| node).collect()
+}
+
+/// 切换到指定节点：连接中走 singbox_switch_node 立即生效，并保存为当前节点
+pub(crate) async fn activate_node(app: &AppHandle, tag: &str) -> Result<(), String> {
+    let state = app.state::<AppState>();
+    if matches!(*state.proxy_state.lock().await, ProxyState::Connected) {
+        let result = crate::commands::singbox_switch_node(state.clone(), tag.to_string()).await?;
+        if !result.success {
+            return Err(result.error.unwrap_or_default());
+        }
+    }
+    crate::commands::node_set_active(state, tag.to_string()).await
+}
+
+/// 选出延迟最低的节点并切换过去，供命令、托盘和快捷键共用
+pub(crate) async fn select_best_node(app: &AppHandle, criteria: &NodeSelectCriteria) -> Result<NodeSelection, String> {
+    let region = criteria.region.as_deref().map(str::trim).filter(|r| !r.is_empty())
+        .map(|r| regex::Regex::new(&format!("(?i){}", r)).map_err(|e| format!("Invalid region pattern {}: {}", r, e)))
+        .transpose()?;
+    let state = app.state::<AppState>();
+    let mut ranking = rank_nodes(&state, region.as_ref()).await;
+    let best = ranking.first().cloned().ok_or_else(|| crate::i18n::tr("error.noTestedNodes").to_string())?;
+
+    activate_node(app, &best.tag).await?;
+    log::info!("Selected fastest node {} ({} ms)", best.tag, best.latency);
+    ranking.truncate(criteria.top.unwrap_or(DEFAULT_TOP).max(1));
+    let selection = NodeSelection { selected: best.tag, latency: best.latency, ranking };
+    let _ = app.emit("node:best-selected", &selection);
+    crate::tray::refresh_tray_menu(app);
+    Ok(selection)
+}
+
+/// 按最近的测速结果切换到最快的节点，可用 region 正则限定范围
+#[tauri::command]
+pub async fn node_select_best(app: AppHandle, criteria: Option<NodeSelectCriteria>) -> Result<NodeSelection, String> {
+    select_best_node(&app, &criteria.unwrap_or_default()).await
+}
@@ -0,0 +1,66 @@
+use tauri::{AppHandle, State};
+use std::fs;
+use crate::state::AppState;
+use crate::types::{CommandResult, SessionState};
+
+pub(crate) fn load_session(state: &AppState) -> Option<SessionState> {
+    let content = fs::read_to_string(state.session_file()).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+/// 记录当前会话；node_tag 为 None 时沿用配置中选中的节点
+pub(crate) async fn record_session(state: &AppState, connected: bool, node_tag: Option<String>) {
+    let (profile_id, active_node) = {
+        let data = state.profiles_data.lock().await;
+        (data.active_profile_id.clone(), data.active_node_tag.clone())
+    };
+    let session = SessionState {
+        connected,
+        profile_id,
+        node_tag: node_tag.or(active_node),
+        routing_mode: state.settings.lock().await.routing_mode.clone(),
+        updated_at: chrono::Utc::now().timestamp_millis() as u64,
+    };
+    save_session(state, &session);
+}
+
+pub(crate) fn save_session(state: &AppState, session: &SessionState) {
+    let written = serde_json::to_string_pretty(session)
+        .map_err(|e| e.to_string())
+        .and_then(|content| fs::write(state.session_file(), content).map_err(|e| e.to_string()));
+    if let Err(e) = written {
+        log::warn!("Failed to record session: {}", e);
+    }
+}
+
+/// 恢复上次会话选中的配置和节点，配置已被删除时保持当前选择
+pub(crate) async fn restore_session_selection(state: &AppState, session: &SessionState) -> Result<(), String> {
+    let mut data = crate::commands::profiles::load_profiles_data(state);
+    let Some(profile_id) = session.profile_id.as_ref().filter(|id| data.profiles.iter().any(|p| &p.id == *id)) else {
+        *state.profiles_data.lock().await = data;
+        return Ok(());
+    };
+    if data.active_profile_id.as_ref() != Some(profile_id) || data.active_node_tag != session.node_tag {
+        data.active_profile_id = Some(profile_id.clone());
+        data.active_node_tag = session.node_tag.clone();
+        crate::commands::profiles::save_profiles_data(state, &data)?;
+    }
+    *state.profiles_data.lock().await = data;
+    Ok(())
+}
+
+/// 上次退出时仍在连接的会话，用于前端询问是否恢复
+#[tauri::command]
+pub async fn session_get_last(state: State<'_, AppState>) -> Result<Option<SessionState>, String> {
+    Ok(load_session(&state).filter(|s| s.connected))
+}
+
+/// 按上次会话的配置和节点重新连接
+#[tauri::command]
+pub async fn session_resume(app: AppHandle, state: State<'_, AppState>) -> Result<CommandResult, String> {
+    let Some(session) = load_session(&state).filter(|s| s.connected) else {
+        return Ok(CommandResult::err("No session to resume"));
+    };
+    restore_session_selection(&state, &session).await?;
+    crate::commands::singbox_start(app, state).await
+}
@@ -1,14 +1,14 @@
-use tauri::State;
+use tauri::{AppHandle, Emitter, Manager, State};
 use std::fs;
+use crate::migrate::{self, DataFile};
 use crate::state::AppState;
-use crate::types::AppSettings;
+use crate::types::{AppSettings, HotkeySettings, KernelSource, NetworkProfile, ProxyState, SettingsError, SettingsFieldError};
 
 #[tauri::command]
 pub async fn get_settings(state: State<'_, AppState>) -> Result<AppSettings, String> {
     let file = state.settings_file();
     if file.exists() {
-        let content = fs::read_to_string(&file).map_err(|e| e.to_string())?;
-        let settings: AppSettings = serde_json::from_str(&content).unwrap_or_default();
+        let settings: AppSettings = migrate::load(&file, DataFile::Settings).unwrap_or_default();
         *state.settings.lock().await = settings.clone();
         Ok(settings)
     } else {
@@ -17,39 +17,466 @@ pub async fn get_settings(state: State<'_, AppState>) -> Result<AppSettings, Str
     }
 }
 
+/// 校验 CIDR 列表，单个地址补全为 /32 或 /128
+fn parse_cidr_list(values: &[serde_json::Value]) -> Result<Vec<String>, String> {
+    values.iter()
+        .filter_map(|v| v.as_str())
+        .map(|v| v.trim())
+        .filter(|v| !v.is_empty())
+        .map(|v| {
+            let (ip, prefix) = v.split_once('/').map(|(ip, p)| (ip, Some(p))).unwrap_or((v, None));
+            let ip: std::net::IpAddr = ip.parse().map_err(|_| format!("Invalid CIDR: {}", v))?;
+            let max = if ip.is_ipv4() { 32 } else { 128 };
+            let prefix = match prefix {
+                Some(p) => p.parse::<u8>().ok().filter(|p| *p <= max).ok_or_else(|| format!("Invalid CIDR: {}", v))?,
+                None => max,
+            };
+            Ok(format!("{}/{}", ip, prefix))
+        })
+        .collect()
+}
+
+/// sing-box 支持的 DNS 地址：IP（可带端口）、local，或 udp / tcp / tls / https / quic / h3 / dhcp URL
+fn validate_dns_server(value: &str) -> Result<(), String> {
+    if value == "local" || value.parse::<std::net::IpAddr>().is_ok() || value.parse::<std::net::SocketAddr>().is_ok() {
+        return Ok(());
+    }
+    let url = reqwest::Url::parse(value).map_err(|_| format!("Invalid DNS server: {}", value))?;
+    match url.scheme() {
+        "dhcp" => Ok(()),
+        "udp" | "tcp" | "tls" | "https" | "quic" | "h3" if url.host_str().is_some_and(|h| !h.is_empty()) => Ok(()),
+        _ => Err(format!("Invalid DNS server: {}", value)),
+    }
+}
+
+/// 逐项读取前端传入的部分设置，类型或取值不对的记录到 errors 而不是直接忽略
+struct SettingsPatch<'a> {
+    obj: &'a serde_json::Map<String, serde_json::Value>,
+    errors: Vec<SettingsFieldError>,
+}
+
+impl<'a> SettingsPatch<'a> {
+    fn fail(&mut self, field: &str, message: impl Into<String>) {
+        self.errors.push(SettingsFieldError { field: field.to_string(), message: message.into() });
+    }
+
+    fn bool(&mut self, field: &str) -> Option<bool> {
+        let value = self.obj.get(field)?.as_bool();
+        if value.is_none() {
+            self.fail(field, "Expected true or false");
+        }
+        value
+    }
+
+    fn str(&mut self, field: &str) -> Option<&'a str> {
+        let value = self.obj.get(field)?.as_str();
+        if value.is_none() {
+            self.fail(field, "Expected a string");
+        }
+        value
+    }
+
+    fn array(&mut self, field: &str) -> Option<&'a [serde_json::Value]> {
+        let value = self.obj.get(field)?.as_array();
+        if value.is_none() {
+            self.fail(field, "Expected a list");
+        }
+        value.map(Vec::as_slice)
+    }
+
+    fn number(&mut self, field: &str, range: std::ops::RangeInclusive<u64>) -> Option<u64> {
+        let value = self.obj.get(field)?.as_u64().filter(|v| range.contains(v));
+        if value.is_none() {
+            self.fail(field, format!("Must be a number between {} and {}", range.start(), range.end()));
+        }
+        value
+    }
+
+    fn one_of(&mut self, field: &str, allowed: &[&str]) -> Option<String> {
+        let value = self.str(field)?;
+        if !allowed.contains(&value) {
+            self.fail(field, format!("Must be one of {}: {}", allowed.join(", "), value));
+            return None;
+        }
+        Some(value.to_string())
+    }
+
+    fn cidr_list(&mut self, field: &str) -> Option<Vec<String>> {
+        match parse_cidr_list(self.array(field)?) {
+            Ok(list) => Some(list),
+            Err(e) => {
+                self.fail(field, e);
+                None
+            }
+        }
+    }
+
+    fn dns_server(&mut self, field: &str) -> Option<String> {
+        let value = self.str(field)?.trim();
+        match validate_dns_server(value) {
+            Ok(()) => Some(value.to_string()),
+            Err(e) => {
+                self.fail(field, e);
+                None
+            }
+        }
+    }
+}
+
+/// 可选的路由模式
+pub(crate) const ROUTING_MODES: [&str; 3] = ["rule", "global-proxy", "global-direct"];
+
+/// 不影响运行中内核的设置项
+const SETTINGS_NO_CORE_EFFECT: &[&str] = &[
+    "theme", "autoConnect", "resumeSession", "minimizeToTray", "startWithWindows", "startMinimized", "exitOnClose",
+    "latencyTestTimeout", "latencyTestConcurrency", "latencyTestProbes", "failoverEnabled", "failoverThreshold", "githubToken", "githubMirrors", "kernelSource",
+    "encryptData", "hotkeys", "language", "preventSleep", "trayIconStyle", "confirmQuit",
+    "bossKeyMute", "windowEffect",
+];
+/// 只需重新设置系统代理 / WinHTTP 代理的设置项；networkProfiles 可覆盖当前网络的 systemProxy
+const SETTINGS_PROXY_ONLY: &[&str] = &["systemProxy", "proxyBypass", "proxyGuardMode", "winHttpProxy", "networkProfiles"];
+
+/// 设置变化后让运行中的内核保持同步所需的最小操作，按代价从小到大排列
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+enum SettingsAction {
+    None,
+    /// 通过 Clash API 切换模式
+    Mode,
+    /// 重新应用系统代理
+    Proxy,
+    /// 重新生成配置并重启内核
+    Restart,
+}
+
+/// 对比保存前后的设置，返回变化的设置项和需要执行的操作；未归类的设置项一律按需要重启处理
+fn diff_settings(old: &AppSettings, new: &AppSettings) -> (Vec<String>, SettingsAction) {
+    let (Ok(serde_json::Value::Object(old)), Ok(serde_json::Value::Object(new))) =
+        (serde_json::to_value(old), serde_json::to_value(new))
+    else {
+        return (Vec::new(), SettingsAction::Restart);
+    };
+    let changed: Vec<String> = new.iter()
+        .filter(|(key, value)| old.get(key.as_str()) != Some(value))
+        .map(|(key, _)| key.clone())
+        .collect();
+    let action = changed.iter()
+        .map(|key| match key.as_str() {
+            k if SETTINGS_NO_CORE_EFFECT.contains(&k) => SettingsAction::None,
+            k if SETTINGS_PROXY_ONLY.contains(&k) => SettingsAction::Proxy,
+            "routingMode" => SettingsAction::Mode,
+            _ => SettingsAction::Restart,
+        })
+        .max()
+        .unwrap_or(SettingsAction::None);
+    (changed, action)
+}
+
+/// 内核运行中时执行设置变化所需的最小操作，并通知前端实际做了什么
+async fn apply_settings_change(app: &AppHandle, state: &AppState, previous: &AppSettings) {
+    let current = state.settings.lock().await.clone();
+    if previous.network_profiles != current.network_profiles {
+        crate::commands::network::refresh_network_profile(state, &current.network_profiles).await;
+    }
+    if !matches!(*state.proxy_state.lock().await, ProxyState::Connected) {
+        return;
+    }
+    if previous.prevent_sleep != current.prevent_sleep {
+        crate::commands::singbox::sync_sleep_inhibitor(state).await;
+    }
+    let (changed, action) = diff_settings(previous, &current);
+    let result = match action {
+        SettingsAction::None => Ok(()),
+        SettingsAction::Mode => crate::commands::singbox::set_clash_mode(&current.routing_mode).await,
+        SettingsAction::Proxy => crate::commands::singbox::reapply_proxy_settings(app, state).await,
+        SettingsAction::Restart => {
+            let state = app.state::<AppState>();
+            match crate::commands::singbox_restart(app.clone(), state).await {
+                Ok(result) if !result.success => Err(result.error.unwrap_or_default()),
+                Ok(_) => Ok(()),
+                Err(e) => Err(e),
+            }
+        }
+    };
+    if let Err(e) = &result {
+        log::warn!("Failed to apply settings change ({:?}): {}", action, e);
+    }
+    let _ = app.emit("settings:applied", serde_json::json!({
+        "action": action,
+        "changed": changed,
+        "error": result.err(),
+    }));
+}
+
 #[tauri::command]
-pub async fn set_settings(state: State<'_, AppState>, settings: serde_json::Value) -> Result<(), String> {
+pub async fn set_settings(app: AppHandle, state: State<'_, AppState>, settings: serde_json::Value) -> Result<(), SettingsError> {
     // Get current settings
-    let mut current = state.settings.lock().await.clone();
-    
+    let previous = state.settings.lock().await.clone();
+    let mut current = previous.clone();
+
+    let Some(obj) = settings.as_object() else {
+        return Err("Settings must be an object".to_string().into());
+    };
+
     // Merge with incoming partial settings
-    if let Some(obj) = settings.as_object() {
-        if let Some(v) = obj.get("localPort").and_then(|v| v.as_u64()) { current.local_port = v as u16; }
-        if let Some(v) = obj.get("socksPort").and_then(|v| v.as_u64()) { current.socks_port = v as u16; }
-        if let Some(v) = obj.get("allowLan").and_then(|v| v.as_bool()) { current.allow_lan = v; }
-        if let Some(v) = obj.get("systemProxy").and_then(|v| v.as_bool()) { current.system_proxy = v; }
-        if let Some(v) = obj.get("tunEnabled").and_then(|v| v.as_bool()) { current.tun_enabled = v; }
-        if let Some(v) = obj.get("tunStack").and_then(|v| v.as_str()) { current.tun_stack = v.to_string(); }
-        if let Some(v) = obj.get("localDns").and_then(|v| v.as_str()) { current.local_dns = v.to_string(); }
-        if let Some(v) = obj.get("remoteDns").and_then(|v| v.as_str()) { current.remote_dns = v.to_string(); }
-        if let Some(v) = obj.get("fakeDns").and_then(|v| v.as_bool()) { current.fake_dns = v; }
-        if let Some(v) = obj.get("blockAds").and_then(|v| v.as_bool()) { current.block_ads = v; }
-        if let Some(v) = obj.get("bypassLan").and_then(|v| v.as_bool()) { current.bypass_lan = v; }
-        if let Some(v) = obj.get("routingMode").and_then(|v| v.as_str()) { current.routing_mode = v.to_string(); }
-        if let Some(v) = obj.get("defaultRule").and_then(|v| v.as_str()) { current.default_rule = v.to_string(); }
-        if let Some(v) = obj.get("latencyTestUrl").and_then(|v| v.as_str()) { current.latency_test_url = v.to_string(); }
-        if let Some(v) = obj.get("latencyTestTimeout").and_then(|v| v.as_u64()) { current.latency_test_timeout = v as u32; }
-        if let Some(v) = obj.get("autoConnect").and_then(|v| v.as_bool()) { current.auto_connect = v; }
-        if let Some(v) = obj.get("minimizeToTray").and_then(|v| v.as_bool()) { current.minimize_to_tray = v; }
-        if let Some(v) = obj.get("startWithWindows").and_then(|v| v.as_bool()) { current.start_with_windows = v; }
-        if let Some(v) = obj.get("startMinimized").and_then(|v| v.as_bool()) { current.start_minimized = v; }
-        if let Some(v) = obj.get("exitOnClose").and_then(|v| v.as_bool()) { current.exit_on_close = v; }
-        if let Some(v) = obj.get("theme").and_then(|v| v.as_str()) { current.theme = v.to_string(); }
-    }
-    
-    fs::create_dir_all(&state.data_dir).map_err(|e| e.to_string())?;
-    let content = serde_json::to_string_pretty(&current).map_err(|e| e.to_string())?;
-    fs::write(state.settings_file(), content).map_err(|e| e.to_string())?;
-    *state.settings.lock().await = current;
+    let mut patch = SettingsPatch { obj, errors: Vec::new() };
+    if let Some(v) = patch.number("localPort", 1..=65535) { current.local_port = v as u16; }
+    if let Some(v) = patch.number("socksPort", 1..=65535) { current.socks_port = v as u16; }
+    if let Some(v) = patch.bool("allowLan") { current.allow_lan = v; }
+    if let Some(v) = obj.get("listenInterface") {
+        current.listen_interface = v.as_str().map(|t| t.trim().to_string()).filter(|t| !t.is_empty());
+    }
+    if let Some(v) = patch.bool("systemProxy") { current.system_proxy = v; }
+    if let Some(v) = patch.bool("tunEnabled") { current.tun_enabled = v; }
+    if let Some(v) = patch.one_of("tunStack", &["system", "gvisor", "mixed"]) { current.tun_stack = v; }
+    if let Some(v) = patch.number("tunMtu", 576..=65535) { current.tun_mtu = v as u32; }
+    if let Some(v) = patch.bool("tunStrictRoute") { current.tun_strict_route = v; }
+    if let Some(v) = patch.cidr_list("tunRouteAddress") { current.tun_route_address = v; }
+    if let Some(v) = patch.cidr_list("tunRouteExcludeAddress") { current.tun_route_exclude_address = v; }
+    if let Some(v) = patch.dns_server("localDns") { current.local_dns = v; }
+    if let Some(v) = patch.dns_server("remoteDns") { current.remote_dns = v; }
+    if let Some(v) = patch.bool("fakeDns") { current.fake_dns = v; }
+    if let Some(v) = patch.bool("blockAds") { current.block_ads = v; }
+    if let Some(v) = patch.bool("bypassLan") { current.bypass_lan = v; }
+    if let Some(v) = patch.one_of("routingMode", &ROUTING_MODES) { current.routing_mode = v; }
+    if let Some(v) = patch.one_of("defaultRule", &["proxy", "direct", "block"]) { current.default_rule = v; }
+    if let Some(v) = patch.str("latencyTestUrl") {
+        let v = v.trim();
+        match reqwest::Url::parse(v) {
+            Ok(url) if matches!(url.scheme(), "http" | "https") => current.latency_test_url = v.to_string(),
+            _ => patch.fail("latencyTestUrl", format!("Invalid URL: {}", v)),
+        }
+    }
+    if let Some(v) = patch.number("latencyTestTimeout", 100..=60000) { current.latency_test_timeout = v as u32; }
+    if let Some(v) = patch.number("latencyTestConcurrency", 1..=64) { current.latency_test_concurrency = v as u32; }
+    if let Some(v) = patch.number("latencyTestProbes", 1..=20) { current.latency_test_probes = v as u32; }
+    if let Some(v) = patch.bool("failoverEnabled") { current.failover_enabled = v; }
+    if let Some(v) = patch.number("failoverThreshold", 1..=10) { current.failover_threshold = v as u32; }
+    if let Some(v) = patch.bool("autoConnect") { current.auto_connect = v; }
+    if let Some(v) = patch.one_of("resumeSession", &["auto", "ask", "off"]) { current.resume_session = v; }
+    if let Some(v) = patch.bool("minimizeToTray") { current.minimize_to_tray = v; }
+    if let Some(v) = patch.one_of("trayIconStyle", &["color", "mono"]) { current.tray_icon_style = v; }
+    if let Some(v) = patch.one_of("windowEffect", &crate::commands::window::WINDOW_EFFECTS) { current.window_effect = v; }
+    if let Some(v) = patch.bool("preventSleep") { current.prevent_sleep = v; }
+    if let Some(v) = patch.bool("startWithWindows") { current.start_with_windows = v; }
+    if let Some(v) = patch.bool("startMinimized") { current.start_minimized = v; }
+    if let Some(v) = patch.bool("exitOnClose") { current.exit_on_close = v; }
+    if let Some(v) = patch.bool("confirmQuit") { current.confirm_quit = v; }
+    if let Some(v) = patch.bool("bossKeyMute") { current.boss_key_mute = v; }
+    if let Some(v) = patch.one_of("theme", &["dark", "light", "system"]) { current.theme = v; }
+    if let Some(v) = patch.one_of("language", &crate::i18n::LANGUAGES) { current.language = v; }
+    if let Some(v) = patch.bool("remoteRuleSets") { current.remote_rule_sets = v; }
+    if let Some(v) = patch.bool("winHttpProxy") { current.winhttp_proxy = v; }
+    if let Some(v) = patch.bool("encryptData") {
+        if v && !cfg!(windows) {
+            patch.fail("encryptData", "Data encryption is only available on Windows");
+        } else {
+            current.encrypt_data = v;
+        }
+    }
+    if let Some(v) = patch.str("proxyBypass") {
+        // 兼容逗号、换行分隔的输入，统一保存为分号分隔
+        current.proxy_bypass = v.split([';', ',', '\n'])
+            .map(|entry| entry.trim())
+            .filter(|entry| !entry.is_empty())
+            .collect::<Vec<_>>()
+            .join(";");
+    }
+    if let Some(v) = patch.one_of("proxyGuardMode", &["reassert", "notify", "off"]) { current.proxy_guard_mode = v; }
+    if let Some(v) = patch.array("githubMirrors") {
+        match v.iter()
+            .filter_map(|m| m.as_str())
+            .map(crate::commands::mirrors::normalize_mirror)
+            .collect::<Result<Vec<_>, _>>()
+        {
+            Ok(mirrors) => current.github_mirrors = mirrors,
+            Err(e) => patch.fail("githubMirrors", e),
+        }
+    }
+    if let Some(v) = obj.get("networkProfiles") {
+        match serde_json::from_value::<Vec<NetworkProfile>>(v.clone()) {
+            Ok(mut profiles) => {
+                let mut valid = true;
+                for profile in &mut profiles {
+                    profile.ssid = profile.ssid.as_deref().map(str::trim).filter(|s| !s.is_empty()).map(String::from);
+                    profile.adapter = profile.adapter.as_deref().map(str::trim).filter(|s| !s.is_empty()).map(String::from);
+                    if profile.ssid.is_none() && profile.adapter.is_none() {
+                        patch.fail("networkProfiles", format!("Network profile {} needs an SSID or adapter", profile.name));
+                        valid = false;
+                    }
+                    if profile.id.is_empty() {
+                        profile.id = uuid::Uuid::new_v4().to_string();
+                    }
+                }
+                if valid {
+                    current.network_profiles = profiles;
+                }
+            }
+            Err(e) => patch.fail("networkProfiles", e.to_string()),
+        }
+    }
+    if let Some(v) = obj.get("hotkeys") {
+        match serde_json::from_value::<HotkeySettings>(v.clone()) {
+            Ok(hotkeys) => match crate::commands::hotkeys::parse_hotkeys(&hotkeys) {
+                Ok(_) => current.hotkeys = hotkeys,
+                Err(errors) => {
+                    for (action, message) in errors {
+                        patch.fail(&format!("hotkeys.{}", action), message);
+                    }
+                }
+            },
+            Err(e) => patch.fail("hotkeys", e.to_string()),
+        }
+    }
+    if let Some(v) = obj.get("githubToken") {
+        current.github_token = v.as_str().map(|t| t.trim().to_string()).filter(|t| !t.is_empty());
+    }
+    if let Some(v) = obj.get("kernelSource") {
+        let source = if v.is_null() {
+            Ok(KernelSource::default())
+        } else {
+            serde_json::from_value::<KernelSource>(v.clone()).map_err(|e| e.to_string())
+        };
+        match source.and_then(|source| source.validate().map(|_| source)) {
+            Ok(source) => current.kernel_source = source,
+            Err(e) => patch.fail("kernelSource", e),
+        }
+    }
+    if let Some(v) = obj.get("kernelPath") {
+        match v.as_str().map(|p| p.trim()).filter(|p| !p.is_empty()) {
+            // 路径未变化时不重新校验
+            Some(path) if current.kernel_path.as_deref() != Some(path) => {
+                match crate::commands::kernel_validate_path(path.to_string()).await {
+                    Ok(_) => current.kernel_path = Some(path.to_string()),
+                    Err(e) => patch.fail("kernelPath", e),
+                }
+            }
+            Some(_) => {}
+            None => current.kernel_path = None,
+        }
+    }
+
+    // 组合校验：两个入站不能监听同一个端口
+    if current.local_port == current.socks_port {
+        let field = if obj.contains_key("socksPort") { "socksPort" } else { "localPort" };
+        patch.fail(field, format!("HTTP and SOCKS ports must differ: {}", current.local_port));
+    }
+
+    if !patch.errors.is_empty() {
+        let message = patch.errors.iter()
+            .map(|e| format!("{}: {}", e.field, e.message))
+            .collect::<Vec<_>>()
+            .join("; ");
+        return Err(SettingsError { message, fields: patch.errors });
+    }
+
+    // 快捷键先注册成功再保存，被其他程序占用时恢复原有快捷键
+    if previous.hotkeys != current.hotkeys {
+        let failed = crate::commands::hotkeys::register_hotkeys(&app, &current.hotkeys);
+        if !failed.is_empty() {
+            crate::commands::hotkeys::register_hotkeys(&app, &previous.hotkeys);
+            let fields: Vec<SettingsFieldError> = failed.into_iter()
+                .map(|(action, message)| SettingsFieldError { field: format!("hotkeys.{}", action), message })
+                .collect();
+            let message = fields.iter().map(|e| format!("{}: {}", e.field, e.message)).collect::<Vec<_>>().join("; ");
+            return Err(SettingsError { message, fields });
+        }
+    }
+
+    let encrypt_changed = previous.encrypt_data != current.encrypt_data;
+    let tray_changed = previous.language != current.language
+        || previous.tray_icon_style != current.tray_icon_style
+        || previous.routing_mode != current.routing_mode;
+    let theme_changed = previous.theme != current.theme;
+    let theme = current.theme.clone();
+    let effect = (previous.window_effect != current.window_effect).then(|| current.window_effect.clone());
+    save_settings(&state, current).await?;
+    if theme_changed {
+        crate::commands::theme::theme_setting_changed(&app, &theme);
+    }
+    if let Some(effect) = effect {
+        crate::commands::window::apply_window_effect(&app, &effect);
+    }
+    if tray_changed {
+        crate::tray::refresh_tray_menu(&app);
+    }
+    if encrypt_changed {
+        crate::commands::profiles::rewrite_encrypted_files(&state)?;
+    }
+    apply_settings_change(&app, &state, &previous).await;
+    Ok(())
+}
+
+pub(crate) async fn save_settings(state: &AppState, settings: AppSettings) -> Result<(), String> {
+    fs::create_dir_all(state.workspace_dir()).map_err(|e| e.to_string())?;
+    migrate::save(&state.settings_file(), DataFile::Settings, &settings, false)?;
+    state.encrypt_data.store(settings.encrypt_data, std::sync::atomic::Ordering::Relaxed);
+    crate::i18n::set_locale(&settings.language);
+    *state.settings.lock().await = settings;
     Ok(())
 }
+
+/// 把选中的设置项恢复为默认值，fields 为 camelCase 名称，为空表示全部设置；返回恢复后的设置
+#[tauri::command]
+pub async fn settings_reset(app: AppHandle, state: State<'_, AppState>, fields: Option<Vec<String>>) -> Result<AppSettings, SettingsError> {
+    let serde_json::Value::Object(defaults) = serde_json::to_value(AppSettings::default()).map_err(|e| e.to_string())? else {
+        return Err("Failed to build default settings".to_string().into());
+    };
+    let patch: serde_json::Map<String, serde_json::Value> = match fields {
+        Some(fields) => {
+            let unknown: Vec<SettingsFieldError> = fields.iter()
+                .filter(|f| !defaults.contains_key(f.as_str()))
+                .map(|f| SettingsFieldError { field: f.clone(), message: "Unknown setting".to_string() })
+                .collect();
+            if !unknown.is_empty() {
+                let message = unknown.iter().map(|e| format!("{}: {}", e.field, e.message)).collect::<Vec<_>>().join("; ");
+                return Err(SettingsError { message, fields: unknown });
+            }
+            defaults.into_iter().filter(|(key, _)| fields.contains(key)).collect()
+        }
+        None => defaults,
+    };
+
+    // 走 set_settings 的合并流程，快捷键、托盘语言和运行中的内核会一并同步
+    set_settings(app, state.clone(), serde_json::Value::Object(patch)).await?;
+    Ok(state.settings.lock().await.clone())
+}
+
+/// 恢复出厂设置：先停止内核并还原系统代理，再删除设置，可选同时清空配置和规则集
+#[tauri::command]
+pub async fn factory_reset(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    include_profiles: bool,
+    include_rulesets: bool,
+) -> Result<serde_json::Value, String> {
+    if matches!(*state.proxy_state.lock().await, ProxyState::Connected) {
+        crate::commands::singbox_stop(app.clone(), state.clone()).await?;
+    }
+
+    let mut removed = vec!["settings.json"];
+    if include_profiles {
+        let _ = fs::remove_file(state.profiles_file());
+        let _ = fs::remove_dir_all(state.configs_dir());
+        fs::create_dir_all(state.configs_dir()).map_err(|e| e.to_string())?;
+        *state.profiles_data.lock().await = Default::default();
+        removed.extend(["profiles.json", "configs"]);
+    }
+    if include_rulesets {
+        let _ = fs::remove_file(state.rulesets_file());
+        let _ = fs::remove_file(state.custom_rules_file());
+        let _ = fs::remove_dir_all(state.rulesets_cache_dir());
+        *state.rulesets.lock().await = crate::commands::rulesets::load_rulesets(&state);
+        removed.extend(["rulesets.json", "custom_rules.json", "rulesets"]);
+    }
+
+    save_settings(&state, AppSettings::default()).await?;
+    // 已加密的节点文件改回明文，否则关闭加密后仍是密文
+    if !include_profiles {
+        crate::commands::profiles::rewrite_encrypted_files(&state)?;
+    }
+    crate::commands::hotkeys::register_hotkeys(&app, &AppSettings::default().hotkeys);
+    crate::tray::refresh_tray_menu(&app);
+
+    Ok(serde_json::json!({ "success": true, "removed": removed }))
+}
@@ -1,4 +1,4 @@
-use tauri::{AppHandle, Emitter, Manager, State};
+use tauri::{AppHandle, Emitter, State};
 use std::fs;
 use std::process::Stdio;
 use std::sync::Arc;
@@ -16,10 +16,10 @@ const CREATE_NO_WINDOW: u32 = 0x08000000;
 
 #[tauri::command]
 pub async fn singbox_start(app: AppHandle, state: State<'_, AppState>) -> Result<CommandResult, String> {
-    let singbox_path = get_singbox_path(&app)?;
+    let singbox_path = get_singbox_path(&app).await?;
     
     if !singbox_path.exists() {
-        return Ok(CommandResult::err("sing-box.exe not found. Please install kernel first."));
+        return Ok(CommandResult::err(crate::i18n::tr("error.kernelNotFound")));
     }
 
     // Generate config
@@ -34,40 +34,57 @@ pub async fn singbox_start(app: AppHandle, state: State<'_, AppState>) -> Result
     *state.proxy_state.lock().await = ProxyState::Connecting;
     let _ = app.emit("singbox:state", "connecting");
 
+    // 连接后测速改走主内核，临时内核的出站和路由不应与主内核同时存在
+    crate::commands::profiles::stop_temp_singbox().await;
+
     // Start sing-box process
     #[cfg(windows)]
-    let mut child = Command::new(&singbox_path)
+    let spawned = Command::new(&singbox_path)
         .args(["run", "-c", config_path.to_str().unwrap()])
         .current_dir(&state.config_dir)
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
         .creation_flags(CREATE_NO_WINDOW)
         .kill_on_drop(true)
-        .spawn()
-        .map_err(|e| e.to_string())?;
+        .spawn();
 
     #[cfg(not(windows))]
-    let mut child = Command::new(&singbox_path)
+    let spawned = Command::new(&singbox_path)
         .args(["run", "-c", config_path.to_str().unwrap()])
         .current_dir(&state.config_dir)
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
         .kill_on_drop(true)
-        .spawn()
-        .map_err(|e| e.to_string())?;
+        .spawn();
+
+    let mut child = match spawned {
+        Ok(child) => child,
+        Err(e) => {
+            *state.proxy_state.lock().await = ProxyState::Error;
+            let _ = app.emit("singbox:state", "error");
+            return Err(e.to_string());
+        }
+    };
 
     // Capture stderr for logging
     if let Some(stderr) = child.stderr.take() {
         let app_clone = app.clone();
+        let kernel_default_interface = state.kernel_default_interface.clone();
+        *kernel_default_interface.lock().await = None;
         tokio::spawn(async move {
             let reader = BufReader::new(stderr);
             let mut lines = reader.lines();
             while let Ok(Some(line)) = lines.next_line().await {
+                // 记录 "updated default interface Wi-Fi, index 12"，供网络诊断使用
+                if let Some((_, rest)) = line.split_once("default interface ") {
+                    let name = rest.split(", index").next().unwrap_or(rest).trim().to_string();
+                    *kernel_default_interface.lock().await = Some(name);
+                }
                 let _ = app_clone.emit("singbox:log", serde_json::json!({
                     "timestamp": chrono::Utc::now().timestamp_millis(),
                     "level": "info",
                     "tag": "sing-box",
-                    "message": line
+                    "message": crate::redact::redact(&line)
                 }));
             }
         });
@@ -86,15 +103,33 @@ pub async fn singbox_start(app: AppHandle, state: State<'_, AppState>) -> Result
     
     let app_for_traffic = app.clone();
     let traffic_stats = state.traffic_stats.clone();
+    let failover_cancel = cancel_token.clone();
     tokio::spawn(async move {
         start_traffic_polling(app_for_traffic, traffic_stats, start_time_val, cancel_token).await;
     });
+    // 与流量轮询同生命周期，断开时一起取消
+    tokio::spawn(crate::commands::failover::run_failover_monitor(app.clone(), failover_cancel));
 
     // Enable system proxy
+    let network_system_proxy = state.network_profile.lock().await.as_ref().and_then(|p| p.system_proxy);
     let settings = state.settings.lock().await;
-    if settings.system_proxy {
-        let _ = enable_system_proxy_internal(settings.local_port).await;
+    if network_system_proxy.unwrap_or(settings.system_proxy) {
+        let _ = enable_system_proxy_internal(&app, &state, settings.local_port, &settings.proxy_bypass).await;
+    }
+    if settings.winhttp_proxy {
+        if let Err(e) = enable_winhttp_proxy_internal(&state, settings.local_port, &settings.proxy_bypass) {
+            log::warn!("Failed to set WinHTTP proxy: {}", e);
+        }
+    }
+    let tun_enabled = settings.tun_enabled;
+    drop(settings);
+    if tun_enabled {
+        if let Err(e) = crate::commands::network::apply_tun_dns(&state).await {
+            log::warn!("Failed to set TUN adapter DNS: {}", e);
+        }
     }
+    crate::commands::session::record_session(&state, true, None).await;
+    sync_sleep_inhibitor(&state).await;
 
     Ok(CommandResult::ok())
 }
@@ -115,15 +150,33 @@ pub async fn singbox_stop(app: AppHandle, state: State<'_, AppState>) -> Result<
     }
 
     // Disable system proxy
-    let _ = disable_system_proxy_internal().await;
+    let _ = disable_system_proxy_internal(&state).await;
+    if let Err(e) = restore_winhttp_proxy(&state) {
+        log::warn!("Failed to restore WinHTTP proxy: {}", e);
+    }
+    crate::commands::network::restore_adapter_dns(&state);
 
     *state.proxy_state.lock().await = ProxyState::Idle;
     *state.start_time.lock().await = None;
     let _ = app.emit("singbox:state", "idle");
+    crate::commands::session::record_session(&state, false, None).await;
+    sync_sleep_inhibitor(&state).await;
 
     Ok(CommandResult::ok())
 }
 
+/// 按 preventSleep 设置和连接状态持有或释放睡眠阻止
+pub(crate) async fn sync_sleep_inhibitor(state: &AppState) {
+    let connected = matches!(*state.proxy_state.lock().await, ProxyState::Connected);
+    let wanted = connected && state.settings.lock().await.prevent_sleep;
+    let mut inhibitor = state.sleep_inhibitor.lock().await;
+    if wanted && inhibitor.is_none() {
+        *inhibitor = Some(crate::power::SleepInhibitor::acquire());
+    } else if !wanted {
+        inhibitor.take();
+    }
+}
+
 #[tauri::command]
 pub async fn singbox_restart(app: AppHandle, state: State<'_, AppState>) -> Result<CommandResult, String> {
     singbox_stop(app.clone(), state.clone()).await?;
@@ -142,11 +195,53 @@ pub async fn singbox_get_status(state: State<'_, AppState>) -> Result<serde_json
     }))
 }
 
+/// 前端重新加载或渲染进程崩溃后一次性取回运行状态，用于完整恢复界面
+#[tauri::command]
+pub async fn get_full_state(app: AppHandle, state: State<'_, AppState>) -> Result<serde_json::Value, String> {
+    let proxy_state = state.proxy_state.lock().await.clone();
+    let start_time = *state.start_time.lock().await;
+    let traffic = state.traffic_stats.lock().await.clone();
+    let profiles_data = crate::commands::profiles::load_profiles_data(&state);
+
+    // 运行中可能已通过 Clash API 切换过节点，以内核实际选中的为准
+    let mut active_node = profiles_data.active_node_tag.clone();
+    if matches!(proxy_state, ProxyState::Connected) {
+        let selector = reqwest::Client::new()
+            .get("http://127.0.0.1:9090/proxies/PROXY")
+            .timeout(std::time::Duration::from_secs(2))
+            .send()
+            .await;
+        if let Ok(response) = selector {
+            if let Ok(selector) = response.json::<serde_json::Value>().await {
+                if let Some(now) = selector["now"].as_str() {
+                    active_node = Some(now.to_string());
+                }
+            }
+        }
+    }
+
+    let kernel_version = crate::commands::kernel_get_local_version(app).await.ok().flatten();
+    let kernel_downloading = state.kernel_download_cancel.lock().await.is_some();
+    let update_downloading = state.update_download_cancel.lock().await.is_some();
+    let progress = state.download_progress.lock().await;
+
+    Ok(serde_json::json!({
+        "state": proxy_state,
+        "startTime": start_time,
+        "traffic": traffic,
+        "activeProfileId": profiles_data.active_profile_id,
+        "activeNodeTag": active_node,
+        "kernelVersion": kernel_version,
+        "kernelDownload": kernel_downloading.then(|| serde_json::json!({ "progress": progress.get("kernel:download-progress") })),
+        "updateDownload": update_downloading.then(|| serde_json::json!({ "progress": progress.get("updater:download-progress") })),
+    }))
+}
+
 #[tauri::command]
 pub async fn singbox_switch_node(state: State<'_, AppState>, node_tag: String) -> Result<CommandResult, String> {
     let proxy_state = state.proxy_state.lock().await.clone();
     if !matches!(proxy_state, ProxyState::Connected) {
-        return Ok(CommandResult::err("VPN not running"));
+        return Ok(CommandResult::err(crate::i18n::tr("error.notRunning")));
     }
 
     let client = reqwest::Client::new();
@@ -158,25 +253,67 @@ pub async fn singbox_switch_node(state: State<'_, AppState>, node_tag: String) -
         .map_err(|e| e.to_string())?;
 
     if res.status().is_success() {
+        crate::commands::session::record_session(&state, true, Some(node_tag)).await;
         Ok(CommandResult::ok())
     } else {
         Ok(CommandResult::err(format!("API returned {}", res.status())))
     }
 }
 
+/// 切换路由模式并保存，连接中通过 Clash API 立即生效，无需重启内核
 #[tauri::command]
-pub async fn singbox_enable_system_proxy(port: Option<u16>) -> Result<CommandResult, String> {
+pub async fn singbox_set_mode(app: AppHandle, state: State<'_, AppState>, mode: String) -> Result<CommandResult, String> {
+    if !crate::commands::settings::ROUTING_MODES.contains(&mode.as_str()) {
+        return Err(format!("Invalid routing mode: {}", mode));
+    }
+    let mut settings = state.settings.lock().await.clone();
+    if settings.routing_mode == mode {
+        return Ok(CommandResult::ok());
+    }
+    settings.routing_mode = mode.clone();
+    crate::commands::settings::save_settings(&state, settings).await?;
+    let _ = app.emit("routing:mode", &mode);
+    crate::tray::refresh_tray_menu(&app);
+
+    if matches!(*state.proxy_state.lock().await, ProxyState::Connected) {
+        if let Err(e) = set_clash_mode(&mode).await {
+            return Ok(CommandResult::err(e));
+        }
+    }
+    Ok(CommandResult::ok())
+}
+
+#[tauri::command]
+pub async fn singbox_enable_system_proxy(app: AppHandle, state: State<'_, AppState>, port: Option<u16>) -> Result<CommandResult, String> {
     let port = port.unwrap_or(7890);
-    enable_system_proxy_internal(port).await?;
+    let bypass = state.settings.lock().await.proxy_bypass.clone();
+    enable_system_proxy_internal(&app, &state, port, &bypass).await?;
     Ok(CommandResult::ok())
 }
 
 #[tauri::command]
-pub async fn singbox_disable_system_proxy() -> Result<CommandResult, String> {
-    disable_system_proxy_internal().await?;
+pub async fn singbox_disable_system_proxy(state: State<'_, AppState>) -> Result<CommandResult, String> {
+    disable_system_proxy_internal(&state).await?;
     Ok(CommandResult::ok())
 }
 
+#[tauri::command]
+pub async fn singbox_enable_winhttp_proxy(state: State<'_, AppState>, port: Option<u16>) -> Result<CommandResult, String> {
+    let settings = state.settings.lock().await.clone();
+    match enable_winhttp_proxy_internal(&state, port.unwrap_or(settings.local_port), &settings.proxy_bypass) {
+        Ok(_) => Ok(CommandResult::ok()),
+        Err(e) => Ok(CommandResult::err(e)),
+    }
+}
+
+#[tauri::command]
+pub async fn singbox_disable_winhttp_proxy(state: State<'_, AppState>) -> Result<CommandResult, String> {
+    match restore_winhttp_proxy(&state) {
+        Ok(_) => Ok(CommandResult::ok()),
+        Err(e) => Ok(CommandResult::err(e)),
+    }
+}
+
 /// 判断节点类型是否是代理类型
 fn is_proxy_type(node_type: &str) -> bool {
     matches!(node_type, 
@@ -245,7 +382,7 @@ fn load_all_profiles(state: &AppState, profiles_data: &crate::types::ProfilesDat
     for profile in &profiles_data.profiles {
         let nodes_file = configs_dir.join(format!("{}.json", profile.id));
         if nodes_file.exists() {
-            if let Ok(content) = fs::read_to_string(&nodes_file) {
+            if let Ok(content) = crate::dpapi::read_data_file(&nodes_file) {
                 if let Ok(nodes) = serde_json::from_str::<Vec<serde_json::Value>>(&content) {
                     result.push(ProfileInfo {
                         id: profile.id.clone(),
@@ -260,26 +397,58 @@ fn load_all_profiles(state: &AppState, profiles_data: &crate::types::ProfilesDat
     result
 }
 
+fn ruleset_matcher(tag: &str) -> serde_json::Map<String, serde_json::Value> {
+    let mut matcher = serde_json::Map::new();
+    matcher.insert("rule_set".to_string(), serde_json::json!([tag]));
+    matcher
+}
+
+/// 组装路由规则：invert 对匹配条件取反；有例外域名时用 logical and 排除，命中例外的流量继续匹配后续规则
+fn build_route_rule(
+    mut matcher: serde_json::Map<String, serde_json::Value>,
+    invert: bool,
+    exceptions: &[String],
+    outbound: String,
+) -> serde_json::Value {
+    if invert {
+        matcher.insert("invert".to_string(), serde_json::Value::Bool(true));
+    }
+    if exceptions.is_empty() {
+        matcher.insert("outbound".to_string(), serde_json::Value::String(outbound));
+        return serde_json::Value::Object(matcher);
+    }
+    serde_json::json!({
+        "type": "logical",
+        "mode": "and",
+        "rules": [
+            matcher,
+            { "domain_suffix": exceptions, "invert": true }
+        ],
+        "outbound": outbound
+    })
+}
+
 async fn generate_config(state: &AppState) -> Result<CommandResult, String> {
+    let tolerance = crate::commands::profiles::urltest_tolerance(state).await;
     let profiles_data = state.profiles_data.lock().await;
     let settings = state.settings.lock().await;
     let rulesets = state.rulesets.lock().await;
 
     let active_profile_id = match &profiles_data.active_profile_id {
         Some(id) => id.clone(),
-        None => return Ok(CommandResult::err("No active profile")),
+        None => return Ok(CommandResult::err(crate::i18n::tr("error.noActiveProfile"))),
     };
 
     let nodes_file = state.configs_dir().join(format!("{}.json", active_profile_id));
     if !nodes_file.exists() {
-        return Ok(CommandResult::err("No nodes in active profile"));
+        return Ok(CommandResult::err(crate::i18n::tr("error.noNodes")));
     }
 
-    let nodes_content = fs::read_to_string(&nodes_file).map_err(|e| e.to_string())?;
+    let nodes_content = crate::dpapi::read_data_file(&nodes_file)?;
     let raw_nodes: Vec<serde_json::Value> = serde_json::from_str(&nodes_content).map_err(|e| e.to_string())?;
 
     if raw_nodes.is_empty() {
-        return Ok(CommandResult::err("No nodes in active profile"));
+        return Ok(CommandResult::err(crate::i18n::tr("error.noNodes")));
     }
 
     // 处理当前配置的节点
@@ -292,13 +461,24 @@ async fn generate_config(state: &AppState) -> Result<CommandResult, String> {
     let all_profiles = load_all_profiles(state, &profiles_data);
 
     // 收集规则集引用的 profile ID 和 node tag
-    let enabled_rulesets: Vec<_> = rulesets.iter().filter(|r| r.enabled).collect();
+    // 带时间段的规则只在生效时段内写入配置
+    let now = chrono::Local::now();
+    let enabled_rulesets: Vec<_> = rulesets.iter()
+        .filter(|r| r.enabled && r.schedule.as_ref().map(|s| s.is_active_at(now)).unwrap_or(true))
+        .collect();
     let mut referenced_profile_ids = std::collections::HashSet::new();
     let mut referenced_node_tags = std::collections::HashSet::new();
     
-    for rs in &enabled_rulesets {
-        if let Some(ref value) = rs.outbound_value {
-            match rs.outbound_mode.as_str() {
+    let custom_rules: Vec<_> = crate::commands::rules::load_custom_rules(state)
+        .into_iter()
+        .filter(|r| r.enabled && r.schedule.as_ref().map(|s| s.is_active_at(now)).unwrap_or(true))
+        .collect();
+    let outbound_targets = enabled_rulesets.iter().map(|rs| (&rs.outbound_mode, &rs.outbound_value))
+        .chain(custom_rules.iter().map(|r| (&r.outbound_mode, &r.outbound_value)));
+    
+    for (outbound_mode, outbound_value) in outbound_targets {
+        if let Some(ref value) = outbound_value {
+            match outbound_mode.as_str() {
                 "profile" | "配置" => { referenced_profile_ids.insert(value.clone()); }
                 "node" | "节点" => { referenced_node_tags.insert(value.clone()); }
                 _ => {}
@@ -307,7 +487,20 @@ async fn generate_config(state: &AppState) -> Result<CommandResult, String> {
     }
 
     // Build config - 使用 sing-box 1.11+ 新格式
-    let listen_addr = if settings.allow_lan { "0.0.0.0" } else { "127.0.0.1" };
+    // 指定了监听网卡时，本机仍监听 127.0.0.1（系统代理依赖），另在该网卡地址上额外监听
+    let bind_addr = match settings.listen_interface.as_deref() {
+        Some(target) => match crate::commands::network::resolve_listen_address(target).await {
+            Some(addr) => Some(addr),
+            None => return Ok(CommandResult::err(format!("No IPv4 address found on interface {}", target))),
+        },
+        None => None,
+    };
+    let bind_ip = bind_addr.as_deref().and_then(|a| a.parse::<std::net::IpAddr>().ok());
+    let listen_addr = match bind_ip {
+        Some(ip) if ip.is_unspecified() || ip.is_loopback() => ip.to_string(),
+        Some(_) => "127.0.0.1".to_string(),
+        None => if settings.allow_lan { "0.0.0.0" } else { "127.0.0.1" }.to_string(),
+    };
     
     let mut config = serde_json::json!({
         "log": {
@@ -318,7 +511,7 @@ async fn generate_config(state: &AppState) -> Result<CommandResult, String> {
         "experimental": {
             "clash_api": {
                 "external_controller": "127.0.0.1:9090",
-                "default_mode": "rule"
+                "default_mode": clash_mode(&settings.routing_mode)
             },
             "cache_file": {
                 "enabled": true,
@@ -369,6 +562,50 @@ async fn generate_config(state: &AppState) -> Result<CommandResult, String> {
         }
     });
 
+    if let Some(ip) = bind_ip.filter(|ip| !ip.is_unspecified() && !ip.is_loopback()) {
+        if let Some(inbounds) = config["inbounds"].as_array_mut() {
+            inbounds.push(serde_json::json!({
+                "type": "mixed",
+                "tag": "mixed-bind-in",
+                "listen": ip.to_string(),
+                "listen_port": settings.local_port,
+                "sniff": true,
+                "sniff_override_destination": true
+            }));
+            inbounds.push(serde_json::json!({
+                "type": "socks",
+                "tag": "socks-bind-in",
+                "listen": ip.to_string(),
+                "listen_port": settings.socks_port
+            }));
+        }
+    }
+
+    // TUN 入站：地址与 network::TUN_DNS_SERVER 同网段，网卡 DNS 指向内核后由 hijack-dns 接管
+    if settings.tun_enabled {
+        let mut tun = serde_json::json!({
+            "type": "tun",
+            "tag": "tun-in",
+            "interface_name": crate::commands::network::TUN_INTERFACE_NAME,
+            "address": ["172.19.0.1/30"],
+            "mtu": settings.tun_mtu,
+            "auto_route": true,
+            "strict_route": settings.tun_strict_route,
+            "stack": settings.tun_stack,
+            "sniff": true,
+            "sniff_override_destination": true
+        });
+        if !settings.tun_route_address.is_empty() {
+            tun["route_address"] = serde_json::json!(settings.tun_route_address);
+        }
+        if !settings.tun_route_exclude_address.is_empty() {
+            tun["route_exclude_address"] = serde_json::json!(settings.tun_route_exclude_address);
+        }
+        if let Some(inbounds) = config["inbounds"].as_array_mut() {
+            inbounds.push(tun);
+        }
+    }
+
     // ========== 构建 outbounds ==========
     let mut outbounds: Vec<serde_json::Value> = Vec::new();
     let mut proxy_tags: Vec<String> = Vec::new();
@@ -442,7 +679,7 @@ async fn generate_config(state: &AppState) -> Result<CommandResult, String> {
                     "outbounds": profile_proxy_tags,
                     "url": settings.latency_test_url,
                     "interval": "30m",
-                    "tolerance": 50,
+                    "tolerance": tolerance,
                     "interrupt_exist_connections": true
                 }));
                 existing_tags.insert(selector_tag.clone());
@@ -472,7 +709,7 @@ async fn generate_config(state: &AppState) -> Result<CommandResult, String> {
             "outbounds": proxy_tags,
             "url": settings.latency_test_url,
             "interval": "300s",
-            "tolerance": 50
+            "tolerance": tolerance
         }));
     }
 
@@ -487,48 +724,19 @@ async fn generate_config(state: &AppState) -> Result<CommandResult, String> {
         .filter_map(|o| o.get("tag").and_then(|t| t.as_str()).map(|s| s.to_string()))
         .collect();
 
-    // ========== 构建路由规则 ==========
-    let mut rules: Vec<serde_json::Value> = vec![
-        serde_json::json!({ "protocol": "dns", "action": "hijack-dns" }),
-    ];
-
-    if settings.bypass_lan {
-        rules.push(serde_json::json!({ "ip_is_private": true, "outbound": "direct" }));
-    }
-
-    // 添加规则集路由规则
-    let mut rule_set_refs = Vec::new();
-    let rulesets_cache_dir = state.rulesets_cache_dir();
-
-    for rs in &enabled_rulesets {
-        // 检查本地缓存文件是否存在
-        let local_path = rulesets_cache_dir.join(format!("{}.srs", rs.tag));
-        
-        if !local_path.exists() {
-            log::warn!("Ruleset cache not found, skipping: {}", rs.tag);
-            continue;
-        }
-
-        // 添加规则集引用
-        rule_set_refs.push(serde_json::json!({
-            "tag": rs.tag,
-            "type": "local",
-            "format": rs.format,
-            "path": local_path.to_string_lossy()
-        }));
-
-        // 映射 outbound_mode 到正确的出站名称
-        let outbound = match rs.outbound_mode.as_str() {
+    // 映射 outbound_mode 到正确的出站名称
+    let resolve_outbound = |outbound_mode: &str, outbound_value: &Option<String>, rule_name: &str| -> String {
+        match outbound_mode {
             "proxy" => "PROXY".to_string(),
             "direct" => "direct".to_string(),
             "block" => "block".to_string(),
             // node 模式：验证节点是否存在
             "node" | "节点" => {
-                if let Some(ref node_tag) = rs.outbound_value {
+                if let Some(ref node_tag) = outbound_value {
                     if available_outbound_tags.contains(node_tag) {
                         node_tag.clone()
                     } else {
-                        log::warn!("Node '{}' not found for ruleset '{}', falling back to PROXY", node_tag, rs.tag);
+                        log::warn!("Node '{}' not found for rule '{}', falling back to PROXY", node_tag, rule_name);
                         "PROXY".to_string()
                     }
                 } else {
@@ -537,16 +745,16 @@ async fn generate_config(state: &AppState) -> Result<CommandResult, String> {
             },
             // profile 模式：使用配置的 urltest selector
             "profile" | "配置" => {
-                if let Some(ref profile_id) = rs.outbound_value {
+                if let Some(ref profile_id) = outbound_value {
                     if let Some(selector_tag) = profile_id_to_selector.get(profile_id) {
                         if available_outbound_tags.contains(selector_tag) {
                             selector_tag.clone()
                         } else {
-                            log::warn!("Profile selector '{}' not found for ruleset '{}', falling back to PROXY", selector_tag, rs.tag);
+                            log::warn!("Profile selector '{}' not found for rule '{}', falling back to PROXY", selector_tag, rule_name);
                             "PROXY".to_string()
                         }
                     } else {
-                        log::warn!("Profile '{}' not found for ruleset '{}', falling back to PROXY", profile_id, rs.tag);
+                        log::warn!("Profile '{}' not found for rule '{}', falling back to PROXY", profile_id, rule_name);
                         "PROXY".to_string()
                     }
                 } else {
@@ -554,12 +762,96 @@ async fn generate_config(state: &AppState) -> Result<CommandResult, String> {
                 }
             },
             other => other.to_string()
+        }
+    };
+
+    // ========== 构建路由规则 ==========
+    let mut rules: Vec<serde_json::Value> = vec![
+        serde_json::json!({ "protocol": "dns", "action": "hijack-dns" }),
+        // 全局模式由 Clash API 切换，无需重新生成配置
+        serde_json::json!({ "clash_mode": "global", "outbound": "PROXY" }),
+        serde_json::json!({ "clash_mode": "direct", "outbound": "direct" }),
+    ];
+
+    if settings.bypass_lan {
+        rules.push(serde_json::json!({ "ip_is_private": true, "outbound": "direct" }));
+    }
+
+    // 添加自定义规则（优先于规则集）
+    for rule in &custom_rules {
+        let (field, values): (&str, Vec<serde_json::Value>) = match rule.rule_type.as_str() {
+            "port" => ("port", rule.values.iter().filter_map(|v| v.parse::<u16>().ok()).map(serde_json::Value::from).collect()),
+            // 通配符没有对应字段，转换为 domain_regex
+            "domain_wildcard" => ("domain_regex", rule.values.iter()
+                .map(|v| serde_json::Value::from(crate::commands::rules::wildcard_to_regex(v)))
+                .collect()),
+            other => (other, rule.values.iter().map(|v| serde_json::Value::from(v.as_str())).collect()),
         };
+        let mut matcher = serde_json::Map::new();
+        matcher.insert(field.to_string(), serde_json::Value::Array(values));
+        let outbound = resolve_outbound(&rule.outbound_mode, &rule.outbound_value, &rule.id);
+        rules.push(build_route_rule(matcher, rule.invert, &rule.exceptions, outbound));
+    }
+
+    // 添加规则集路由规则
+    let mut rule_set_refs = Vec::new();
+    // 已写入配置、可以在 DNS 规则中引用的规则集
+    let mut dns_rulesets = Vec::new();
+
+    for rs in &enabled_rulesets {
+        // 远程规则集直接交给内核下载，经 PROXY 更新；拦截列表需要先在本地转换，始终使用缓存文件
+        if settings.remote_rule_sets && rs.rule_type == "remote" && rs.source_format.is_none() {
+            if let Some(ref url) = rs.url {
+                let mut rule_set = serde_json::json!({
+                    "tag": rs.tag,
+                    "type": "remote",
+                    "format": rs.format,
+                    "url": url,
+                    "download_detour": if available_outbound_tags.contains("PROXY") { "PROXY" } else { "direct" }
+                });
+                if rs.update_interval > 0 {
+                    rule_set["update_interval"] = serde_json::Value::String(format!("{}m", rs.update_interval));
+                }
+                rule_set_refs.push(rule_set);
+                dns_rulesets.push(*rs);
+                let outbound = resolve_outbound(&rs.outbound_mode, &rs.outbound_value, &rs.tag);
+                rules.push(build_route_rule(ruleset_matcher(&rs.tag), rs.invert, &rs.exceptions, outbound));
+                continue;
+            }
+        }
 
-        rules.push(serde_json::json!({
-            "rule_set": [rs.tag],
-            "outbound": outbound
+        // 检查本地缓存文件是否存在（远程下载或本地导入）
+        let local_path = state.ruleset_cache_file(&rs.tag, &rs.format);
+        
+        if !local_path.exists() {
+            log::warn!("Ruleset cache not found, skipping: {}", rs.tag);
+            continue;
+        }
+
+        // source 规则集优先使用编译好的 .srs，未编译或已过期时直接引用 JSON
+        let (format, local_path) = if rs.format == "source" {
+            let compiled = state.ruleset_cache_file(&rs.tag, "binary");
+            let is_fresh = match (compiled.metadata().and_then(|m| m.modified()), local_path.metadata().and_then(|m| m.modified())) {
+                (Ok(compiled_time), Ok(source_time)) => compiled_time >= source_time,
+                _ => false,
+            };
+            if is_fresh { ("binary", compiled) } else { ("source", local_path) }
+        } else {
+            (rs.format.as_str(), local_path)
+        };
+
+        // 添加规则集引用
+        rule_set_refs.push(serde_json::json!({
+            "tag": rs.tag,
+            "type": "local",
+            "format": format,
+            "path": local_path.to_string_lossy()
         }));
+        dns_rulesets.push(*rs);
+
+        let outbound = resolve_outbound(&rs.outbound_mode, &rs.outbound_value, &rs.tag);
+
+        rules.push(build_route_rule(ruleset_matcher(&rs.tag), rs.invert, &rs.exceptions, outbound));
     }
 
     if !rule_set_refs.is_empty() {
@@ -568,6 +860,39 @@ async fn generate_config(state: &AppState) -> Result<CommandResult, String> {
 
     config["route"]["rules"] = serde_json::Value::Array(rules);
 
+    // 按规则集指定 DNS 服务器，插在“出站域名走本地 DNS”规则之后
+    let mut dns_rules = Vec::new();
+    let mut use_fakeip = false;
+    for rs in dns_rulesets {
+        let Some(server) = rs.dns_server.as_deref() else { continue };
+        if server == "dns-fakeip" {
+            use_fakeip = true;
+            dns_rules.push(serde_json::json!({
+                "rule_set": [rs.tag],
+                "query_type": ["A", "AAAA"],
+                "server": server
+            }));
+        } else {
+            dns_rules.push(serde_json::json!({
+                "rule_set": [rs.tag],
+                "server": server
+            }));
+        }
+    }
+    if let Some(dns_rule_list) = config["dns"]["rules"].as_array_mut() {
+        dns_rule_list.splice(1..1, dns_rules);
+    }
+    if use_fakeip {
+        if let Some(servers) = config["dns"]["servers"].as_array_mut() {
+            servers.push(serde_json::json!({ "tag": "dns-fakeip", "address": "fakeip" }));
+        }
+        config["dns"]["fakeip"] = serde_json::json!({
+            "enabled": true,
+            "inet4_range": "198.18.0.0/15",
+            "inet6_range": "fc00::/18"
+        });
+    }
+
     // Write config
     fs::create_dir_all(&state.config_dir).map_err(|e| e.to_string())?;
     let config_path = state.config_dir.join("config.json");
@@ -577,49 +902,197 @@ async fn generate_config(state: &AppState) -> Result<CommandResult, String> {
     Ok(CommandResult::ok())
 }
 
-fn get_singbox_path(app: &AppHandle) -> Result<std::path::PathBuf, String> {
-    let resource_path = app.path().resource_dir().map_err(|e| e.to_string())?;
-    Ok(resource_path.join("resources/libs/sing-box.exe"))
+async fn get_singbox_path(app: &AppHandle) -> Result<std::path::PathBuf, String> {
+    crate::commands::kernel::resolve_kernel_path(app).await
+}
+
+/// 设置中的路由模式对应的 Clash 模式
+fn clash_mode(routing_mode: &str) -> &'static str {
+    match routing_mode {
+        "global-proxy" => "global",
+        "global-direct" => "direct",
+        _ => "rule",
+    }
+}
+
+/// 通过 Clash API 切换运行中内核的路由模式
+pub(crate) async fn set_clash_mode(routing_mode: &str) -> Result<(), String> {
+    let response = reqwest::Client::new()
+        .patch("http://127.0.0.1:9090/configs")
+        .json(&serde_json::json!({ "mode": clash_mode(routing_mode) }))
+        .timeout(std::time::Duration::from_secs(3))
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!("Failed to switch mode: HTTP {}", response.status()));
+    }
+    Ok(())
 }
 
-async fn enable_system_proxy_internal(port: u16) -> Result<(), String> {
+/// 连接中按当前设置重新应用系统代理和 WinHTTP 代理（网络配置的覆盖优先）
+pub(crate) async fn reapply_proxy_settings(app: &AppHandle, state: &AppState) -> Result<(), String> {
+    let network_system_proxy = state.network_profile.lock().await.as_ref().and_then(|p| p.system_proxy);
+    let settings = state.settings.lock().await.clone();
+    apply_system_proxy(app, state, network_system_proxy.unwrap_or(settings.system_proxy)).await?;
+    if settings.winhttp_proxy {
+        enable_winhttp_proxy_internal(state, settings.local_port, &settings.proxy_bypass)
+    } else {
+        restore_winhttp_proxy(state)
+    }
+}
+
+/// 连接中按网络配置切换系统代理
+pub(crate) async fn apply_system_proxy(app: &AppHandle, state: &AppState, enabled: bool) -> Result<(), String> {
+    if enabled {
+        let settings = state.settings.lock().await.clone();
+        enable_system_proxy_internal(app, state, settings.local_port, &settings.proxy_bypass).await
+    } else {
+        disable_system_proxy_internal(state).await
+    }
+}
+
+async fn enable_system_proxy_internal(app: &AppHandle, state: &AppState, port: u16, bypass: &str) -> Result<(), String> {
     let proxy = format!("127.0.0.1:{}", port);
     
-    #[cfg(windows)]
-    {
-        Command::new("reg")
-            .args(["add", "HKCU\\Software\\Microsoft\\Windows\\CurrentVersion\\Internet Settings", "/v", "ProxyEnable", "/t", "REG_DWORD", "/d", "1", "/f"])
-            .creation_flags(CREATE_NO_WINDOW)
-            .output()
-            .await
-            .map_err(|e| e.to_string())?;
-
-        Command::new("reg")
-            .args(["add", "HKCU\\Software\\Microsoft\\Windows\\CurrentVersion\\Internet Settings", "/v", "ProxyServer", "/t", "REG_SZ", "/d", &proxy, "/f"])
-            .creation_flags(CREATE_NO_WINDOW)
-            .output()
-            .await
-            .map_err(|e| e.to_string())?;
+    // 首次启用时保存用户原有的代理设置；已经指向本程序（如上次异常退出）则不覆盖备份
+    let backup_file = state.proxy_backup_file();
+    if !backup_file.exists() {
+        match crate::sysproxy::query_system_proxy() {
+            Ok(snapshot) if snapshot.proxy_enabled() && snapshot.server.as_deref() == Some(proxy.as_str()) => {}
+            Ok(snapshot) => {
+                let content = serde_json::to_string_pretty(&snapshot).map_err(|e| e.to_string())?;
+                fs::write(&backup_file, content).map_err(|e| e.to_string())?;
+            }
+            Err(e) => log::warn!("Failed to snapshot system proxy: {}", e),
+        }
     }
+    
+    crate::sysproxy::set_system_proxy(Some(&proxy), Some(bypass))?;
+    
+    // 只有 Windows 能读取系统代理设置
+    if cfg!(windows) {
+        let cancel_token = CancellationToken::new();
+        if let Some(previous) = state.proxy_guard_cancel.lock().await.replace(cancel_token.clone()) {
+            previous.cancel();
+        }
+        tokio::spawn(run_proxy_guard(app.clone(), state.settings.clone(), proxy, bypass.to_string(), cancel_token));
+    }
+    
+    Ok(())
+}
 
-    #[cfg(not(windows))]
-    {
-        let _ = proxy;
+async fn disable_system_proxy_internal(state: &AppState) -> Result<(), String> {
+    if let Some(cancel) = state.proxy_guard_cancel.lock().await.take() {
+        cancel.cancel();
     }
+    restore_system_proxy(state)
+}
 
+fn enable_winhttp_proxy_internal(state: &AppState, port: u16, bypass: &str) -> Result<(), String> {
+    let proxy = format!("127.0.0.1:{}", port);
+    
+    // 与系统代理相同：只在首次启用时备份原有配置
+    let backup_file = state.winhttp_backup_file();
+    if !backup_file.exists() {
+        let current = crate::sysproxy::query_winhttp_proxy()?;
+        if current.proxy.as_deref() != Some(proxy.as_str()) {
+            let content = serde_json::to_string_pretty(&current).map_err(|e| e.to_string())?;
+            fs::write(&backup_file, content).map_err(|e| e.to_string())?;
+        }
+    }
+    
+    crate::sysproxy::set_winhttp_proxy(&crate::sysproxy::WinHttpProxy {
+        proxy: Some(proxy),
+        bypass: Some(bypass.to_string()).filter(|b| !b.is_empty()),
+    })
+}
+
+/// 恢复启用前的 WinHTTP 代理；没有备份说明本程序未设置过，不做改动
+pub(crate) fn restore_winhttp_proxy(state: &AppState) -> Result<(), String> {
+    let backup_file = state.winhttp_backup_file();
+    let Some(previous) = fs::read_to_string(&backup_file)
+        .ok()
+        .and_then(|content| serde_json::from_str::<crate::sysproxy::WinHttpProxy>(&content).ok())
+    else {
+        return Ok(());
+    };
+    
+    crate::sysproxy::set_winhttp_proxy(&previous)?;
+    let _ = fs::remove_file(&backup_file);
     Ok(())
 }
 
-async fn disable_system_proxy_internal() -> Result<(), String> {
-    #[cfg(windows)]
-    Command::new("reg")
-        .args(["add", "HKCU\\Software\\Microsoft\\Windows\\CurrentVersion\\Internet Settings", "/v", "ProxyEnable", "/t", "REG_DWORD", "/d", "0", "/f"])
-        .creation_flags(CREATE_NO_WINDOW)
-        .output()
-        .await
-        .map_err(|e| e.to_string())?;
+/// 系统代理守护：定期检查代理是否仍指向本程序，被其他软件改写时按设置改回或通知前端
+async fn run_proxy_guard(
+    app: AppHandle,
+    settings: Arc<tokio::sync::Mutex<crate::types::AppSettings>>,
+    proxy: String,
+    bypass: String,
+    cancel: CancellationToken,
+) {
+    // 同一个冲突只通知一次，直到设置再次变化
+    let mut last_conflict: Option<crate::sysproxy::ProxySnapshot> = None;
+    
+    loop {
+        tokio::select! {
+            _ = cancel.cancelled() => {
+                log::info!("Proxy guard cancelled");
+                break;
+            }
+            _ = tokio::time::sleep(std::time::Duration::from_secs(5)) => {
+                let mode = settings.lock().await.proxy_guard_mode.clone();
+                if mode == "off" {
+                    continue;
+                }
+                
+                let current = match crate::sysproxy::query_system_proxy() {
+                    Ok(current) => current,
+                    Err(e) => {
+                        log::warn!("Proxy guard failed to read system proxy: {}", e);
+                        continue;
+                    }
+                };
+                if current.proxy_enabled() && current.server.as_deref() == Some(proxy.as_str()) {
+                    last_conflict = None;
+                    continue;
+                }
+                if last_conflict.as_ref() == Some(&current) {
+                    continue;
+                }
+                
+                log::warn!("System proxy changed externally: {:?}", current);
+                if mode == "reassert" {
+                    match crate::sysproxy::set_system_proxy(Some(&proxy), Some(&bypass)) {
+                        Ok(_) => {
+                            let _ = app.emit("sysproxy:reasserted", &current);
+                            continue;
+                        }
+                        Err(e) => log::warn!("Failed to reassert system proxy: {}", e),
+                    }
+                }
+                let _ = app.emit("sysproxy:conflict", &current);
+                last_conflict = Some(current);
+            }
+        }
+    }
+}
 
-    Ok(())
+/// 恢复启用前保存的代理设置，没有备份时直接关闭代理；同步执行以便在应用退出时调用
+pub(crate) fn restore_system_proxy(state: &AppState) -> Result<(), String> {
+    let backup_file = state.proxy_backup_file();
+    let snapshot = fs::read_to_string(&backup_file)
+        .ok()
+        .and_then(|content| serde_json::from_str::<crate::sysproxy::ProxySnapshot>(&content).ok());
+    
+    match snapshot {
+        Some(snapshot) => {
+            crate::sysproxy::restore_system_proxy(&snapshot)?;
+            let _ = fs::remove_file(&backup_file);
+            Ok(())
+        }
+        None => crate::sysproxy::set_system_proxy(None, None),
+    }
 }
 
 async fn start_traffic_polling(
@@ -0,0 +1,165 @@
+use tauri::{AppHandle, State};
+use futures::StreamExt;
+use serde::Serialize;
+use std::fs;
+use std::time::{Duration, Instant};
+use crate::state::AppState;
+
+/// 单节点测试内核的混合入站端口和 Clash API 端口，与延迟测试的临时内核互不影响
+const NODE_PROXY_PORT: u16 = 19091;
+const NODE_PROXY_API_PORT: u16 = 19092;
+/// 测速下载地址，按需返回指定字节数
+const SPEED_TEST_URL: &str = "https://speed.cloudflare.com/__down?bytes=50000000";
+/// 最多下载这么久，慢节点不必等到下载完
+const SPEED_TEST_DURATION: Duration = Duration::from_secs(10);
+/// 前面这段时间处于 TCP 慢启动，不计入持续速度
+const SPEED_TEST_WARMUP: Duration = Duration::from_secs(1);
+
+/// 同一时间只跑一个单节点测试，避免端口冲突和多个测速互相抢带宽
+static NODE_PROXY_LOCK: once_cell::sync::Lazy<tokio::sync::Mutex<()>> = once_cell::sync::Lazy::new(|| tokio::sync::Mutex::new(()));
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpeedTestResult {
+    pub tag: String,
+    /// 去掉预热阶段后的平均速度（Mbps）
+    pub mbps: f64,
+    pub bytes: u64,
+    pub duration_ms: u64,
+}
+
+/// 只包含目标节点的内核配置：混合入站的流量全部走该节点
+fn node_proxy_config(node: &serde_json::Value, tag: &str) -> serde_json::Value {
+    let mut config = crate::commands::profiles::generate_temp_config_raw(std::slice::from_ref(node), NODE_PROXY_API_PORT);
+    config["log"]["level"] = serde_json::json!("warn");
+    config["inbounds"] = serde_json::json!([{
+        "type": "mixed",
+        "tag": "node-test-in",
+        "listen": "127.0.0.1",
+        "listen_port": NODE_PROXY_PORT,
+    }]);
+    config["route"]["final"] = serde_json::json!(tag);
+    config
+}
+
+async fn wait_for_kernel(child: &mut tokio::process::Child) -> Result<(), String> {
+    for _ in 0..30 {
+        if let Ok(Some(status)) = child.try_wait() {
+            return Err(format!("Node test kernel exited: {}", status));
+        }
+        if crate::commands::profiles::check_clash_api_running(NODE_PROXY_API_PORT).await {
+            return Ok(());
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    Err("Node test kernel did not start in time".to_string())
+}
+
+/// 只含一个节点的测试内核，经其混合入站访问的流量都走该节点；持有期间其他单节点测试会被拒绝
+pub(crate) struct NodeProxy {
+    child: tokio::process::Child,
+    config_path: std::path::PathBuf,
+    _guard: tokio::sync::MutexGuard<'static, ()>,
+}
+
+impl NodeProxy {
+    /// 为当前配置中的 tag 节点启动测试内核并等待就绪
+    pub(crate) async fn start(app: &AppHandle, state: &AppState, tag: &str) -> Result<Self, String> {
+        let guard = NODE_PROXY_LOCK.try_lock().map_err(|_| "Another node test is already running".to_string())?;
+
+        let data = crate::commands::profiles::load_profiles_data(state);
+        let profile_id = data.active_profile_id.ok_or_else(|| crate::i18n::tr("error.noActiveProfile").to_string())?;
+        let node = crate::commands::profiles::load_profile_nodes_raw(state, &profile_id).into_iter()
+            .find(|n| n.get("tag").and_then(|t| t.as_str()) == Some(tag))
+            .ok_or("Node not found")?;
+
+        let kernel_path = crate::commands::kernel::resolve_kernel_path(app).await?;
+        if !kernel_path.exists() {
+            return Err(crate::i18n::tr("error.kernelNotFound").to_string());
+        }
+        let temp_dir = state.data_dir.join("temp_test");
+        fs::create_dir_all(&temp_dir).map_err(|e| e.to_string())?;
+        let config_path = temp_dir.join("node_test.json");
+        let config = serde_json::to_string_pretty(&node_proxy_config(&node, tag)).map_err(|e| e.to_string())?;
+        fs::write(&config_path, config).map_err(|e| e.to_string())?;
+
+        let child = crate::commands::profiles::spawn_test_kernel(&kernel_path, &config_path, &temp_dir)
+            .map_err(|e| format!("Failed to start node test kernel: {}", e))?;
+        let mut proxy = NodeProxy { child, config_path, _guard: guard };
+        if let Err(e) = wait_for_kernel(&mut proxy.child).await {
+            proxy.stop().await;
+            return Err(e);
+        }
+        Ok(proxy)
+    }
+
+    /// 混合入站的本地端口，同时支持 HTTP 和 SOCKS5（含 UDP）
+    pub(crate) fn port(&self) -> u16 {
+        NODE_PROXY_PORT
+    }
+
+    /// 经该节点发出请求的 HTTP 客户端
+    pub(crate) fn client(&self, builder: reqwest::ClientBuilder) -> Result<reqwest::Client, String> {
+        builder
+            .proxy(reqwest::Proxy::all(format!("http://127.0.0.1:{}", self.port())).map_err(|e| e.to_string())?)
+            .build()
+            .map_err(|e| e.to_string())
+    }
+
+    pub(crate) async fn stop(mut self) {
+        let _ = self.child.kill().await;
+        let _ = fs::remove_file(&self.config_path);
+    }
+}
+
+/// 通过代理下载测速文件，返回 (预热后下载的字节数, 预热后经过的时间)
+async fn measure_download(proxy: &NodeProxy) -> Result<(u64, Duration), String> {
+    let client = proxy.client(reqwest::Client::builder().connect_timeout(Duration::from_secs(10)))?;
+    let response = client.get(SPEED_TEST_URL).send().await.map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!("Speed test server returned {}", response.status()));
+    }
+
+    let start = Instant::now();
+    let mut measured: u64 = 0;
+    let mut stream = response.bytes_stream();
+    let deadline = tokio::time::sleep(SPEED_TEST_DURATION);
+    tokio::pin!(deadline);
+    loop {
+        tokio::select! {
+            chunk = stream.next() => match chunk {
+                Some(Ok(chunk)) => {
+                    if start.elapsed() >= SPEED_TEST_WARMUP {
+                        measured += chunk.len() as u64;
+                    }
+                }
+                Some(Err(e)) => return Err(e.to_string()),
+                None => break,
+            },
+            _ = &mut deadline => break,
+        }
+    }
+    Ok((measured, start.elapsed().saturating_sub(SPEED_TEST_WARMUP)))
+}
+
+/// 测试节点的实际下载速度：单独启动一个只含该节点的内核，经其代理下载测速文件
+#[tauri::command]
+pub async fn node_speed_test(app: AppHandle, state: State<'_, AppState>, tag: String) -> Result<SpeedTestResult, String> {
+    let proxy = NodeProxy::start(&app, &state, &tag).await?;
+    let measured = measure_download(&proxy).await;
+    proxy.stop().await;
+
+    let (bytes, elapsed) = measured?;
+    let seconds = elapsed.as_secs_f64();
+    let mbps = if seconds > 0.0 { bytes as f64 * 8.0 / seconds / 1_000_000.0 } else { 0.0 };
+    let mbps = (mbps * 10.0).round() / 10.0;
+    log::info!("Speed test for {}: {} Mbps", tag, mbps);
+    state.node_speed.lock().await.insert(tag.clone(), mbps);
+    Ok(SpeedTestResult { tag, mbps, bytes, duration_ms: elapsed.as_millis() as u64 })
+}
+
+/// 各节点最近一次测得的下载速度（Mbps）
+#[tauri::command]
+pub async fn node_speed_results(state: State<'_, AppState>) -> Result<std::collections::HashMap<String, f64>, String> {
+    Ok(state.node_speed.lock().await.clone())
+}
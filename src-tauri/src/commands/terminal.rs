@@ -0,0 +1,162 @@
+use tauri::State;
+use crate::state::AppState;
+
+#[cfg(windows)]
+use std::os::windows::process::CommandExt;
+
+#[cfg(windows)]
+const CREATE_NEW_CONSOLE: u32 = 0x00000010;
+
+/// 本地地址不走代理
+const TERMINAL_NO_PROXY: &str = "localhost,127.0.0.1,::1";
+
+/// 终端需要设置的代理环境变量，git / npm / curl 都会读取
+fn proxy_env_vars(http_port: u16, socks_port: u16) -> Vec<(&'static str, String)> {
+    let http = format!("http://127.0.0.1:{}", http_port);
+    let socks = format!("socks5://127.0.0.1:{}", socks_port);
+    vec![
+        ("HTTP_PROXY", http.clone()),
+        ("HTTPS_PROXY", http),
+        ("ALL_PROXY", socks),
+        ("NO_PROXY", TERMINAL_NO_PROXY.to_string()),
+    ]
+}
+
+fn format_exports(shell: &str, vars: &[(&'static str, String)]) -> String {
+    vars.iter()
+        .map(|(key, value)| match shell {
+            "cmd" => format!("set {}={}", key, value),
+            "powershell" => format!("$env:{}=\"{}\"", key, value),
+            _ => format!("export {}=\"{}\"", key, value),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// 按当前端口生成 cmd / PowerShell / bash 可直接粘贴的代理环境变量
+#[tauri::command]
+pub async fn terminal_proxy_env(state: State<'_, AppState>) -> Result<serde_json::Value, String> {
+    let settings = state.settings.lock().await;
+    let vars = proxy_env_vars(settings.local_port, settings.socks_port);
+    Ok(serde_json::json!({
+        "cmd": format_exports("cmd", &vars),
+        "powershell": format_exports("powershell", &vars),
+        "bash": format_exports("bash", &vars),
+    }))
+}
+
+/// 打开一个已设置好代理环境变量的终端，shell 为 cmd / powershell / bash
+#[tauri::command]
+pub async fn terminal_open_with_proxy(state: State<'_, AppState>, shell: String) -> Result<(), String> {
+    let vars = {
+        let settings = state.settings.lock().await;
+        proxy_env_vars(settings.local_port, settings.socks_port)
+    };
+
+    #[cfg(windows)]
+    {
+        let mut command = match shell.as_str() {
+            "cmd" => {
+                let mut c = std::process::Command::new("cmd");
+                c.arg("/K");
+                c
+            }
+            "powershell" => {
+                let mut c = std::process::Command::new("powershell");
+                c.arg("-NoExit");
+                c
+            }
+            // Git Bash / WSL 提供的 bash
+            "bash" => {
+                let mut c = std::process::Command::new("bash");
+                c.arg("--login");
+                c
+            }
+            other => return Err(format!("Unsupported shell: {}", other)),
+        };
+        command
+            .envs(vars.iter().map(|(key, value)| (*key, value.as_str())))
+            .creation_flags(CREATE_NEW_CONSOLE);
+        if let Ok(home) = std::env::var("USERPROFILE") {
+            command.current_dir(home);
+        }
+        command.spawn().map_err(|e| format!("Failed to launch {}: {}", shell, e))?;
+        Ok(())
+    }
+
+    #[cfg(not(windows))]
+    {
+        let _ = (vars, shell);
+        Err("Launching a terminal is only available on Windows".to_string())
+    }
+}
+
+/// 把进程放进关闭时终止全部进程的作业对象，KunBox 退出时系统回收句柄，该程序及其子进程随之结束
+#[cfg(windows)]
+fn attach_kill_on_close_job(child: &std::process::Child) -> Result<(), String> {
+    use std::os::windows::io::AsRawHandle;
+    use windows::core::PCWSTR;
+    use windows::Win32::Foundation::{CloseHandle, HANDLE};
+    use windows::Win32::System::JobObjects::{
+        AssignProcessToJobObject, CreateJobObjectW, JobObjectExtendedLimitInformation, SetInformationJobObject,
+        JOBOBJECT_EXTENDED_LIMIT_INFORMATION, JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE,
+    };
+
+    unsafe {
+        let job = CreateJobObjectW(None, PCWSTR::null()).map_err(|e| format!("Failed to create job object: {}", e))?;
+        let mut info = JOBOBJECT_EXTENDED_LIMIT_INFORMATION::default();
+        info.BasicLimitInformation.LimitFlags = JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE;
+        let result = SetInformationJobObject(
+            job,
+            JobObjectExtendedLimitInformation,
+            &info as *const _ as *const core::ffi::c_void,
+            std::mem::size_of::<JOBOBJECT_EXTENDED_LIMIT_INFORMATION>() as u32,
+        )
+        .and_then(|_| AssignProcessToJobObject(job, HANDLE(child.as_raw_handle())));
+        if let Err(e) = result {
+            let _ = CloseHandle(job);
+            return Err(format!("Failed to assign job object: {}", e));
+        }
+        // 句柄有意不关闭，由进程退出时统一释放
+    }
+    Ok(())
+}
+
+/// 以代理环境变量启动指定程序，无需开启系统代理或 TUN 即可只代理单个命令行工具
+#[tauri::command]
+pub async fn launch_app_with_proxy(
+    state: State<'_, AppState>,
+    path: String,
+    args: Option<Vec<String>>,
+    use_job: Option<bool>,
+) -> Result<serde_json::Value, String> {
+    let exe = std::path::PathBuf::from(path.trim());
+    if !exe.is_file() {
+        return Err(format!("Executable not found: {}", exe.display()));
+    }
+    let vars = {
+        let settings = state.settings.lock().await;
+        proxy_env_vars(settings.local_port, settings.socks_port)
+    };
+
+    let mut command = std::process::Command::new(&exe);
+    command
+        .args(args.unwrap_or_default())
+        .envs(vars.iter().map(|(key, value)| (*key, value.as_str())));
+    if let Some(dir) = exe.parent() {
+        command.current_dir(dir);
+    }
+    #[cfg(windows)]
+    command.creation_flags(CREATE_NEW_CONSOLE);
+
+    let child = command.spawn().map_err(|e| format!("Failed to launch {}: {}", exe.display(), e))?;
+
+    #[cfg(windows)]
+    if use_job.unwrap_or(false) {
+        attach_kill_on_close_job(&child)?;
+    }
+    #[cfg(not(windows))]
+    let _ = use_job;
+
+    Ok(serde_json::json!({ "success": true, "pid": child.id() }))
+}
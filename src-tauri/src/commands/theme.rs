@@ -0,0 +1,80 @@
+use tauri::{AppHandle, Emitter, Manager, State};
+use crate::state::AppState;
+
+/// 读取 Windows“应用模式”的深浅色；取不到时按深色处理，与默认主题一致
+#[cfg(windows)]
+fn os_theme() -> &'static str {
+    use windows::core::w;
+    use windows::Win32::System::Registry::{RegGetValueW, HKEY_CURRENT_USER, RRF_RT_REG_DWORD};
+
+    let mut value: u32 = 0;
+    let mut size = std::mem::size_of::<u32>() as u32;
+    let result = unsafe {
+        RegGetValueW(
+            HKEY_CURRENT_USER,
+            w!("Software\\Microsoft\\Windows\\CurrentVersion\\Themes\\Personalize"),
+            w!("AppsUseLightTheme"),
+            RRF_RT_REG_DWORD,
+            None,
+            Some(&mut value as *mut u32 as *mut _),
+            Some(&mut size),
+        )
+    };
+    if result.is_ok() && value == 1 { "light" } else { "dark" }
+}
+
+#[cfg(not(windows))]
+fn os_theme() -> &'static str {
+    "dark"
+}
+
+/// 设置为 system 时跟随系统，否则就是设置值本身
+fn effective_theme(setting: &str) -> &str {
+    if setting == "system" { os_theme() } else { setting }
+}
+
+fn theme_payload(setting: &str) -> serde_json::Value {
+    serde_json::json!({
+        "setting": setting,
+        "theme": effective_theme(setting),
+        "system": os_theme(),
+    })
+}
+
+/// 让窗口标题栏等原生部分使用同样的主题；system 时交给系统，系统切换时才会收到 ThemeChanged
+pub(crate) fn apply_window_theme(app: &AppHandle, setting: &str) {
+    let theme = match setting {
+        "dark" => Some(tauri::Theme::Dark),
+        "light" => Some(tauri::Theme::Light),
+        _ => None,
+    };
+    for window in app.webview_windows().values() {
+        if let Err(e) = window.set_theme(theme) {
+            log::warn!("Failed to set theme of window {}: {}", window.label(), e);
+        }
+    }
+}
+
+/// 主题设置变化后同步窗口并通知前端
+pub(crate) fn theme_setting_changed(app: &AppHandle, setting: &str) {
+    apply_window_theme(app, setting);
+    let _ = app.emit("theme:changed", theme_payload(setting));
+}
+
+/// Windows 切换深浅色时由窗口事件调用，仅在跟随系统时通知前端
+pub(crate) fn system_theme_changed(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let setting = app.state::<AppState>().settings.lock().await.theme.clone();
+        if setting == "system" {
+            log::info!("System theme changed to {}", os_theme());
+            let _ = app.emit("theme:changed", theme_payload(&setting));
+        }
+    });
+}
+
+/// 当前实际使用的主题：{ setting, theme, system }
+#[tauri::command]
+pub async fn theme_get_effective(state: State<'_, AppState>) -> Result<serde_json::Value, String> {
+    Ok(theme_payload(&state.settings.lock().await.theme))
+}
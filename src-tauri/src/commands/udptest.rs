@@ -0,0 +1,283 @@
+use tauri::{AppHandle, State};
+use serde::Serialize;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpStream, UdpSocket};
+use crate::commands::speedtest::NodeProxy;
+use crate::state::AppState;
+
+/// 两个不同的 STUN 服务器，比较同一本地端口在两者看到的映射地址来判断 NAT 行为
+const STUN_SERVERS: &[(&str, u16)] = &[("stun.l.google.com", 19302), ("stun.cloudflare.com", 3478)];
+const STUN_MAGIC_COOKIE: u32 = 0x2112_A442;
+/// 每个服务器最多发送的次数和每次等待回复的时间，UDP 可能丢包
+const STUN_ATTEMPTS: usize = 3;
+const STUN_REPLY_TIMEOUT: Duration = Duration::from_millis(1500);
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UdpTestResult {
+    pub tag: String,
+    /// 至少一个 STUN 服务器经节点返回了结果
+    pub udp: bool,
+    /// endpointIndependent 映射与目标无关（适合游戏、语音）/ symmetric 每个目标映射不同 / unknown 只有一个服务器有回复；UDP 不通时为 None
+    pub nat_type: Option<&'static str>,
+    /// STUN 服务器看到的出口地址
+    pub mapped_address: Option<String>,
+    /// 第一个 STUN 回复的往返时间（毫秒）
+    pub latency: Option<u64>,
+    pub tested_at: u64,
+}
+
+/// 与混合入站建立 SOCKS5 UDP ASSOCIATE，返回控制连接（关闭后中继随之失效）和中继地址
+async fn socks5_udp_associate(port: u16) -> Result<(TcpStream, SocketAddr), String> {
+    let mut control = TcpStream::connect(("127.0.0.1", port)).await.map_err(|e| e.to_string())?;
+    control.write_all(&[5, 1, 0]).await.map_err(|e| e.to_string())?;
+    let mut method = [0u8; 2];
+    control.read_exact(&mut method).await.map_err(|e| e.to_string())?;
+    if method != [5, 0] {
+        return Err("SOCKS5 handshake rejected".to_string());
+    }
+
+    control.write_all(&[5, 3, 0, 1, 0, 0, 0, 0, 0, 0]).await.map_err(|e| e.to_string())?;
+    let mut head = [0u8; 4];
+    control.read_exact(&mut head).await.map_err(|e| e.to_string())?;
+    if head[1] != 0 {
+        return Err(format!("UDP associate rejected by proxy ({})", head[1]));
+    }
+    let ip = match head[3] {
+        1 => {
+            let mut addr = [0u8; 4];
+            control.read_exact(&mut addr).await.map_err(|e| e.to_string())?;
+            IpAddr::from(addr)
+        }
+        4 => {
+            let mut addr = [0u8; 16];
+            control.read_exact(&mut addr).await.map_err(|e| e.to_string())?;
+            IpAddr::from(addr)
+        }
+        atyp => return Err(format!("Unsupported relay address type {}", atyp)),
+    };
+    let mut relay_port = [0u8; 2];
+    control.read_exact(&mut relay_port).await.map_err(|e| e.to_string())?;
+    // 绑定在 0.0.0.0 上时回复的就是未指定地址
+    let ip = if ip.is_unspecified() { IpAddr::V4(Ipv4Addr::LOCALHOST) } else { ip };
+    Ok((control, SocketAddr::new(ip, u16::from_be_bytes(relay_port))))
+}
+
+/// SOCKS5 UDP 请求头：RSV FRAG ATYP=域名，由节点负责解析
+fn socks5_udp_packet(host: &str, port: u16, payload: &[u8]) -> Vec<u8> {
+    let mut packet = vec![0, 0, 0, 3, host.len() as u8];
+    packet.extend_from_slice(host.as_bytes());
+    packet.extend_from_slice(&port.to_be_bytes());
+    packet.extend_from_slice(payload);
+    packet
+}
+
+/// 去掉 SOCKS5 UDP 回复头，返回数据部分
+fn socks5_udp_payload(packet: &[u8]) -> Option<&[u8]> {
+    let header_len = match *packet.get(3)? {
+        1 => 4 + 4 + 2,
+        4 => 4 + 16 + 2,
+        3 => 4 + 1 + *packet.get(4)? as usize + 2,
+        _ => return None,
+    };
+    packet.get(header_len..)
+}
+
+fn stun_binding_request(transaction_id: &[u8; 12]) -> Vec<u8> {
+    let mut request = Vec::with_capacity(20);
+    request.extend_from_slice(&0x0001u16.to_be_bytes());
+    request.extend_from_slice(&0u16.to_be_bytes());
+    request.extend_from_slice(&STUN_MAGIC_COOKIE.to_be_bytes());
+    request.extend_from_slice(transaction_id);
+    request
+}
+
+/// 从 Binding 成功响应中取 XOR-MAPPED-ADDRESS，老服务器只返回 MAPPED-ADDRESS
+fn stun_mapped_address(response: &[u8], transaction_id: &[u8; 12]) -> Option<SocketAddr> {
+    if response.len() < 20 || response[0..2] != [0x01, 0x01] || response[8..20] != transaction_id[..] {
+        return None;
+    }
+    let mut mapped = None;
+    let mut offset = 20;
+    while offset + 4 <= response.len() {
+        let kind = u16::from_be_bytes([response[offset], response[offset + 1]]);
+        let len = u16::from_be_bytes([response[offset + 2], response[offset + 3]]) as usize;
+        let value = response.get(offset + 4..offset + 4 + len)?;
+        match kind {
+            0x0020 => return parse_stun_address(value, Some(transaction_id)),
+            0x0001 => mapped = parse_stun_address(value, None),
+            _ => {}
+        }
+        // 属性按 4 字节对齐
+        offset += 4 + len.div_ceil(4) * 4;
+    }
+    mapped
+}
+
+/// transaction_id 为 Some 时按 XOR-MAPPED-ADDRESS 解码
+fn parse_stun_address(value: &[u8], transaction_id: Option<&[u8; 12]>) -> Option<SocketAddr> {
+    let family = *value.get(1)?;
+    let port = u16::from_be_bytes([*value.get(2)?, *value.get(3)?]);
+    let (port, mask) = match transaction_id {
+        Some(id) => (port ^ (STUN_MAGIC_COOKIE >> 16) as u16, [&STUN_MAGIC_COOKIE.to_be_bytes()[..], &id[..]].concat()),
+        None => (port, vec![0; 16]),
+    };
+    let ip = match family {
+        1 => {
+            let raw: [u8; 4] = value.get(4..8)?.try_into().ok()?;
+            IpAddr::V4(Ipv4Addr::from(std::array::from_fn::<u8, 4, _>(|i| raw[i] ^ mask[i])))
+        }
+        2 => {
+            let raw: [u8; 16] = value.get(4..20)?.try_into().ok()?;
+            IpAddr::V6(Ipv6Addr::from(std::array::from_fn::<u8, 16, _>(|i| raw[i] ^ mask[i])))
+        }
+        _ => return None,
+    };
+    Some(SocketAddr::new(ip, port))
+}
+
+/// 经中继向一个 STUN 服务器发送 Binding 请求，返回 (映射地址, 往返时间)
+async fn stun_query(socket: &UdpSocket, relay: SocketAddr, host: &str, port: u16) -> Option<(SocketAddr, Duration)> {
+    let mut transaction_id = [0u8; 12];
+    transaction_id.copy_from_slice(&uuid::Uuid::new_v4().as_bytes()[..12]);
+    let packet = socks5_udp_packet(host, port, &stun_binding_request(&transaction_id));
+    let mut buf = [0u8; 1024];
+    for _ in 0..STUN_ATTEMPTS {
+        let sent = Instant::now();
+        socket.send_to(&packet, relay).await.ok()?;
+        let deadline = tokio::time::sleep(STUN_REPLY_TIMEOUT);
+        tokio::pin!(deadline);
+        loop {
+            tokio::select! {
+                received = socket.recv_from(&mut buf) => {
+                    let Ok((len, _)) = received else { break };
+                    let mapped = socks5_udp_payload(&buf[..len]).and_then(|payload| stun_mapped_address(payload, &transaction_id));
+                    if let Some(mapped) = mapped {
+                        return Some((mapped, sent.elapsed()));
+                    }
+                }
+                _ = &mut deadline => break,
+            }
+        }
+    }
+    None
+}
+
+async fn run_udp_test(proxy: &NodeProxy) -> Result<Vec<Option<(SocketAddr, Duration)>>, String> {
+    let (_control, relay) = socks5_udp_associate(proxy.port()).await?;
+    let socket = UdpSocket::bind(if relay.is_ipv4() { "127.0.0.1:0" } else { "[::1]:0" }).await.map_err(|e| e.to_string())?;
+    let mut replies = Vec::new();
+    for (host, port) in STUN_SERVERS {
+        replies.push(stun_query(&socket, relay, host, *port).await);
+    }
+    Ok(replies)
+}
+
+/// 经指定节点做 STUN 测试，检查 UDP 转发是否可用并判断节点出口的 NAT 映射行为
+#[tauri::command]
+pub async fn node_udp_test(app: AppHandle, state: State<'_, AppState>, tag: String) -> Result<UdpTestResult, String> {
+    let proxy = NodeProxy::start(&app, &state, &tag).await?;
+    let replies = run_udp_test(&proxy).await;
+    proxy.stop().await;
+
+    let replies: Vec<(SocketAddr, Duration)> = replies?.into_iter().flatten().collect();
+    let nat_type = match replies.as_slice() {
+        [] => None,
+        [(first, _), (second, _)] if first == second => Some("endpointIndependent"),
+        [_, _] => Some("symmetric"),
+        _ => Some("unknown"),
+    };
+    let result = UdpTestResult {
+        tag: tag.clone(),
+        udp: !replies.is_empty(),
+        nat_type,
+        mapped_address: replies.first().map(|(addr, _)| addr.to_string()),
+        latency: replies.first().map(|(_, rtt)| rtt.as_millis() as u64),
+        tested_at: chrono::Utc::now().timestamp_millis() as u64,
+    };
+    log::info!("UDP test for {}: udp={} nat={:?}", tag, result.udp, result.nat_type);
+    state.node_udp.lock().await.insert(tag, result.clone());
+    Ok(result)
+}
+
+/// 各节点最近一次的 UDP / NAT 测试结果
+#[tauri::command]
+pub async fn node_udp_results(state: State<'_, AppState>) -> Result<std::collections::HashMap<String, UdpTestResult>, String> {
+    Ok(state.node_udp.lock().await.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // RFC 5769 中示例响应使用的事务 ID
+    const TRANSACTION_ID: [u8; 12] = [0xb7, 0xe7, 0xa7, 0x01, 0xbc, 0x34, 0xd6, 0x86, 0xfa, 0x87, 0xdf, 0xae];
+
+    fn stun_response(transaction_id: &[u8; 12], attributes: &[u8]) -> Vec<u8> {
+        let mut response = vec![0x01, 0x01];
+        response.extend_from_slice(&(attributes.len() as u16).to_be_bytes());
+        response.extend_from_slice(&STUN_MAGIC_COOKIE.to_be_bytes());
+        response.extend_from_slice(transaction_id);
+        response.extend_from_slice(attributes);
+        response
+    }
+
+    /// 5 字节的 SOFTWARE 属性补齐到 8 字节，用来检查对齐处理
+    const PADDED_SOFTWARE: [u8; 12] = [0x80, 0x22, 0x00, 0x05, b't', b'e', b's', b't', b'!', 0, 0, 0];
+
+    #[test]
+    fn decodes_xor_mapped_ipv4() {
+        let mut attributes = PADDED_SOFTWARE.to_vec();
+        attributes.extend_from_slice(&[0x00, 0x20, 0x00, 0x08, 0x00, 0x01, 0xa1, 0x47, 0xe1, 0x12, 0xa6, 0x43]);
+        let response = stun_response(&TRANSACTION_ID, &attributes);
+        assert_eq!(stun_mapped_address(&response, &TRANSACTION_ID), Some("192.0.2.1:32853".parse().unwrap()));
+    }
+
+    #[test]
+    fn decodes_xor_mapped_ipv6() {
+        let mut attributes = PADDED_SOFTWARE.to_vec();
+        attributes.extend_from_slice(&[
+            0x00, 0x20, 0x00, 0x14, 0x00, 0x02, 0xa1, 0x47,
+            0x01, 0x13, 0xa9, 0xfa, 0xa5, 0xd3, 0xf1, 0x79,
+            0xbc, 0x25, 0xf4, 0xb5, 0xbe, 0xd2, 0xb9, 0xd9,
+        ]);
+        let response = stun_response(&TRANSACTION_ID, &attributes);
+        assert_eq!(
+            stun_mapped_address(&response, &TRANSACTION_ID),
+            Some("[2001:db8:1234:5678:11:2233:4455:6677]:32853".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn falls_back_to_mapped_address() {
+        let response = stun_response(&TRANSACTION_ID, &[0x00, 0x01, 0x00, 0x08, 0x00, 0x01, 0x80, 0x55, 0xc0, 0x00, 0x02, 0x01]);
+        assert_eq!(stun_mapped_address(&response, &TRANSACTION_ID), Some("192.0.2.1:32853".parse().unwrap()));
+    }
+
+    #[test]
+    fn rejects_mismatched_transaction_id() {
+        let response = stun_response(&TRANSACTION_ID, &[0x00, 0x20, 0x00, 0x08, 0x00, 0x01, 0xa1, 0x47, 0xe1, 0x12, 0xa6, 0x43]);
+        let mut other = TRANSACTION_ID;
+        other[0] ^= 0xff;
+        assert_eq!(stun_mapped_address(&response, &other), None);
+    }
+
+    #[test]
+    fn strips_socks5_udp_headers() {
+        let payload = [0xde, 0xad];
+        assert_eq!(socks5_udp_payload(&[0, 0, 0, 1, 1, 2, 3, 4, 0, 53, 0xde, 0xad]), Some(&payload[..]));
+
+        let mut ipv6 = vec![0, 0, 0, 4];
+        ipv6.extend_from_slice(&[0; 16]);
+        ipv6.extend_from_slice(&[0, 53, 0xde, 0xad]);
+        assert_eq!(socks5_udp_payload(&ipv6), Some(&payload[..]));
+
+        let packet = socks5_udp_packet("stun.example.com", 3478, &payload);
+        assert_eq!(socks5_udp_payload(&packet), Some(&payload[..]));
+
+        assert_eq!(socks5_udp_payload(&[0, 0, 0, 9, 0xde, 0xad]), None);
+        assert_eq!(socks5_udp_payload(&[0, 0, 0, 3, 20, b'a']), None);
+    }
+}
@@ -0,0 +1,122 @@
+use tauri::{AppHandle, State};
+use serde::Serialize;
+use std::time::Duration;
+use crate::commands::speedtest::NodeProxy;
+use crate::state::AppState;
+
+/// 单个服务的请求超时
+const UNLOCK_REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// 模拟浏览器，部分服务会拦截非浏览器的请求
+const UNLOCK_USER_AGENT: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0 Safari/537.36";
+/// 非 Netflix 自制剧，能打开说明完整解锁，只能打开自制剧说明仅限自制内容
+const NETFLIX_LICENSED_TITLE: &str = "https://www.netflix.com/title/70143836";
+const NETFLIX_ORIGINAL_TITLE: &str = "https://www.netflix.com/title/81280792";
+/// OpenAI 未开放服务的地区
+const CHATGPT_UNSUPPORTED: &[&str] = &["CN", "HK", "MO", "RU", "BY", "IR", "KP", "SY", "CU", "VE"];
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnlockResult {
+    pub service: &'static str,
+    /// unlocked 可用 / originals 仅自制内容（Netflix）/ blocked 不可用 / failed 请求失败
+    pub status: &'static str,
+    /// 服务识别出的地区代码
+    pub region: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeUnlockReport {
+    pub tag: String,
+    pub results: Vec<UnlockResult>,
+    pub tested_at: u64,
+}
+
+fn result(service: &'static str, status: &'static str, region: Option<String>) -> UnlockResult {
+    UnlockResult { service, status, region }
+}
+
+/// 从 netflix.com/xx-en/title/... 这类跳转后的地址中取地区，没有地区前缀的是美区
+fn netflix_region(url: &reqwest::Url) -> String {
+    url.path_segments()
+        .and_then(|mut segments| segments.next())
+        .filter(|segment| *segment != "title")
+        .and_then(|segment| segment.split('-').next())
+        .map(str::to_uppercase)
+        .unwrap_or_else(|| "US".to_string())
+}
+
+async fn test_netflix(client: &reqwest::Client) -> UnlockResult {
+    let licensed = client.get(NETFLIX_LICENSED_TITLE).send().await;
+    let original = client.get(NETFLIX_ORIGINAL_TITLE).send().await;
+    match (licensed, original) {
+        (Ok(licensed), _) if licensed.status().is_success() => result("netflix", "unlocked", Some(netflix_region(licensed.url()))),
+        (Ok(_), Ok(original)) if original.status().is_success() => result("netflix", "originals", Some(netflix_region(original.url()))),
+        (Ok(licensed), _) if licensed.status() == reqwest::StatusCode::FORBIDDEN || licensed.status() == reqwest::StatusCode::NOT_FOUND => {
+            result("netflix", "blocked", None)
+        }
+        _ => result("netflix", "failed", None),
+    }
+}
+
+/// Cloudflare trace 给出出口地区，再看 OpenAI 是否在该地区提供服务
+async fn test_chatgpt(client: &reqwest::Client) -> UnlockResult {
+    let trace = match client.get("https://chatgpt.com/cdn-cgi/trace").send().await {
+        Ok(response) => response.text().await.unwrap_or_default(),
+        Err(_) => return result("chatgpt", "failed", None),
+    };
+    let region = trace.lines().find_map(|line| line.strip_prefix("loc=")).map(|loc| loc.trim().to_uppercase());
+    let status = match region.as_deref() {
+        Some(loc) if CHATGPT_UNSUPPORTED.contains(&loc) => "blocked",
+        Some(_) => "unlocked",
+        None => "failed",
+    };
+    result("chatgpt", status, region)
+}
+
+async fn test_youtube_premium(client: &reqwest::Client) -> UnlockResult {
+    let body = match client.get("https://www.youtube.com/premium").header("Accept-Language", "en").send().await {
+        Ok(response) => response.text().await.unwrap_or_default(),
+        Err(_) => return result("youtubePremium", "failed", None),
+    };
+    let region = body.split("\"INNERTUBE_CONTEXT_GL\":\"").nth(1)
+        .and_then(|rest| rest.split('"').next())
+        .map(str::to_string);
+    if body.contains("Premium is not available in your country") || region.as_deref() == Some("CN") {
+        result("youtubePremium", "blocked", region)
+    } else if body.contains("ad-free") || region.is_some() {
+        result("youtubePremium", "unlocked", region)
+    } else {
+        result("youtubePremium", "failed", None)
+    }
+}
+
+/// 经指定节点检测 Netflix、ChatGPT、YouTube Premium 是否可用及识别出的地区
+#[tauri::command]
+pub async fn node_unlock_test(app: AppHandle, state: State<'_, AppState>, tag: String) -> Result<NodeUnlockReport, String> {
+    let proxy = NodeProxy::start(&app, &state, &tag).await?;
+    let client = proxy.client(reqwest::Client::builder().timeout(UNLOCK_REQUEST_TIMEOUT).user_agent(UNLOCK_USER_AGENT));
+    let results = match client {
+        Ok(client) => {
+            let (netflix, chatgpt, youtube) = tokio::join!(test_netflix(&client), test_chatgpt(&client), test_youtube_premium(&client));
+            Ok(vec![netflix, chatgpt, youtube])
+        }
+        Err(e) => Err(e),
+    };
+    proxy.stop().await;
+
+    let report = NodeUnlockReport {
+        tag: tag.clone(),
+        results: results?,
+        tested_at: chrono::Utc::now().timestamp_millis() as u64,
+    };
+    log::info!("Unlock test for {}: {:?}", tag, report.results.iter().map(|r| (r.service, r.status)).collect::<Vec<_>>());
+    state.node_unlock.lock().await.insert(tag, report.clone());
+    Ok(report)
+}
+
+/// 各节点最近一次的解锁检测结果
+#[tauri::command]
+pub async fn node_unlock_results(state: State<'_, AppState>) -> Result<std::collections::HashMap<String, NodeUnlockReport>, String> {
+    Ok(state.node_unlock.lock().await.clone())
+}
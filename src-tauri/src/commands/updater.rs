@@ -0,0 +1,275 @@
+use tauri::{AppHandle, Emitter, State};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tokio_util::sync::CancellationToken;
+use crate::state::AppState;
+use crate::commands::kernel::{
+    build_http_clients, download_with_resume, fetch_expected_sha256, fetch_github_json, sha256_file,
+};
+
+#[cfg(windows)]
+use std::os::windows::process::CommandExt;
+
+const APP_RELEASE_LATEST: &str = "https://api.github.com/repos/roseforljh/KunBoxForWindows/releases/latest";
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct AppUpdate {
+    pub current_version: String,
+    pub version: String,
+    pub tag_name: String,
+    pub published_at: String,
+    pub notes: String,
+    pub html_url: String,
+    pub has_update: bool,
+    pub download_url: String,
+    pub asset_name: String,
+    pub size: u64,
+    /// true 为 NSIS 安装包，false 为便携版 zip
+    pub is_installer: bool,
+    #[serde(default)]
+    pub sha256: Option<String>,
+    #[serde(default)]
+    pub checksum_url: Option<String>,
+}
+
+#[derive(serde::Deserialize, Debug)]
+struct AppRelease {
+    tag_name: String,
+    published_at: String,
+    html_url: String,
+    #[serde(default)]
+    body: Option<String>,
+    assets: Vec<AppReleaseAsset>,
+}
+
+#[derive(serde::Deserialize, Debug)]
+struct AppReleaseAsset {
+    name: String,
+    browser_download_url: String,
+    size: u64,
+    #[serde(default)]
+    digest: Option<String>,
+}
+
+/// 按数字段比较版本号，忽略前缀 v 和 -beta 之类的后缀
+fn is_newer_version(remote: &str, current: &str) -> bool {
+    let parse = |v: &str| -> Vec<u64> {
+        v.trim_start_matches('v')
+            .split(['-', '+'])
+            .next()
+            .unwrap_or_default()
+            .split('.')
+            .map(|part| part.parse().unwrap_or(0))
+            .collect()
+    };
+    let (remote, current) = (parse(remote), parse(current));
+    for i in 0..remote.len().max(current.len()) {
+        let (r, c) = (remote.get(i).copied().unwrap_or(0), current.get(i).copied().unwrap_or(0));
+        if r != c {
+            return r > c;
+        }
+    }
+    false
+}
+
+/// 优先选择安装包，其次是便携版 zip
+fn find_update_asset(assets: &[AppReleaseAsset]) -> Option<(&AppReleaseAsset, bool)> {
+    let lower = |a: &AppReleaseAsset| a.name.to_lowercase();
+    assets.iter()
+        .find(|a| lower(a).ends_with(".exe") && lower(a).contains("setup"))
+        .or_else(|| assets.iter().find(|a| lower(a).ends_with(".exe")))
+        .map(|a| (a, true))
+        .or_else(|| assets.iter().find(|a| lower(a).ends_with(".zip")).map(|a| (a, false)))
+}
+
+fn find_update_checksum<'a>(assets: &'a [AppReleaseAsset], asset_name: &str) -> Option<&'a AppReleaseAsset> {
+    let sidecar = format!("{}.sha256", asset_name);
+    assets.iter()
+        .find(|a| a.name == sidecar)
+        .or_else(|| assets.iter().find(|a| {
+            let name = a.name.to_lowercase();
+            name.contains("checksum") || name.contains("sha256sums")
+        }))
+}
+
+fn update_cache_dir(state: &AppState) -> PathBuf {
+    state.data_dir.join("cache").join("update")
+}
+
+/// 通过校验的更新包旁写入的 SHA256 记录
+fn verified_digest_path(file_path: &Path) -> PathBuf {
+    let mut name = file_path.as_os_str().to_owned();
+    name.push(".verified");
+    PathBuf::from(name)
+}
+
+/// 只有下载时通过校验且之后未被改动的文件才允许安装
+fn ensure_verified(file_path: &Path) -> Result<(), String> {
+    let expected = fs::read_to_string(verified_digest_path(file_path))
+        .map_err(|_| format!("Update was not verified: {:?}", file_path))?;
+    let actual = sha256_file(file_path)?;
+    if actual != expected.trim() {
+        return Err(format!("Update changed after verification: {:?}", file_path));
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn updater_check(app: AppHandle, state: State<'_, AppState>) -> Result<AppUpdate, String> {
+    let clients = build_http_clients(&state, 30).await?;
+    let token = state.settings.lock().await.github_token.clone();
+
+    let release = fetch_github_json::<AppRelease>(&clients, APP_RELEASE_LATEST, token.as_deref())
+        .await
+        .map_err(|e| format!("Failed to fetch app releases: {}", e))?;
+    let (asset, is_installer) = find_update_asset(&release.assets)
+        .ok_or_else(|| format!("No Windows package in release {}", release.tag_name))?;
+
+    let current_version = app.package_info().version.to_string();
+    let version = release.tag_name.trim_start_matches('v').to_string();
+
+    Ok(AppUpdate {
+        has_update: is_newer_version(&version, &current_version),
+        current_version,
+        version,
+        tag_name: release.tag_name.clone(),
+        published_at: release.published_at.clone(),
+        notes: release.body.clone().unwrap_or_default(),
+        html_url: release.html_url.clone(),
+        download_url: asset.browser_download_url.clone(),
+        asset_name: asset.name.clone(),
+        size: asset.size,
+        is_installer,
+        sha256: asset.digest.as_deref()
+            .and_then(|d| d.strip_prefix("sha256:"))
+            .map(|d| d.to_lowercase()),
+        checksum_url: find_update_checksum(&release.assets, &asset.name)
+            .map(|a| a.browser_download_url.clone()),
+    })
+}
+
+#[tauri::command]
+pub async fn updater_download(app: AppHandle, state: State<'_, AppState>, update: AppUpdate) -> Result<serde_json::Value, String> {
+    // 资源名来自远端，只允许普通文件名，避免写到缓存目录之外
+    if update.asset_name.is_empty() || update.asset_name.contains(['/', '\\']) || update.asset_name.contains("..") {
+        return Err(format!("Invalid update asset name: {}", update.asset_name));
+    }
+
+    let _ = app.emit("updater:download-start", ());
+
+    let clients = build_http_clients(&state, 600).await?;
+
+    let cancel_token = CancellationToken::new();
+    *state.update_download_cancel.lock().await = Some(cancel_token.clone());
+    state.download_progress.lock().await.remove("updater:download-progress");
+
+    let download_dir = update_cache_dir(&state);
+    fs::create_dir_all(&download_dir).map_err(|e| e.to_string())?;
+    let part_path = download_dir.join(format!("{}.part", update.asset_name));
+    let file_path = download_dir.join(&update.asset_name);
+    // 旧的校验记录作废，直到本次下载重新通过校验
+    let _ = fs::remove_file(verified_digest_path(&file_path));
+
+    let mut downloaded = false;
+    let mut last_error = String::new();
+    let mirrors = state.settings.lock().await.github_mirrors.clone();
+    'urls: for url in crate::github::mirror_urls(&update.download_url, &mirrors) {
+        for (via, client) in &clients {
+            let _ = app.emit("updater:download-source", serde_json::json!({ "url": crate::redact::redact(&url), "via": via }));
+            match download_with_resume(&app, client, &url, &part_path, &cancel_token, "updater:download-progress").await {
+                Ok(_) => {
+                    log::info!("Update downloaded via {}: {}", via, url);
+                    downloaded = true;
+                    break 'urls;
+                }
+                Err(_) if cancel_token.is_cancelled() => break 'urls,
+                Err(e) => {
+                    log::warn!("Update download via {} failed for {}: {}", via, url, e);
+                    last_error = e;
+                }
+            }
+        }
+    }
+    state.update_download_cancel.lock().await.take();
+
+    if cancel_token.is_cancelled() {
+        let _ = app.emit("updater:download-cancelled", ());
+        return Ok(serde_json::json!({ "success": false, "cancelled": true }));
+    }
+
+    if !downloaded {
+        let err = format!("All download attempts failed: {}", last_error);
+        let _ = app.emit("updater:download-error", crate::redact::redact(&err));
+        return Err(err);
+    }
+
+    // 更新包可能来自第三方镜像，没有校验值时拒绝使用
+    let Some(expected) = fetch_expected_sha256(&clients, update.sha256.as_deref(), update.checksum_url.as_deref(), &update.asset_name).await else {
+        let _ = fs::remove_file(&part_path);
+        let err = format!("No checksum available for {}, refusing unverified update", update.asset_name);
+        let _ = app.emit("updater:download-error", crate::redact::redact(&err));
+        return Err(err);
+    };
+    let actual = sha256_file(&part_path)?;
+    if actual != expected {
+        let _ = fs::remove_file(&part_path);
+        let err = format!("SHA256 mismatch for {}: expected {}, got {}", update.asset_name, expected, actual);
+        let _ = app.emit("updater:download-error", crate::redact::redact(&err));
+        return Err(err);
+    }
+    log::info!("Update SHA256 verified: {}", actual);
+
+    fs::rename(&part_path, &file_path).map_err(|e| e.to_string())?;
+    fs::write(verified_digest_path(&file_path), &actual).map_err(|e| e.to_string())?;
+    let _ = app.emit("updater:download-complete", ());
+
+    Ok(serde_json::json!({
+        "success": true,
+        "path": file_path.to_string_lossy(),
+        "isInstaller": update.is_installer
+    }))
+}
+
+#[tauri::command]
+pub async fn updater_download_cancel(state: State<'_, AppState>) -> Result<bool, String> {
+    if let Some(cancel) = state.update_download_cancel.lock().await.take() {
+        cancel.cancel();
+        return Ok(true);
+    }
+    Ok(false)
+}
+
+/// 用户确认后调用：安装包直接启动并退出应用，便携版 zip 打开所在目录由用户手动替换
+#[tauri::command]
+pub async fn updater_install(app: AppHandle, state: State<'_, AppState>, path: String) -> Result<(), String> {
+    let path = PathBuf::from(path);
+    let cache_dir = update_cache_dir(&state);
+    if path.parent() != Some(cache_dir.as_path()) || !path.is_file() {
+        return Err(format!("Not a downloaded update: {:?}", path));
+    }
+    ensure_verified(&path)?;
+
+    let is_installer = path.extension().and_then(|e| e.to_str()).map(|e| e.eq_ignore_ascii_case("exe")).unwrap_or(false);
+    if !is_installer {
+        return open::that(&cache_dir).map_err(|e| e.to_string());
+    }
+
+    // 安装程序需要替换正在使用的文件，先停止核心
+    if matches!(*state.proxy_state.lock().await, crate::types::ProxyState::Connected) {
+        crate::commands::singbox_stop(app.clone(), state.clone()).await?;
+    }
+
+    launch_installer(&path)?;
+    log::info!("Launched installer {:?}, exiting", path);
+    app.exit(0);
+    Ok(())
+}
+
+fn launch_installer(path: &Path) -> Result<(), String> {
+    let mut cmd = std::process::Command::new(path);
+    #[cfg(windows)]
+    cmd.creation_flags(0x00000008); // DETACHED_PROCESS
+    cmd.spawn().map_err(|e| format!("Failed to launch installer: {}", e))?;
+    Ok(())
+}
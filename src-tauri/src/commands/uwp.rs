@@ -0,0 +1,127 @@
+#[cfg(windows)]
+const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+#[derive(serde::Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct UwpApp {
+    pub name: String,
+    pub family_name: String,
+    /// 是否已允许访问 127.0.0.1（回环豁免）
+    pub exempt: bool,
+}
+
+/// PackageFamilyName 只包含字母、数字和 . _ -，其余输入一律拒绝
+fn is_valid_family_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'))
+}
+
+/// 解析 CheckNetIsolation LoopbackExempt -s 输出中的 "Name: xxx" 行
+#[cfg(windows)]
+fn parse_exempt_names(output: &str) -> Vec<String> {
+    output.lines()
+        .filter_map(|line| line.trim().strip_prefix("Name:"))
+        .map(|name| name.trim().to_lowercase())
+        .collect()
+}
+
+#[cfg(windows)]
+async fn check_net_isolation(args: &[&str]) -> Result<String, String> {
+    let output = tokio::process::Command::new("CheckNetIsolation.exe")
+        .args(args)
+        .creation_flags(CREATE_NO_WINDOW)
+        .output()
+        .await
+        .map_err(|e| e.to_string())?;
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    if !output.status.success() {
+        // CheckNetIsolation 把错误信息输出到 stdout
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let message = if stderr.trim().is_empty() { stdout.trim() } else { stderr.trim() };
+        return Err(message.to_string());
+    }
+    Ok(stdout)
+}
+
+async fn set_loopback_exempt(family_name: &str, exempt: bool) -> Result<(), String> {
+    #[cfg(windows)]
+    {
+        let action = if exempt { "-a" } else { "-d" };
+        let target = format!("-n={}", family_name);
+        check_net_isolation(&["LoopbackExempt", action, &target]).await.map(|_| ())
+    }
+
+    #[cfg(not(windows))]
+    {
+        let _ = (family_name, exempt);
+        Err("UWP loopback exemption is only available on Windows".to_string())
+    }
+}
+
+/// 列出已安装的 UWP 应用及其回环豁免状态
+#[tauri::command]
+pub async fn uwp_list_apps() -> Result<Vec<UwpApp>, String> {
+    #[cfg(windows)]
+    {
+        let output = tokio::process::Command::new("powershell")
+            .args([
+                "-NoProfile",
+                "-NonInteractive",
+                "-Command",
+                "Get-AppxPackage | Where-Object { -not $_.IsFramework } | Select-Object Name,PackageFamilyName | ConvertTo-Json -Compress",
+            ])
+            .creation_flags(CREATE_NO_WINDOW)
+            .output()
+            .await
+            .map_err(|e| e.to_string())?;
+        if !output.status.success() {
+            return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+        }
+
+        let exempt = parse_exempt_names(&check_net_isolation(&["LoopbackExempt", "-s"]).await?);
+
+        // 只有一个应用时 ConvertTo-Json 输出单个对象而不是数组
+        let value: serde_json::Value = serde_json::from_slice(&output.stdout).map_err(|e| e.to_string())?;
+        let items = match value {
+            serde_json::Value::Array(items) => items,
+            other => vec![other],
+        };
+
+        let mut apps: Vec<UwpApp> = items.iter()
+            .filter_map(|item| {
+                let name = item.get("Name")?.as_str()?.to_string();
+                let family_name = item.get("PackageFamilyName")?.as_str()?.to_string();
+                let exempt = exempt.contains(&family_name.to_lowercase());
+                Some(UwpApp { name, family_name, exempt })
+            })
+            .collect();
+        apps.sort_by_key(|a| a.name.to_lowercase());
+        apps.dedup_by(|a, b| a.family_name == b.family_name);
+        Ok(apps)
+    }
+
+    #[cfg(not(windows))]
+    Ok(Vec::new())
+}
+
+/// 为选中的 UWP 应用添加或移除回环豁免，需要管理员权限
+#[tauri::command]
+pub async fn uwp_set_loopback_exempt(family_names: Vec<String>, exempt: bool) -> Result<serde_json::Value, String> {
+    if let Some(invalid) = family_names.iter().find(|n| !is_valid_family_name(n)) {
+        return Err(format!("Invalid package family name: {}", invalid));
+    }
+
+    let mut updated = Vec::new();
+    let mut errors = Vec::new();
+    for family_name in &family_names {
+        match set_loopback_exempt(family_name, exempt).await {
+            Ok(_) => updated.push(family_name.clone()),
+            Err(e) => errors.push(serde_json::json!({ "familyName": family_name, "error": e })),
+        }
+    }
+
+    Ok(serde_json::json!({
+        "success": errors.is_empty(),
+        "updated": updated,
+        "errors": errors,
+    }))
+}
@@ -1,4 +1,102 @@
-use tauri::{AppHandle, WebviewWindow};
+use tauri::{AppHandle, Emitter, Manager, WebviewUrl, WebviewWindow, WebviewWindowBuilder};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use crate::state::AppState;
+use crate::types::ProxyState;
+
+const LOG_WINDOW_LABEL: &str = "logs";
+/// 可选的窗口背景材质
+pub(crate) const WINDOW_EFFECTS: [&str; 3] = ["none", "mica", "acrylic"];
+/// 窗口移动、缩放停止这么久后再保存，拖动过程中不反复写文件
+const WINDOW_STATE_SAVE_DELAY: std::time::Duration = std::time::Duration::from_millis(500);
+/// 恢复位置时窗口至少要有这么大一块（物理像素）落在某个显示器内，否则按默认位置打开
+const WINDOW_MIN_VISIBLE: i32 = 100;
+
+static WINDOW_STATE_CHANGED: once_cell::sync::Lazy<tokio::sync::Notify> = once_cell::sync::Lazy::new(tokio::sync::Notify::new);
+
+/// 主窗口上次的位置和大小（物理像素），最大化时保留还原前的大小
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WindowGeometry {
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+    maximized: bool,
+}
+
+fn window_state_file(app: &AppHandle) -> std::path::PathBuf {
+    app.state::<AppState>().data_dir.join("window_state.json")
+}
+
+fn load_window_geometry(app: &AppHandle) -> Option<WindowGeometry> {
+    let content = std::fs::read_to_string(window_state_file(app)).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+/// 显示器布局可能已变化（拔掉副屏、改分辨率），只在窗口仍足够可见时恢复位置
+fn is_on_screen(window: &WebviewWindow, geometry: &WindowGeometry) -> bool {
+    let monitors = window.available_monitors().unwrap_or_default();
+    monitors.iter().any(|monitor| {
+        let (position, size) = (monitor.position(), monitor.size());
+        let overlap_x = (geometry.x + geometry.width as i32).min(position.x + size.width as i32) - geometry.x.max(position.x);
+        let overlap_y = (geometry.y + geometry.height as i32).min(position.y + size.height as i32) - geometry.y.max(position.y);
+        overlap_x >= WINDOW_MIN_VISIBLE && overlap_y >= WINDOW_MIN_VISIBLE
+    })
+}
+
+/// 启动时恢复主窗口上次的大小、位置和最大化状态
+pub(crate) fn restore_window_state(window: &WebviewWindow) {
+    let Some(geometry) = load_window_geometry(window.app_handle()) else { return };
+    let _ = window.set_size(tauri::PhysicalSize::new(geometry.width, geometry.height));
+    if is_on_screen(window, &geometry) {
+        let _ = window.set_position(tauri::PhysicalPosition::new(geometry.x, geometry.y));
+    } else {
+        let _ = window.center();
+    }
+    if geometry.maximized {
+        let _ = window.maximize();
+    }
+}
+
+/// 主窗口移动或缩放时调用，实际保存由 start_window_state_saver 合并处理
+pub(crate) fn window_geometry_changed() {
+    WINDOW_STATE_CHANGED.notify_one();
+}
+
+pub(crate) fn start_window_state_saver(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            WINDOW_STATE_CHANGED.notified().await;
+            tokio::time::sleep(WINDOW_STATE_SAVE_DELAY).await;
+            if let Err(e) = save_window_state(&app) {
+                log::warn!("Failed to save window state: {}", e);
+            }
+        }
+    });
+}
+
+fn save_window_state(app: &AppHandle) -> Result<(), String> {
+    let Some(window) = app.get_webview_window("main") else { return Ok(()) };
+    // 最小化时 Windows 会把窗口移到 (-32000, -32000)，不记录
+    if window.is_minimized().unwrap_or(false) {
+        return Ok(());
+    }
+    let maximized = window.is_maximized().unwrap_or(false);
+    let geometry = match load_window_geometry(app) {
+        Some(previous) if maximized => WindowGeometry { maximized, ..previous },
+        _ => {
+            let position = window.outer_position().map_err(|e| e.to_string())?;
+            let size = window.inner_size().map_err(|e| e.to_string())?;
+            WindowGeometry { x: position.x, y: position.y, width: size.width, height: size.height, maximized }
+        }
+    };
+    let content = serde_json::to_string_pretty(&geometry).map_err(|e| e.to_string())?;
+    std::fs::write(window_state_file(app), content).map_err(|e| e.to_string())
+}
+
+/// 退出流程只执行一次，避免托盘和前端同时触发
+static QUITTING: AtomicBool = AtomicBool::new(false);
 
 #[tauri::command]
 pub async fn window_minimize(window: WebviewWindow) -> Result<(), String> {
@@ -16,7 +114,12 @@ pub async fn window_maximize(window: WebviewWindow) -> Result<(), String> {
 
 #[tauri::command]
 pub async fn window_close(window: WebviewWindow) -> Result<(), String> {
-    window.hide().map_err(|e| e.to_string())
+    // 主窗口隐藏到托盘，日志等附属窗口直接关闭
+    if window.label() == "main" {
+        window.hide().map_err(|e| e.to_string())
+    } else {
+        window.close().map_err(|e| e.to_string())
+    }
 }
 
 #[tauri::command]
@@ -25,8 +128,98 @@ pub async fn window_show(window: WebviewWindow) -> Result<(), String> {
     window.set_focus().map_err(|e| e.to_string())
 }
 
+/// 给所有窗口应用或移除 Mica / Acrylic 背景。材质透过网页显示，开启时把网页背景设为透明，
+/// 前端需改用半透明背景；set_effects 要求窗口以透明方式创建，主窗口和日志窗口都已开启 transparent。
+/// 系统不支持时 set_effects 不会生效，窗口保持原样
+pub(crate) fn apply_window_effect(app: &AppHandle, effect: &str) {
+    use tauri::window::{Color, Effect, EffectsBuilder};
+
+    let effects = match effect {
+        "mica" => Some(EffectsBuilder::new().effect(Effect::Mica).build()),
+        "acrylic" => Some(EffectsBuilder::new().effect(Effect::Acrylic).build()),
+        _ => None,
+    };
+    let background = effects.as_ref().map(|_| Color(0, 0, 0, 0));
+    for window in app.webview_windows().values() {
+        let result = window.set_effects(effects.clone())
+            .and_then(|_| window.set_background_color(background));
+        if let Err(e) = result {
+            log::warn!("Failed to set window effect of {}: {}", window.label(), e);
+        }
+    }
+}
+
+/// 打开独立的日志窗口，已打开时切到前台。
+/// 页面带 ?window=logs，前端据此只渲染日志视图；日志仍通过 singbox:log 事件推送，关闭窗口即停止接收
 #[tauri::command]
-pub async fn quit_app(app: AppHandle) -> Result<(), String> {
-    app.exit(0);
+pub async fn open_log_window(app: AppHandle) -> Result<(), String> {
+    if let Some(window) = app.get_webview_window(LOG_WINDOW_LABEL) {
+        window.unminimize().map_err(|e| e.to_string())?;
+        window.show().map_err(|e| e.to_string())?;
+        return window.set_focus().map_err(|e| e.to_string());
+    }
+    WebviewWindowBuilder::new(&app, LOG_WINDOW_LABEL, WebviewUrl::App("index.html?window=logs".into()))
+        .title("KunBox - Logs")
+        .inner_size(900.0, 600.0)
+        .min_inner_size(500.0, 300.0)
+        .decorations(false)
+        .transparent(true)
+        .build()
+        .map_err(|e| e.to_string())?;
+    let state = app.state::<AppState>();
+    let (theme, effect) = {
+        let settings = state.settings.lock().await;
+        (settings.theme.clone(), settings.window_effect.clone())
+    };
+    crate::commands::theme::apply_window_theme(&app, &theme);
+    apply_window_effect(&app, &effect);
     Ok(())
 }
+
+/// 有序退出：断开内核并恢复系统代理 / WinHTTP / 网卡 DNS，取消后台任务，结束临时内核后再退出
+async fn graceful_quit(app: &AppHandle) {
+    if QUITTING.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    let state = app.state::<AppState>();
+    // 断开会把会话记为未连接，退出时保留原状态，下次启动才能恢复
+    let session = crate::commands::session::load_session(&state).filter(|s| s.connected);
+    if !matches!(*state.proxy_state.lock().await, ProxyState::Idle) {
+        if let Err(e) = crate::commands::singbox_stop(app.clone(), state.clone()).await {
+            log::warn!("Failed to stop core on quit: {}", e);
+        }
+    }
+    if let Some(session) = session {
+        crate::commands::session::save_session(&state, &session);
+    }
+    for cancel in [&state.traffic_cancel, &state.proxy_guard_cancel, &state.kernel_download_cancel, &state.update_download_cancel, &state.latency_test_cancel] {
+        if let Some(token) = cancel.lock().await.take() {
+            token.cancel();
+        }
+    }
+    crate::commands::profiles::stop_temp_singbox().await;
+    log::logger().flush();
+    app.exit(0);
+}
+
+/// 请求退出；开启 confirmQuit 且连接中时先显示窗口并通知前端确认，返回是否已开始退出
+pub(crate) async fn request_quit(app: &AppHandle, force: bool) -> bool {
+    let state = app.state::<AppState>();
+    let confirm = state.settings.lock().await.confirm_quit;
+    if confirm && !force && matches!(*state.proxy_state.lock().await, ProxyState::Connected) {
+        if let Some(window) = app.get_webview_window("main") {
+            let _ = window.show();
+            let _ = window.set_focus();
+        }
+        let _ = app.emit("app:quit-confirm", ());
+        return false;
+    }
+    graceful_quit(app).await;
+    true
+}
+
+/// 前端确认后以 force 再次调用即可跳过确认
+#[tauri::command]
+pub async fn quit_app(app: AppHandle, force: Option<bool>) -> Result<bool, String> {
+    Ok(request_quit(&app, force.unwrap_or(false)).await)
+}
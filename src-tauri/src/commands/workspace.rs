@@ -0,0 +1,211 @@
+use tauri::{AppHandle, Emitter, State};
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::state::AppState;
+use crate::types::{AppSettings, ProxyState};
+
+/// 默认工作区的名称，对应 data_dir 本身，兼容旧版本的数据位置
+const DEFAULT_WORKSPACE: &str = "default";
+const WORKSPACE_NAME_MAX_LEN: usize = 32;
+
+fn workspaces_root(data_dir: &Path) -> PathBuf {
+    data_dir.join("workspaces")
+}
+
+fn active_workspace_file(data_dir: &Path) -> PathBuf {
+    data_dir.join("workspace.json")
+}
+
+/// 工作区的数据目录，None 为默认工作区
+pub(crate) fn workspace_path(data_dir: &Path, name: Option<&str>) -> PathBuf {
+    match name {
+        Some(name) => workspaces_root(data_dir).join(name),
+        None => data_dir.to_path_buf(),
+    }
+}
+
+/// 读取上次使用的工作区，目录已不存在时回到默认工作区
+pub(crate) fn load_active_workspace(data_dir: &Path) -> Option<String> {
+    let content = fs::read_to_string(active_workspace_file(data_dir)).ok()?;
+    let value: serde_json::Value = serde_json::from_str(&content).ok()?;
+    let name = value["active"].as_str()?.to_string();
+    workspace_path(data_dir, Some(&name)).is_dir().then_some(name)
+}
+
+fn save_active_workspace(data_dir: &Path, name: Option<&str>) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(&serde_json::json!({ "active": name })).map_err(|e| e.to_string())?;
+    fs::write(active_workspace_file(data_dir), content).map_err(|e| e.to_string())
+}
+
+/// 名称直接作为目录名，只允许字母、数字、空格、- 和 _
+fn validate_workspace_name(name: &str) -> Result<String, String> {
+    let name = name.trim();
+    if name.is_empty() || name.chars().count() > WORKSPACE_NAME_MAX_LEN {
+        return Err(format!("Workspace name must be 1-{} characters", WORKSPACE_NAME_MAX_LEN));
+    }
+    if !name.chars().all(|c| c.is_alphanumeric() || c == ' ' || c == '-' || c == '_') {
+        return Err("Workspace name may only contain letters, digits, spaces, '-' and '_'".to_string());
+    }
+    if name.eq_ignore_ascii_case(DEFAULT_WORKSPACE) {
+        return Err(format!("\"{}\" is reserved", DEFAULT_WORKSPACE));
+    }
+    Ok(name.to_string())
+}
+
+fn copy_dir(from: &Path, to: &Path) -> Result<(), String> {
+    fs::create_dir_all(to).map_err(|e| e.to_string())?;
+    for entry in fs::read_dir(from).map_err(|e| e.to_string())?.flatten() {
+        let path = entry.path();
+        let target = to.join(entry.file_name());
+        if path.is_dir() {
+            copy_dir(&path, &target)?;
+        } else {
+            fs::copy(&path, &target).map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(())
+}
+
+/// 从当前工作区目录重新读取设置、配置和规则集，并同步加密、语言、快捷键、主题、窗口材质和托盘
+pub(crate) async fn reload_workspace_data(app: &AppHandle, state: &AppState) -> Result<(), String> {
+    let settings: AppSettings = crate::migrate::load(&state.settings_file(), crate::migrate::DataFile::Settings)
+        .unwrap_or_default();
+    state.encrypt_data.store(settings.encrypt_data, std::sync::atomic::Ordering::Relaxed);
+    crate::i18n::set_locale(&settings.language);
+    let failed = crate::commands::hotkeys::register_hotkeys(app, &settings.hotkeys);
+    if !failed.is_empty() {
+        let conflicts: Vec<_> = failed.iter()
+            .map(|(action, message)| serde_json::json!({ "action": action, "message": message }))
+            .collect();
+        let _ = app.emit("hotkeys:conflict", conflicts);
+    }
+    crate::commands::theme::theme_setting_changed(app, &settings.theme);
+    crate::commands::window::apply_window_effect(app, &settings.window_effect);
+    *state.settings.lock().await = settings;
+    crate::commands::profiles::rewrite_encrypted_files(state)?;
+    crate::tray::refresh_tray_menu(app);
+    *state.profiles_data.lock().await = crate::commands::profiles::load_profiles_data(state);
+    *state.rulesets.lock().await = crate::commands::rulesets::load_rulesets(state);
+    Ok(())
+}
+
+/// 列出所有工作区，默认工作区始终排在第一位
+#[tauri::command]
+pub async fn workspace_list(state: State<'_, AppState>) -> Result<serde_json::Value, String> {
+    let mut names: Vec<String> = fs::read_dir(workspaces_root(&state.data_dir))
+        .map(|dir| {
+            dir.flatten()
+                .filter(|entry| entry.path().is_dir())
+                .filter_map(|entry| entry.file_name().into_string().ok())
+                .collect()
+        })
+        .unwrap_or_default();
+    names.sort();
+    names.insert(0, DEFAULT_WORKSPACE.to_string());
+
+    let active = state.workspace.read().map_err(|e| e.to_string())?.clone();
+    Ok(serde_json::json!({
+        "active": active.as_deref().unwrap_or(DEFAULT_WORKSPACE),
+        "workspaces": names,
+    }))
+}
+
+/// 新建工作区；copy_current 为 true 时复制当前工作区的配置、规则集和设置作为起点
+#[tauri::command]
+pub async fn workspace_create(
+    state: State<'_, AppState>,
+    name: String,
+    copy_current: Option<bool>,
+) -> Result<serde_json::Value, String> {
+    let name = validate_workspace_name(&name)?;
+    let dir = workspace_path(&state.data_dir, Some(&name));
+    if dir.exists() {
+        return Err(format!("Workspace {} already exists", name));
+    }
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+
+    if copy_current.unwrap_or(false) {
+        let current = state.workspace_dir();
+        let copied = (|| -> Result<(), String> {
+            for file in crate::commands::backup::BACKUP_FILES {
+                let source = current.join(file);
+                if source.is_file() {
+                    fs::copy(&source, dir.join(file)).map_err(|e| e.to_string())?;
+                }
+            }
+            for sub in crate::commands::backup::BACKUP_DIRS {
+                let source = current.join(sub);
+                if source.is_dir() {
+                    copy_dir(&source, &dir.join(sub))?;
+                }
+            }
+            Ok(())
+        })();
+        if let Err(e) = copied {
+            let _ = fs::remove_dir_all(&dir);
+            return Err(format!("Failed to copy workspace: {}", e));
+        }
+    }
+
+    Ok(serde_json::json!({ "success": true, "name": name }))
+}
+
+/// 删除工作区及其全部数据，不能删除默认工作区和当前工作区
+#[tauri::command]
+pub async fn workspace_delete(state: State<'_, AppState>, name: String) -> Result<serde_json::Value, String> {
+    let name = validate_workspace_name(&name)?;
+    if state.workspace.read().map_err(|e| e.to_string())?.as_deref() == Some(name.as_str()) {
+        return Err("Cannot delete the active workspace".to_string());
+    }
+    let dir = workspace_path(&state.data_dir, Some(&name));
+    if !dir.is_dir() {
+        return Err(format!("Workspace {} not found", name));
+    }
+    fs::remove_dir_all(&dir).map_err(|e| format!("Failed to delete workspace: {}", e))?;
+    Ok(serde_json::json!({ "success": true }))
+}
+
+/// 切换到指定工作区：连接中会先断开，切换后用新工作区的配置重新连接
+#[tauri::command]
+pub async fn workspace_switch(app: AppHandle, state: State<'_, AppState>, name: String) -> Result<serde_json::Value, String> {
+    let target = match name.trim() {
+        DEFAULT_WORKSPACE => None,
+        other => {
+            let other = validate_workspace_name(other)?;
+            if !workspace_path(&state.data_dir, Some(&other)).is_dir() {
+                return Err(format!("Workspace {} not found", other));
+            }
+            Some(other)
+        }
+    };
+    if *state.workspace.read().map_err(|e| e.to_string())? == target {
+        return Ok(serde_json::json!({ "success": true, "restarted": false }));
+    }
+
+    let was_connected = matches!(*state.proxy_state.lock().await, ProxyState::Connected);
+    if was_connected {
+        crate::commands::singbox_stop(app.clone(), state.clone()).await?;
+    }
+
+    save_active_workspace(&state.data_dir, target.as_deref())?;
+    *state.workspace.write().map_err(|e| e.to_string())? = target.clone();
+    reload_workspace_data(&app, &state).await?;
+
+    let active = target.as_deref().unwrap_or(DEFAULT_WORKSPACE);
+    let _ = app.emit("workspace:switched", serde_json::json!({ "name": active }));
+
+    let mut error = None;
+    if was_connected {
+        let result = crate::commands::singbox_start(app.clone(), state.clone()).await?;
+        if !result.success {
+            error = result.error;
+        }
+    }
+
+    Ok(serde_json::json!({
+        "success": error.is_none(),
+        "name": active,
+        "restarted": was_connected,
+        "error": error,
+    }))
+}
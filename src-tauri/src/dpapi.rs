@@ -0,0 +1,93 @@
+use std::fs;
+use std::path::Path;
+
+/// 加密文件的文件头，明文 JSON 不可能以此开头
+const ENCRYPTED_MAGIC: &[u8] = b"KUNBOX-DPAPI1\n";
+
+/// 读取数据文件，带加密文件头的先用 DPAPI 解密，调用方无需关心文件是否加密
+pub fn read_data_file(path: &Path) -> Result<String, String> {
+    let bytes = fs::read(path).map_err(|e| e.to_string())?;
+    let plain = match bytes.strip_prefix(ENCRYPTED_MAGIC) {
+        Some(cipher) => unprotect(cipher)?,
+        None => bytes,
+    };
+    String::from_utf8(plain).map_err(|e| e.to_string())
+}
+
+/// 文件是否为加密格式
+pub fn is_encrypted_file(path: &Path) -> bool {
+    use std::io::Read;
+    let mut header = [0u8; ENCRYPTED_MAGIC.len()];
+    fs::File::open(path).and_then(|mut f| f.read_exact(&mut header)).is_ok() && header == ENCRYPTED_MAGIC
+}
+
+/// 写入数据文件，encrypt 为 true 时用当前用户的 DPAPI 密钥加密
+pub fn write_data_file(path: &Path, content: &str, encrypt: bool) -> Result<(), String> {
+    if !encrypt {
+        return fs::write(path, content).map_err(|e| e.to_string());
+    }
+    let mut bytes = ENCRYPTED_MAGIC.to_vec();
+    bytes.extend(protect(content.as_bytes())?);
+    fs::write(path, bytes).map_err(|e| e.to_string())
+}
+
+fn protect(data: &[u8]) -> Result<Vec<u8>, String> {
+    #[cfg(windows)]
+    return win::protect(data);
+    #[cfg(not(windows))]
+    {
+        let _ = data;
+        Err("Data encryption is only available on Windows".to_string())
+    }
+}
+
+fn unprotect(data: &[u8]) -> Result<Vec<u8>, String> {
+    #[cfg(windows)]
+    return win::unprotect(data);
+    #[cfg(not(windows))]
+    {
+        let _ = data;
+        Err("Data encryption is only available on Windows".to_string())
+    }
+}
+
+#[cfg(windows)]
+mod win {
+    use windows::core::PCWSTR;
+    use windows::Win32::Foundation::{LocalFree, HLOCAL};
+    use windows::Win32::Security::Cryptography::{
+        CryptProtectData, CryptUnprotectData, CRYPTPROTECT_UI_FORBIDDEN, CRYPT_INTEGER_BLOB,
+    };
+
+    fn input_blob(data: &[u8]) -> CRYPT_INTEGER_BLOB {
+        CRYPT_INTEGER_BLOB { cbData: data.len() as u32, pbData: data.as_ptr() as *mut u8 }
+    }
+
+    /// 复制系统分配的输出缓冲区并释放
+    unsafe fn take_blob(blob: CRYPT_INTEGER_BLOB) -> Vec<u8> {
+        let data = std::slice::from_raw_parts(blob.pbData, blob.cbData as usize).to_vec();
+        LocalFree(Some(HLOCAL(blob.pbData as *mut core::ffi::c_void)));
+        data
+    }
+
+    pub fn protect(data: &[u8]) -> Result<Vec<u8>, String> {
+        let input = input_blob(data);
+        let mut output = CRYPT_INTEGER_BLOB::default();
+        unsafe {
+            CryptProtectData(&input, PCWSTR::null(), None, None, None, CRYPTPROTECT_UI_FORBIDDEN, &mut output)
+                .map_err(|e| format!("Failed to encrypt data: {}", e))?;
+            Ok(take_blob(output))
+        }
+    }
+
+    pub fn unprotect(data: &[u8]) -> Result<Vec<u8>, String> {
+        let input = input_blob(data);
+        let mut output = CRYPT_INTEGER_BLOB::default();
+        unsafe {
+            // 换了用户或重装系统后密钥不同，无法解密
+            CryptUnprotectData(&input, None, None, None, None, CRYPTPROTECT_UI_FORBIDDEN, &mut output)
+                .map_err(|e| format!("Failed to decrypt data: {}", e))?;
+            Ok(take_blob(output))
+        }
+    }
+}
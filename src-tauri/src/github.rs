@@ -0,0 +1,27 @@
+/// 默认的 GitHub 加速镜像（前缀拼接完整地址）
+pub const DEFAULT_MIRRORS: &[&str] = &[
+    "https://ghfast.top/",
+    "https://gh-proxy.com/",
+];
+
+/// 生成下载地址列表：先直连原始地址，GitHub 地址再按镜像顺序展开
+pub fn mirror_urls(url: &str, mirrors: &[String]) -> Vec<String> {
+    let mut urls = vec![url.to_string()];
+    if url.starts_with("https://github.com/") || url.starts_with("https://raw.githubusercontent.com/") {
+        urls.extend(mirrors.iter().map(|mirror| format!("{}{}", mirror, url)));
+    }
+    urls
+}
+
+/// GitHub API 请求构造：统一 Accept 头，并在配置了 token 时附带认证（仅发送给 api.github.com）
+pub fn api_get(client: &reqwest::Client, url: &str, token: Option<&str>) -> reqwest::RequestBuilder {
+    let mut request = client.get(url)
+        .header("Accept", "application/vnd.github.v3+json")
+        .header("User-Agent", "KunBox-Windows-App");
+    if let Some(token) = token.filter(|t| !t.is_empty()) {
+        if url.starts_with("https://api.github.com/") {
+            request = request.bearer_auth(token);
+        }
+    }
+    request
+}
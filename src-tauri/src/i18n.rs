@@ -0,0 +1,53 @@
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// 支持的界面语言，与设置中的 language 对应
+pub const LANGUAGES: [&str; 2] = ["zh-CN", "en"];
+
+/// 当前语言在 LANGUAGES 中的下标；托盘和命令返回的错误都是同步读取，放在全局
+static LOCALE: AtomicU8 = AtomicU8::new(0);
+
+/// (键, 中文, 英文)
+const MESSAGES: &[(&str, &str, &str)] = &[
+    ("tray.show", "显示", "Show"),
+    ("tray.quit", "退出", "Quit"),
+    ("tray.connect", "连接", "Connect"),
+    ("tray.disconnect", "断开", "Disconnect"),
+    ("tray.connecting", "连接中…", "Connecting…"),
+    ("tray.disconnecting", "断开中…", "Disconnecting…"),
+    ("tray.mode", "模式", "Mode"),
+    ("tray.mode.rule", "规则", "Rule"),
+    ("tray.mode.global-proxy", "全局代理", "Global"),
+    ("tray.mode.global-direct", "全局直连", "Direct"),
+    ("tray.profiles", "配置", "Profiles"),
+    ("tray.noProfiles", "无可用配置", "No profiles"),
+    ("tray.nodes", "节点", "Nodes"),
+    ("tray.noNodes", "无可用节点", "No nodes"),
+    ("tray.testLatency", "测速", "Test latency"),
+    ("tray.testingLatency", "测速中…", "Testing…"),
+    ("tray.timeout", "超时", "timeout"),
+    ("tray.selectBest", "切换到最快节点", "Switch to fastest node"),
+    ("taskbar.connected", "已连接", "Connected"),
+    ("taskbar.error", "连接异常", "Connection problem"),
+    ("quota.low", "剩余流量", "data left"),
+    ("quota.expiring", "到期剩余天数", "days until expiry"),
+    ("error.kernelNotFound", "未找到 sing-box.exe，请先安装内核", "sing-box.exe not found. Please install kernel first."),
+    ("error.noActiveProfile", "没有选中的配置", "No active profile"),
+    ("error.noNodes", "当前配置中没有节点", "No nodes in active profile"),
+    ("error.notRunning", "代理未运行", "VPN not running"),
+    ("error.noNodesToSwitch", "没有可切换的节点", "No nodes to switch to"),
+    ("error.noTestedNodes", "没有测速可用的节点，请先测速", "No reachable nodes, test latency first"),
+];
+
+pub fn set_locale(language: &str) {
+    let index = LANGUAGES.iter().position(|l| *l == language).unwrap_or(0);
+    LOCALE.store(index as u8, Ordering::Relaxed);
+}
+
+/// 按当前语言取文案，未收录的键原样返回
+pub fn tr(key: &'static str) -> &'static str {
+    let Some((_, zh, en)) = MESSAGES.iter().find(|(k, _, _)| *k == key) else { return key };
+    match LOCALE.load(Ordering::Relaxed) {
+        1 => en,
+        _ => zh,
+    }
+}
@@ -0,0 +1,37 @@
+use std::net::Ipv4Addr;
+
+/// 发送一个 ICMP Echo，返回往返时间（毫秒）；超时、不可达或系统不支持时返回 None。
+/// IcmpSendEcho 不需要管理员权限，但会阻塞到收到回复或超时，调用方应放到阻塞线程里
+#[cfg(windows)]
+pub fn ping(addr: Ipv4Addr, timeout_ms: u32) -> Option<u32> {
+    use windows::Win32::NetworkManagement::IpHelper::{IcmpCloseHandle, IcmpCreateFile, IcmpSendEcho, ICMP_ECHO_REPLY};
+
+    const PAYLOAD: &[u8; 32] = b"abcdefghijklmnopqrstuvwabcdefghi";
+    // 回复缓冲区要能放下一个 ICMP_ECHO_REPLY、请求数据和 8 字节的 ICMP 错误信息
+    let mut reply = vec![0u8; std::mem::size_of::<ICMP_ECHO_REPLY>() + PAYLOAD.len() + 8];
+    unsafe {
+        let handle = IcmpCreateFile().ok()?;
+        let count = IcmpSendEcho(
+            handle,
+            u32::from_ne_bytes(addr.octets()),
+            PAYLOAD.as_ptr() as *const _,
+            PAYLOAD.len() as u16,
+            None,
+            reply.as_mut_ptr() as *mut _,
+            reply.len() as u32,
+            timeout_ms,
+        );
+        let _ = IcmpCloseHandle(handle);
+        if count == 0 {
+            return None;
+        }
+        let echo = std::ptr::read_unaligned(reply.as_ptr() as *const ICMP_ECHO_REPLY);
+        // IP_SUCCESS
+        (echo.Status == 0).then_some(echo.RoundTripTime)
+    }
+}
+
+#[cfg(not(windows))]
+pub fn ping(_addr: Ipv4Addr, _timeout_ms: u32) -> Option<u32> {
+    None
+}
@@ -1,30 +1,70 @@
-use tauri::Manager;
+use tauri::{Emitter, Manager};
 use std::path::PathBuf;
 
 mod types;
 mod state;
+mod github;
+mod sysproxy;
+mod adapter_dns;
+mod power;
+mod icmp;
+mod migrate;
+mod dpapi;
+mod i18n;
+mod tray;
+#[cfg(windows)]
+mod taskbar;
+mod redact;
+mod cli;
 mod commands;
 
 use state::AppState;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    // --status only reports on the running instance and never opens a window
+    let launch_args = cli::parse(&std::env::args().collect::<Vec<_>>());
+    if launch_args.status {
+        std::process::exit(cli::print_status(&get_data_dir()));
+    }
+
     tauri::Builder::default()
-        .plugin(tauri_plugin_single_instance::init(|app, _args, _cwd| {
+        .plugin(tauri_plugin_single_instance::init(|app, args, cwd| {
+            // Let the UI see everything the second launch was given
+            let _ = app.emit("app:second-instance", serde_json::json!({ "args": &args, "cwd": &cwd }));
+            // Command line control flags run in the background without bringing up the window
+            let cli_args = cli::parse(&args);
+            if cli_args.has_actions() {
+                cli::dispatch(app, cli_args);
+                return;
+            }
             if let Some(window) = app.get_webview_window("main") {
                 let _ = window.show();
                 let _ = window.set_focus();
             }
+            // Clicking an import link or opening a profile file while running starts a second instance carrying it
+            commands::deeplink::handle_launch_args(app, &args, std::path::Path::new(&cwd));
         }))
+        .plugin(tauri_plugin_global_shortcut::Builder::new().build())
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_process::init())
         .plugin(tauri_plugin_fs::init())
         .plugin(
             tauri_plugin_log::Builder::default()
                 .level(log::LevelFilter::Info)
+                // Logs are often shared for troubleshooting; strip subscription tokens and node credentials
+                .format(|out, message, record| {
+                    out.finish(format_args!(
+                        "{}[{}][{}] {}",
+                        chrono::Local::now().format("[%Y-%m-%d][%H:%M:%S]"),
+                        record.target(),
+                        record.level(),
+                        redact::redact(&message.to_string())
+                    ))
+                })
                 .build(),
         )
-        .setup(|app| {
+        .setup(move |app| {
             // Initialize app state
             let data_dir = get_data_dir();
             std::fs::create_dir_all(&data_dir).ok();
@@ -36,29 +76,119 @@ pub fn run() {
             log::info!("Data directory: {:?}", data_dir);
             
             let state = AppState::new(data_dir);
+            // Freshly created state, so the lock can't be contended here
+            let (start_minimized, hotkeys, theme, effect) = state.settings.try_lock()
+                .map(|s| (s.start_minimized, s.hotkeys.clone(), s.theme.clone(), s.window_effect.clone()))
+                .unwrap_or_default();
+            // Config generation reads the in-memory rulesets, so load them before anything can start the core
+            if let Ok(mut rulesets) = state.rulesets.try_lock() {
+                *rulesets = commands::rulesets::load_rulesets(&state);
+            }
             app.manage(state);
 
-            // Show window after setup
+            // Register global hotkeys; report the ones taken by other applications
+            let failed = commands::hotkeys::register_hotkeys(app.handle(), &hotkeys);
+            if !failed.is_empty() {
+                log::warn!("Failed to register hotkeys: {:?}", failed);
+                let conflicts: Vec<_> = failed.iter()
+                    .map(|(action, message)| serde_json::json!({ "action": action, "message": message }))
+                    .collect();
+                let _ = app.emit("hotkeys:conflict", conflicts);
+            }
+
+            // Handle clash:// and sing-box:// import links and profile files, including the ones we were launched with
+            commands::deeplink::register_url_schemes();
+            let args: Vec<String> = std::env::args().collect();
+            let cwd = std::env::current_dir().unwrap_or_default();
+            commands::deeplink::handle_launch_args(app.handle(), &args, &cwd);
+
+            // Move kernels downloaded into the resource dir by older versions
+            commands::kernel::migrate_legacy_kernel_dir(app.handle());
+
+            // Verify the kernel runs before the user hits a start failure
+            let handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                let report = commands::kernel::check_kernel_integrity(&handle).await;
+                if !report.ok {
+                    log::warn!("Kernel integrity check failed: {:?}", report.reason);
+                    let _ = handle.emit("kernel:invalid", &report);
+                }
+            });
+
+            // Keep remote rulesets fresh in the background
+            commands::rulesets::start_ruleset_auto_update(app.handle().clone());
+
+            // Apply time-windowed rules at their boundaries
+            commands::rules::start_rule_scheduler(app.handle().clone());
+
+            // Recover the connection when the default network interface changes
+            commands::network::start_network_monitor(app.handle().clone());
+
+            // Connect on launch once the network is usable
+            commands::network::start_auto_connect(app.handle().clone());
+
+            // Launched with control flags: run them and stay in the tray
+            let headless = launch_args.has_actions();
+            if headless {
+                cli::dispatch(app.handle(), launch_args);
+            }
+
+            // Restore the last size and position, then show the window unless configured to start hidden in the tray
             if let Some(window) = app.get_webview_window("main") {
-                let _ = window.show();
+                commands::window::restore_window_state(&window);
+                commands::theme::apply_window_theme(app.handle(), &theme);
+                commands::window::apply_window_effect(app.handle(), &effect);
+                if !start_minimized && !headless {
+                    let _ = window.show();
+                }
             }
+            commands::window::start_window_state_saver(app.handle().clone());
 
             // Setup tray icon
-            setup_tray(app)?;
+            tray::setup_tray(app)?;
+
+            // Warn about subscriptions running out of data or close to expiry
+            commands::quota::start_quota_monitor(app.handle().clone());
 
             Ok(())
         })
         .on_window_event(|window, event| {
-            if let tauri::WindowEvent::CloseRequested { api, .. } = event {
-                // Hide window instead of closing
-                let _ = window.hide();
-                api.prevent_close();
+            match event {
+                // Hide the main window instead of closing; secondary windows (logs) close normally
+                tauri::WindowEvent::CloseRequested { api, .. } if window.label() == "main" => {
+                    let _ = window.hide();
+                    api.prevent_close();
+                }
+                tauri::WindowEvent::Moved(_) | tauri::WindowEvent::Resized(_) if window.label() == "main" => {
+                    commands::window::window_geometry_changed();
+                }
+                tauri::WindowEvent::ThemeChanged(_) if window.label() == "main" => {
+                    commands::theme::system_theme_changed(window.app_handle());
+                }
+                // The taskbar button is recreated after hiding to the tray, which drops the overlay badge
+                #[cfg(windows)]
+                tauri::WindowEvent::Focused(true) if window.label() == "main" => {
+                    window.state::<AppState>().tray_refresh.notify_one();
+                }
+                _ => {}
             }
         })
         .invoke_handler(tauri::generate_handler![
             // Settings
             commands::get_settings,
             commands::set_settings,
+            commands::settings_reset,
+            commands::factory_reset,
+            commands::backup_create,
+            commands::backup_restore,
+            commands::workspace_list,
+            commands::workspace_create,
+            commands::workspace_delete,
+            commands::workspace_switch,
+            commands::mirror_list,
+            commands::mirror_add,
+            commands::mirror_remove,
+            commands::mirror_test,
             // Profiles
             commands::profile_list,
             commands::profile_add,
@@ -67,6 +197,8 @@ pub fn run() {
             commands::profile_set_active,
             commands::profile_edit,
             commands::profile_set_enabled,
+            commands::profile_set_quota_alert,
+            commands::quota_warnings,
             // Nodes
             commands::node_list,
             commands::node_set_active,
@@ -75,6 +207,17 @@ pub fn run() {
             commands::node_export,
             commands::node_test_latency,
             commands::node_test_all,
+            commands::node_test_cancel,
+            commands::node_latency_stats,
+            commands::node_speed_test,
+            commands::node_speed_results,
+            commands::node_unlock_test,
+            commands::node_unlock_results,
+            commands::node_udp_test,
+            commands::node_udp_results,
+            commands::failover_history,
+            commands::failover_history_clear,
+            commands::node_select_best,
             // Profiles extra
             commands::profile_import_content,
             // Rulesets
@@ -82,33 +225,99 @@ pub fn run() {
             commands::ruleset_save,
             commands::ruleset_download,
             commands::ruleset_is_cached,
+            commands::ruleset_check_updates,
+            commands::ruleset_list_presets,
+            commands::ruleset_apply_preset,
+            commands::ruleset_import_file,
+            commands::ruleset_add_country,
+            commands::ruleset_import_blocklist,
+            commands::ruleset_delete,
+            commands::ruleset_inspect,
+            commands::ruleset_export_bundle,
+            commands::ruleset_import_bundle,
             commands::ruleset_fetch_hub,
+            // Custom rules
+            commands::rule_list,
+            commands::rule_add,
+            commands::rule_update,
+            commands::rule_delete,
+            commands::rule_import_clash,
+            commands::process_list,
             // Singbox
             commands::singbox_start,
             commands::singbox_stop,
             commands::singbox_restart,
             commands::singbox_get_status,
+            commands::get_full_state,
+            commands::session_get_last,
+            commands::session_resume,
             commands::singbox_switch_node,
+            commands::singbox_set_mode,
             commands::singbox_enable_system_proxy,
             commands::singbox_disable_system_proxy,
+            commands::singbox_enable_winhttp_proxy,
+            commands::singbox_disable_winhttp_proxy,
+            commands::network_check_connectivity,
+            commands::network_current,
+            commands::network_diagnose,
+            commands::network_lan_share,
+            commands::network_list_bind_addresses,
+            commands::uwp_list_apps,
+            commands::uwp_set_loopback_exempt,
+            commands::terminal_proxy_env,
+            commands::terminal_open_with_proxy,
+            commands::launch_app_with_proxy,
             // Window
             commands::window_minimize,
             commands::window_maximize,
             commands::window_close,
             commands::window_show,
             commands::quit_app,
+            commands::open_log_window,
+            commands::deeplink_pending,
+            commands::deeplink_accept,
+            commands::deeplink_reject,
+            commands::theme_get_effective,
             // Kernel
             commands::kernel_get_local_version,
             commands::kernel_get_remote_releases,
+            commands::kernel_check_update,
+            commands::kernel_get_changelog,
             commands::kernel_download,
+            commands::kernel_download_cancel,
             commands::kernel_rollback,
             commands::kernel_can_rollback,
+            commands::kernel_rollback_to,
+            commands::kernel_list_backups,
+            commands::kernel_list_installed,
+            commands::kernel_set_active,
+            commands::kernel_validate_path,
+            commands::kernel_check_integrity,
             commands::kernel_clear_cache,
             commands::kernel_open_releases_page,
             commands::kernel_open_directory,
+            // Updater
+            commands::updater_check,
+            commands::updater_download,
+            commands::updater_download_cancel,
+            commands::updater_install,
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            if let tauri::RunEvent::Exit = event {
+                // Give the user's own proxy settings back even when exiting while connected
+                if let Some(state) = app.try_state::<AppState>() {
+                    if let Err(e) = commands::singbox::restore_system_proxy(&state) {
+                        log::warn!("Failed to restore system proxy on exit: {}", e);
+                    }
+                    if let Err(e) = commands::singbox::restore_winhttp_proxy(&state) {
+                        log::warn!("Failed to restore WinHTTP proxy on exit: {}", e);
+                    }
+                    commands::network::restore_adapter_dns(&state);
+                }
+            }
+        });
 }
 
 fn get_data_dir() -> PathBuf {
@@ -118,42 +327,3 @@ fn get_data_dir() -> PathBuf {
         PathBuf::from(".")
     }
 }
-
-fn setup_tray(app: &tauri::App) -> Result<(), Box<dyn std::error::Error>> {
-    use tauri::tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent};
-    use tauri::menu::{Menu, MenuItem};
-
-    let show_item = MenuItem::with_id(app, "show", "显示", true, None::<&str>)?;
-    let quit_item = MenuItem::with_id(app, "quit", "退出", true, None::<&str>)?;
-    let menu = Menu::with_items(app, &[&show_item, &quit_item])?;
-
-    let _tray = TrayIconBuilder::new()
-        .icon(app.default_window_icon().unwrap().clone())
-        .menu(&menu)
-        .show_menu_on_left_click(false)
-        .on_tray_icon_event(|tray, event| {
-            if let TrayIconEvent::Click { button: MouseButton::Left, button_state: MouseButtonState::Up, .. } = event {
-                if let Some(window) = tray.app_handle().get_webview_window("main") {
-                    let _ = window.show();
-                    let _ = window.set_focus();
-                }
-            }
-        })
-        .on_menu_event(|app, event| {
-            match event.id.as_ref() {
-                "show" => {
-                    if let Some(window) = app.get_webview_window("main") {
-                        let _ = window.show();
-                        let _ = window.set_focus();
-                    }
-                }
-                "quit" => {
-                    app.exit(0);
-                }
-                _ => {}
-            }
-        })
-        .build(app)?;
-
-    Ok(())
-}
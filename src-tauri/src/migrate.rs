@@ -0,0 +1,125 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+
+/// 带版本号的数据文件
+#[derive(Debug, Clone, Copy)]
+pub enum DataFile {
+    Settings,
+    Profiles,
+    Rulesets,
+}
+
+/// 把数据从版本 i 升级到 i + 1
+type Migration = fn(Value) -> Value;
+
+impl DataFile {
+    /// 第 i 项把版本 i 升级到 i + 1，当前版本即列表长度；以后字段改名在这里追加一项
+    fn migrations(self) -> &'static [Migration] {
+        match self {
+            DataFile::Settings => &[stamp_version],
+            DataFile::Profiles => &[stamp_version],
+            DataFile::Rulesets => &[wrap_rulesets],
+        }
+    }
+
+    pub fn current_version(self) -> u64 {
+        self.migrations().len() as u64
+    }
+}
+
+/// v0 没有 version 字段，内容与 v1 相同
+fn stamp_version(value: Value) -> Value {
+    value
+}
+
+/// v0 的 rulesets.json 是裸数组，v1 起放到 rulesets 字段下以便携带版本号
+fn wrap_rulesets(value: Value) -> Value {
+    match value {
+        Value::Array(_) => serde_json::json!({ "rulesets": value }),
+        other => other,
+    }
+}
+
+fn file_version(value: &Value) -> u64 {
+    value.get("version").and_then(|v| v.as_u64()).unwrap_or(0)
+}
+
+/// 去掉版本号，取出真正的数据部分
+fn payload(kind: DataFile, mut value: Value) -> Value {
+    match kind {
+        DataFile::Rulesets => value.get_mut("rulesets").map(Value::take).unwrap_or(Value::Null),
+        DataFile::Settings | DataFile::Profiles => {
+            if let Some(obj) = value.as_object_mut() {
+                obj.remove("version");
+            }
+            value
+        }
+    }
+}
+
+/// 读取数据文件并按需升级到当前版本，升级后写回
+///
+/// 文件不存在返回 None；无法解密或解析时先把原文件另存为 .bak 再返回 None，
+/// 避免调用方回落到默认值后保存时把用户数据悄悄覆盖掉
+pub fn load<T: DeserializeOwned>(path: &Path, kind: DataFile) -> Option<T> {
+    if !path.exists() {
+        return None;
+    }
+    let current = kind.current_version();
+
+    let result = (|| -> Result<(T, Option<Value>), String> {
+        let content = crate::dpapi::read_data_file(path)?;
+        let mut value: Value = serde_json::from_str(&content).map_err(|e| e.to_string())?;
+        let version = file_version(&value);
+        let mut upgraded = None;
+        if version > current {
+            // 新版本写入的文件：尽量按当前结构读取，但不写回
+            log::warn!("{:?} file is version {}, newer than supported {}", kind, version, current);
+        } else if version < current {
+            for migrate in &kind.migrations()[version as usize..] {
+                value = migrate(value);
+            }
+            if let Some(obj) = value.as_object_mut() {
+                obj.insert("version".to_string(), Value::from(current));
+            }
+            upgraded = Some(value.clone());
+        }
+        let data = serde_json::from_value(payload(kind, value)).map_err(|e| e.to_string())?;
+        Ok((data, upgraded))
+    })();
+
+    match result {
+        Ok((data, upgraded)) => {
+            if let Some(value) = upgraded {
+                log::info!("Migrated {} to version {}", path.display(), current);
+                if let Ok(content) = serde_json::to_string_pretty(&value) {
+                    let _ = crate::dpapi::write_data_file(path, &content, crate::dpapi::is_encrypted_file(path));
+                }
+            }
+            Some(data)
+        }
+        Err(e) => {
+            let backup = path.with_extension("json.bak");
+            log::warn!("Failed to load {}: {}, keeping a copy at {}", path.display(), e, backup.display());
+            let _ = fs::copy(path, &backup);
+            None
+        }
+    }
+}
+
+/// 带上当前版本号写入数据文件，encrypt 为 true 时用 DPAPI 加密
+pub fn save<T: Serialize + ?Sized>(path: &Path, kind: DataFile, data: &T, encrypt: bool) -> Result<(), String> {
+    let value = serde_json::to_value(data).map_err(|e| e.to_string())?;
+    let mut doc = match kind {
+        DataFile::Rulesets => serde_json::json!({ "rulesets": value }),
+        DataFile::Settings | DataFile::Profiles => value,
+    };
+    if let Some(obj) = doc.as_object_mut() {
+        obj.insert("version".to_string(), Value::from(kind.current_version()));
+    }
+    let content = serde_json::to_string_pretty(&doc).map_err(|e| e.to_string())?;
+    crate::dpapi::write_data_file(path, &content, encrypt)
+}
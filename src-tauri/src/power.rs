@@ -0,0 +1,33 @@
+use std::sync::mpsc;
+
+/// 持有期间阻止系统自动睡眠（不影响关闭显示器），drop 后恢复。
+/// SetThreadExecutionState 只对调用线程生效，tokio 的工作线程不固定，所以放在专用线程里持有
+pub struct SleepInhibitor {
+    _release: mpsc::Sender<()>,
+}
+
+impl SleepInhibitor {
+    pub fn acquire() -> Self {
+        let (release, released) = mpsc::channel::<()>();
+        std::thread::spawn(move || {
+            set_system_required(true);
+            // 发送端被 drop 时 recv 返回错误，随即恢复并退出线程
+            let _ = released.recv();
+            set_system_required(false);
+        });
+        Self { _release: release }
+    }
+}
+
+#[cfg(windows)]
+fn set_system_required(required: bool) {
+    use windows::Win32::System::Power::{SetThreadExecutionState, ES_CONTINUOUS, ES_SYSTEM_REQUIRED};
+    let flags = if required { ES_CONTINUOUS | ES_SYSTEM_REQUIRED } else { ES_CONTINUOUS };
+    let previous = unsafe { SetThreadExecutionState(flags) };
+    if previous.0 == 0 {
+        log::warn!("SetThreadExecutionState failed");
+    }
+}
+
+#[cfg(not(windows))]
+fn set_system_required(_required: bool) {}
@@ -0,0 +1,71 @@
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+
+const REDACTED: &str = "***";
+
+/// 视为凭据的字段名，同时用于 JSON 字段和 URL 查询参数（不区分大小写）
+const SENSITIVE_KEYS: [&str; 16] = [
+    "password", "passwd", "pwd", "uuid", "token", "access_token", "secret", "key",
+    "auth", "auth_str", "private_key", "pre_shared_key", "psk", "obfs_password", "obfs-password", "sig",
+];
+
+static URL_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r#"[a-zA-Z][a-zA-Z0-9+.-]*://[^\s"'<>]+"#).unwrap());
+
+static JSON_FIELD_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(&format!(r#"(?i)"({})"(\s*:\s*)"(?:[^"\\]|\\.)*""#, SENSITIVE_KEYS.join("|"))).unwrap()
+});
+
+fn is_sensitive_key(key: &str) -> bool {
+    SENSITIVE_KEYS.iter().any(|k| k.eq_ignore_ascii_case(key))
+}
+
+/// 订阅地址常把令牌放在路径里（/link/AbC123...），长且同时含字母和数字的路径段按令牌处理
+fn looks_like_token(segment: &str) -> bool {
+    let digits = segment.chars().filter(|c| c.is_ascii_digit()).count();
+    let letters = segment.chars().filter(|c| c.is_ascii_alphabetic()).count();
+    segment.len() >= 16
+        && segment.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        && digits >= 4
+        && letters >= 4
+}
+
+fn redact_url(raw: &str) -> String {
+    let scheme = raw.split_once("://").map(|(s, _)| s).unwrap_or_default();
+    // 分享链接（vmess:// vless:// ss:// 等）整段都是凭据
+    if !matches!(scheme.to_ascii_lowercase().as_str(), "http" | "https" | "ws" | "wss") {
+        return format!("{}://{}", scheme, REDACTED);
+    }
+    let Ok(mut url) = url::Url::parse(raw) else { return raw.to_string() };
+
+    let mut changed = false;
+    if !url.username().is_empty() || url.password().is_some() {
+        let _ = url.set_username(REDACTED);
+        let _ = url.set_password(None);
+        changed = true;
+    }
+    if url.query_pairs().any(|(k, _)| is_sensitive_key(&k)) {
+        let pairs: Vec<(String, String)> = url.query_pairs()
+            .map(|(k, v)| {
+                let v = if is_sensitive_key(&k) { REDACTED.to_string() } else { v.into_owned() };
+                (k.into_owned(), v)
+            })
+            .collect();
+        url.query_pairs_mut().clear().extend_pairs(pairs);
+        changed = true;
+    }
+    let segments: Vec<String> = url.path_segments().map(|s| s.map(str::to_string).collect()).unwrap_or_default();
+    if segments.iter().any(|s| looks_like_token(s)) {
+        if let Ok(mut path) = url.path_segments_mut() {
+            path.clear().extend(segments.iter().map(|s| if looks_like_token(s) { REDACTED } else { s.as_str() }));
+        }
+        changed = true;
+    }
+
+    if changed { url.to_string() } else { raw.to_string() }
+}
+
+/// 去掉文本中的订阅令牌、节点凭据和分享链接，用于日志和发给前端的事件
+pub fn redact(text: &str) -> String {
+    let text = JSON_FIELD_RE.replace_all(text, |c: &Captures| format!("\"{}\"{}\"{}\"", &c[1], &c[2], REDACTED));
+    URL_RE.replace_all(&text, |c: &Captures| redact_url(&c[0])).into_owned()
+}
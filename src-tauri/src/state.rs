@@ -1,8 +1,10 @@
+use std::collections::{HashMap, VecDeque};
 use std::path::PathBuf;
-use std::sync::Arc;
-use tokio::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use tokio::sync::{Mutex, Notify};
 use tokio_util::sync::CancellationToken;
-use crate::types::{AppSettings, ProfilesData, RuleSet, ProxyState, TrafficStats};
+use crate::types::{AppSettings, NetworkProfile, ProfilesData, RuleSet, ProxyState, TrafficStats};
 
 pub struct AppState {
     pub data_dir: PathBuf,
@@ -15,42 +17,144 @@ pub struct AppState {
     pub singbox_process: Arc<Mutex<Option<tokio::process::Child>>>,
     pub start_time: Arc<Mutex<Option<u64>>>,
     pub traffic_cancel: Arc<Mutex<Option<CancellationToken>>>,
+    pub kernel_download_cancel: Arc<Mutex<Option<CancellationToken>>>,
+    pub update_download_cancel: Arc<Mutex<Option<CancellationToken>>>,
+    pub proxy_guard_cancel: Arc<Mutex<Option<CancellationToken>>>,
+    /// 进行中的批量延迟测试
+    pub latency_test_cancel: Arc<Mutex<Option<CancellationToken>>>,
+    /// 当前网络匹配到的网络配置
+    pub network_profile: Arc<Mutex<Option<NetworkProfile>>>,
+    /// 内核日志中 auto_detect_interface 最近选中的网卡
+    pub kernel_default_interface: Arc<Mutex<Option<String>>>,
+    /// 与设置中的 encryptData 同步，供同步的文件读写函数使用
+    pub encrypt_data: Arc<AtomicBool>,
+    /// 当前工作区名称，None 为默认工作区（即 data_dir 本身）
+    pub workspace: Arc<RwLock<Option<String>>>,
+    /// 进行中的下载最近一次的进度，键为进度事件名，供前端重新加载后恢复
+    pub download_progress: Arc<Mutex<HashMap<String, serde_json::Value>>>,
+    /// 开启 preventSleep 时连接期间持有，阻止系统睡眠
+    pub sleep_inhibitor: Arc<Mutex<Option<crate::power::SleepInhibitor>>>,
+    /// 通知托盘菜单重建（配置、节点切换后），由托盘的后台任务统一处理
+    pub tray_refresh: Arc<Notify>,
+    /// 各节点最近一次测得的延迟（毫秒，-1 为超时），用于任务栏角标
+    pub node_latency: Arc<Mutex<HashMap<String, i64>>>,
+    /// 各节点最近一次测试的最小 / 平均 / 最大延迟、抖动和丢包率
+    pub node_latency_stats: Arc<Mutex<HashMap<String, crate::types::LatencyStats>>>,
+    /// 各节点最近若干次测试的延迟（-1 为失败），用于计算稳定性评分
+    pub node_latency_history: Arc<Mutex<HashMap<String, VecDeque<i64>>>>,
+    /// 各节点最近一次测得的下载速度（Mbps）
+    pub node_speed: Arc<Mutex<HashMap<String, f64>>>,
+    /// 各节点最近一次的流媒体 / AI 服务解锁检测结果
+    pub node_unlock: Arc<Mutex<HashMap<String, crate::commands::unlock::NodeUnlockReport>>>,
+    /// 各节点最近一次的 UDP 转发和 NAT 类型测试结果
+    pub node_udp: Arc<Mutex<HashMap<String, crate::commands::udptest::UdpTestResult>>>,
 }
 
 impl AppState {
     pub fn new(data_dir: PathBuf) -> Self {
         let config_dir = data_dir.clone();
+        let workspace = crate::commands::workspace::load_active_workspace(&data_dir);
+        let workspace_dir = crate::commands::workspace::workspace_path(&data_dir, workspace.as_deref());
+        // Load persisted settings up front so startup tasks see the user's configuration
+        let settings: AppSettings = crate::migrate::load(&workspace_dir.join("settings.json"), crate::migrate::DataFile::Settings)
+            .unwrap_or_default();
+        let encrypt_data = settings.encrypt_data;
+        crate::i18n::set_locale(&settings.language);
         Self {
             data_dir,
             config_dir,
             profiles_data: Arc::new(Mutex::new(ProfilesData::default())),
-            settings: Arc::new(Mutex::new(AppSettings::default())),
+            settings: Arc::new(Mutex::new(settings)),
             rulesets: Arc::new(Mutex::new(Vec::new())),
             proxy_state: Arc::new(Mutex::new(ProxyState::Idle)),
             traffic_stats: Arc::new(Mutex::new(TrafficStats::default())),
             singbox_process: Arc::new(Mutex::new(None)),
             start_time: Arc::new(Mutex::new(None)),
             traffic_cancel: Arc::new(Mutex::new(None)),
+            kernel_download_cancel: Arc::new(Mutex::new(None)),
+            update_download_cancel: Arc::new(Mutex::new(None)),
+            proxy_guard_cancel: Arc::new(Mutex::new(None)),
+            latency_test_cancel: Arc::new(Mutex::new(None)),
+            network_profile: Arc::new(Mutex::new(None)),
+            kernel_default_interface: Arc::new(Mutex::new(None)),
+            encrypt_data: Arc::new(AtomicBool::new(encrypt_data)),
+            workspace: Arc::new(RwLock::new(workspace)),
+            download_progress: Arc::new(Mutex::new(HashMap::new())),
+            sleep_inhibitor: Arc::new(Mutex::new(None)),
+            tray_refresh: Arc::new(Notify::new()),
+            node_latency: Arc::new(Mutex::new(HashMap::new())),
+            node_latency_stats: Arc::new(Mutex::new(HashMap::new())),
+            node_latency_history: Arc::new(Mutex::new(HashMap::new())),
+            node_speed: Arc::new(Mutex::new(HashMap::new())),
+            node_unlock: Arc::new(Mutex::new(HashMap::new())),
+            node_udp: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 
+    pub fn encrypt_data(&self) -> bool {
+        self.encrypt_data.load(Ordering::Relaxed)
+    }
+
+    /// 当前工作区的数据目录，配置、规则集和设置都存放在这里
+    pub fn workspace_dir(&self) -> PathBuf {
+        let workspace = self.workspace.read().unwrap_or_else(|e| e.into_inner());
+        crate::commands::workspace::workspace_path(&self.data_dir, workspace.as_deref())
+    }
+
     pub fn profiles_file(&self) -> PathBuf {
-        self.data_dir.join("profiles.json")
+        self.workspace_dir().join("profiles.json")
     }
 
     pub fn settings_file(&self) -> PathBuf {
-        self.data_dir.join("settings.json")
+        self.workspace_dir().join("settings.json")
     }
 
     pub fn rulesets_file(&self) -> PathBuf {
-        self.data_dir.join("rulesets.json")
+        self.workspace_dir().join("rulesets.json")
+    }
+
+    pub fn custom_rules_file(&self) -> PathBuf {
+        self.workspace_dir().join("custom_rules.json")
+    }
+
+    /// 启用系统代理前保存的原始代理设置
+    pub fn proxy_backup_file(&self) -> PathBuf {
+        self.data_dir.join("proxy_backup.json")
+    }
+
+    /// 启用 WinHTTP 代理前保存的原始设置
+    pub fn winhttp_backup_file(&self) -> PathBuf {
+        self.data_dir.join("winhttp_backup.json")
+    }
+
+    /// 修改网卡 DNS 前保存的原始设置
+    pub fn adapter_dns_backup_file(&self) -> PathBuf {
+        self.data_dir.join("adapter_dns_backup.json")
+    }
+
+    pub fn session_file(&self) -> PathBuf {
+        self.workspace_dir().join("session.json")
+    }
+
+    /// 自动切换节点的记录
+    pub fn failover_history_file(&self) -> PathBuf {
+        self.workspace_dir().join("failover_history.json")
     }
 
     pub fn configs_dir(&self) -> PathBuf {
-        self.data_dir.join("configs")
+        self.workspace_dir().join("configs")
     }
 
     pub fn rulesets_cache_dir(&self) -> PathBuf {
-        self.data_dir.join("rulesets")
+        self.workspace_dir().join("rulesets")
+    }
+
+    pub fn ruleset_cache_file(&self, tag: &str, format: &str) -> PathBuf {
+        let ext = if format == "source" { "json" } else { "srs" };
+        self.rulesets_cache_dir().join(format!("{}.{}", tag, ext))
+    }
+
+    pub fn kernel_dir(&self) -> PathBuf {
+        self.data_dir.join("kernel")
     }
 }
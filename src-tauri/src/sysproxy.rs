@@ -0,0 +1,248 @@
+use serde::{Deserialize, Serialize};
+
+/// 系统代理设置快照，启用代理前保存，关闭时原样恢复（包括 PAC 地址）
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProxySnapshot {
+    /// PROXY_TYPE_* 标志位组合
+    pub flags: u32,
+    pub server: Option<String>,
+    pub bypass: Option<String>,
+    pub auto_config_url: Option<String>,
+}
+
+#[cfg(windows)]
+const PROXY_TYPE_DIRECT: u32 = 1;
+const PROXY_TYPE_PROXY: u32 = 2;
+
+impl ProxySnapshot {
+    pub fn proxy_enabled(&self) -> bool {
+        self.flags & PROXY_TYPE_PROXY != 0
+    }
+}
+
+/// 通过 WinINET 设置当前用户的系统代理，并广播设置变更让浏览器等立即生效
+///
+/// `server` 为 None 时关闭代理（保留直连），否则设置为 `host:port`；`bypass` 为 None 时不修改例外列表
+pub fn set_system_proxy(server: Option<&str>, bypass: Option<&str>) -> Result<(), String> {
+    #[cfg(windows)]
+    {
+        let flags = if server.is_some() { PROXY_TYPE_DIRECT | PROXY_TYPE_PROXY } else { PROXY_TYPE_DIRECT };
+        win::set_options(flags, server, bypass.filter(|_| server.is_some()), None)
+    }
+    #[cfg(not(windows))]
+    {
+        let _ = (server, bypass);
+        Ok(())
+    }
+}
+
+/// 读取当前的系统代理设置
+pub fn query_system_proxy() -> Result<ProxySnapshot, String> {
+    #[cfg(windows)]
+    return win::query_options();
+    #[cfg(not(windows))]
+    return Ok(ProxySnapshot::default());
+}
+
+/// 按快照恢复系统代理设置
+pub fn restore_system_proxy(snapshot: &ProxySnapshot) -> Result<(), String> {
+    #[cfg(windows)]
+    {
+        // 快照里没有的选项清空，避免残留本程序写入的地址
+        win::set_options(
+            snapshot.flags | PROXY_TYPE_DIRECT,
+            Some(snapshot.server.as_deref().unwrap_or_default()),
+            Some(snapshot.bypass.as_deref().unwrap_or_default()),
+            Some(snapshot.auto_config_url.as_deref().unwrap_or_default()),
+        )
+    }
+    #[cfg(not(windows))]
+    {
+        let _ = snapshot;
+        Ok(())
+    }
+}
+
+/// WinHTTP（机器级）默认代理，服务和不读取用户代理设置的程序会使用；proxy 为 None 表示直连
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct WinHttpProxy {
+    pub proxy: Option<String>,
+    pub bypass: Option<String>,
+}
+
+/// 读取 WinHTTP 默认代理（netsh winhttp show proxy）
+pub fn query_winhttp_proxy() -> Result<WinHttpProxy, String> {
+    #[cfg(windows)]
+    return win::query_winhttp();
+    #[cfg(not(windows))]
+    return Ok(WinHttpProxy::default());
+}
+
+/// 设置 WinHTTP 默认代理（netsh winhttp set proxy），需要管理员权限
+pub fn set_winhttp_proxy(config: &WinHttpProxy) -> Result<(), String> {
+    #[cfg(windows)]
+    return win::set_winhttp(config);
+    #[cfg(not(windows))]
+    {
+        let _ = config;
+        Ok(())
+    }
+}
+
+#[cfg(windows)]
+mod win {
+    use super::{ProxySnapshot, WinHttpProxy};
+    use windows::core::PWSTR;
+    use windows::Win32::Foundation::{GlobalFree, HGLOBAL, ERROR_ACCESS_DENIED};
+    use windows::Win32::Networking::WinHttp::{
+        WinHttpGetDefaultProxyConfiguration, WinHttpSetDefaultProxyConfiguration, WINHTTP_ACCESS_TYPE_NAMED_PROXY,
+        WINHTTP_ACCESS_TYPE_NO_PROXY, WINHTTP_PROXY_INFO,
+    };
+    use windows::Win32::Networking::WinInet::{
+        InternetQueryOptionW, InternetSetOptionW, INTERNET_OPTION_PER_CONNECTION_OPTION, INTERNET_OPTION_REFRESH,
+        INTERNET_OPTION_SETTINGS_CHANGED, INTERNET_PER_CONN, INTERNET_PER_CONN_AUTOCONFIG_URL, INTERNET_PER_CONN_FLAGS,
+        INTERNET_PER_CONN_OPTIONW, INTERNET_PER_CONN_OPTIONW_0, INTERNET_PER_CONN_OPTION_LISTW,
+        INTERNET_PER_CONN_PROXY_BYPASS, INTERNET_PER_CONN_PROXY_SERVER,
+    };
+
+    fn to_wide(s: &str) -> Vec<u16> {
+        s.encode_utf16().chain(std::iter::once(0)).collect()
+    }
+
+    fn string_option(option: INTERNET_PER_CONN, buf: &mut [u16]) -> INTERNET_PER_CONN_OPTIONW {
+        INTERNET_PER_CONN_OPTIONW {
+            dwOption: option,
+            Value: INTERNET_PER_CONN_OPTIONW_0 { pszValue: PWSTR(buf.as_mut_ptr()) },
+        }
+    }
+
+    fn option_list(options: &mut [INTERNET_PER_CONN_OPTIONW]) -> INTERNET_PER_CONN_OPTION_LISTW {
+        // pszConnection 为空表示局域网设置（默认连接）
+        INTERNET_PER_CONN_OPTION_LISTW {
+            dwSize: std::mem::size_of::<INTERNET_PER_CONN_OPTION_LISTW>() as u32,
+            pszConnection: PWSTR::null(),
+            dwOptionCount: options.len() as u32,
+            dwOptionError: 0,
+            pOptions: options.as_mut_ptr(),
+        }
+    }
+
+    /// 写入代理选项，None 的选项保持不变
+    pub fn set_options(flags: u32, server: Option<&str>, bypass: Option<&str>, auto_config_url: Option<&str>) -> Result<(), String> {
+        let mut server_buf = to_wide(server.unwrap_or_default());
+        let mut bypass_buf = to_wide(bypass.unwrap_or_default());
+        let mut pac_buf = to_wide(auto_config_url.unwrap_or_default());
+
+        let mut options = vec![INTERNET_PER_CONN_OPTIONW {
+            dwOption: INTERNET_PER_CONN_FLAGS,
+            Value: INTERNET_PER_CONN_OPTIONW_0 { dwValue: flags },
+        }];
+        if server.is_some() {
+            options.push(string_option(INTERNET_PER_CONN_PROXY_SERVER, &mut server_buf));
+        }
+        if bypass.is_some() {
+            options.push(string_option(INTERNET_PER_CONN_PROXY_BYPASS, &mut bypass_buf));
+        }
+        if auto_config_url.is_some() {
+            options.push(string_option(INTERNET_PER_CONN_AUTOCONFIG_URL, &mut pac_buf));
+        }
+        let list = option_list(&mut options);
+
+        unsafe {
+            InternetSetOptionW(
+                None,
+                INTERNET_OPTION_PER_CONNECTION_OPTION,
+                Some(&list as *const _ as *const core::ffi::c_void),
+                list.dwSize,
+            )
+            .map_err(|e| format!("Failed to set system proxy: {}", e))?;
+            // 通知已运行的程序重新读取代理设置
+            InternetSetOptionW(None, INTERNET_OPTION_SETTINGS_CHANGED, None, 0)
+                .map_err(|e| format!("Failed to broadcast proxy change: {}", e))?;
+            InternetSetOptionW(None, INTERNET_OPTION_REFRESH, None, 0)
+                .map_err(|e| format!("Failed to refresh proxy settings: {}", e))?;
+        }
+
+        Ok(())
+    }
+
+    /// 读取 WinINET 返回的字符串并释放其内存
+    unsafe fn take_string(value: PWSTR) -> Option<String> {
+        if value.is_null() {
+            return None;
+        }
+        let result = value.to_string().ok().filter(|s| !s.is_empty());
+        // GlobalFree 成功时返回 NULL，windows-rs 会把它包装成 Err，这里忽略返回值
+        let _ = GlobalFree(Some(HGLOBAL(value.0 as *mut core::ffi::c_void)));
+        result
+    }
+
+    pub fn query_options() -> Result<ProxySnapshot, String> {
+        let mut options = [
+            INTERNET_PER_CONN_OPTIONW { dwOption: INTERNET_PER_CONN_FLAGS, ..Default::default() },
+            INTERNET_PER_CONN_OPTIONW { dwOption: INTERNET_PER_CONN_PROXY_SERVER, ..Default::default() },
+            INTERNET_PER_CONN_OPTIONW { dwOption: INTERNET_PER_CONN_PROXY_BYPASS, ..Default::default() },
+            INTERNET_PER_CONN_OPTIONW { dwOption: INTERNET_PER_CONN_AUTOCONFIG_URL, ..Default::default() },
+        ];
+        let mut list = option_list(&mut options);
+        let mut size = list.dwSize;
+
+        unsafe {
+            InternetQueryOptionW(
+                None,
+                INTERNET_OPTION_PER_CONNECTION_OPTION,
+                Some(&mut list as *mut _ as *mut core::ffi::c_void),
+                &mut size,
+            )
+            .map_err(|e| format!("Failed to query system proxy: {}", e))?;
+
+            Ok(ProxySnapshot {
+                flags: options[0].Value.dwValue,
+                server: take_string(options[1].Value.pszValue),
+                bypass: take_string(options[2].Value.pszValue),
+                auto_config_url: take_string(options[3].Value.pszValue),
+            })
+        }
+    }
+
+    pub fn query_winhttp() -> Result<WinHttpProxy, String> {
+        let mut info = WINHTTP_PROXY_INFO::default();
+        unsafe {
+            WinHttpGetDefaultProxyConfiguration(&mut info)
+                .map_err(|e| format!("Failed to query WinHTTP proxy: {}", e))?;
+            let proxy = take_string(info.lpszProxy);
+            let bypass = take_string(info.lpszProxyBypass);
+            Ok(WinHttpProxy {
+                proxy: proxy.filter(|_| info.dwAccessType == WINHTTP_ACCESS_TYPE_NAMED_PROXY),
+                bypass,
+            })
+        }
+    }
+
+    pub fn set_winhttp(config: &WinHttpProxy) -> Result<(), String> {
+        let mut proxy_buf = to_wide(config.proxy.as_deref().unwrap_or_default());
+        let mut bypass_buf = to_wide(config.bypass.as_deref().unwrap_or_default());
+        let mut info = match config.proxy {
+            Some(_) => WINHTTP_PROXY_INFO {
+                dwAccessType: WINHTTP_ACCESS_TYPE_NAMED_PROXY,
+                lpszProxy: PWSTR(proxy_buf.as_mut_ptr()),
+                lpszProxyBypass: if config.bypass.is_some() { PWSTR(bypass_buf.as_mut_ptr()) } else { PWSTR::null() },
+            },
+            None => WINHTTP_PROXY_INFO {
+                dwAccessType: WINHTTP_ACCESS_TYPE_NO_PROXY,
+                lpszProxy: PWSTR::null(),
+                lpszProxyBypass: PWSTR::null(),
+            },
+        };
+        unsafe {
+            WinHttpSetDefaultProxyConfiguration(&mut info).map_err(|e| {
+                if e.code() == ERROR_ACCESS_DENIED.to_hresult() {
+                    "Setting the WinHTTP proxy requires administrator privileges".to_string()
+                } else {
+                    format!("Failed to set WinHTTP proxy: {}", e)
+                }
+            })
+        }
+    }
+}
@@ -0,0 +1,114 @@
+use tauri::{AppHandle, Manager};
+use windows::core::HSTRING;
+use windows::Win32::Foundation::HWND;
+use windows::Win32::Graphics::Gdi::{CreateBitmap, DeleteObject};
+use windows::Win32::System::Com::{CoCreateInstance, CLSCTX_INPROC_SERVER};
+use windows::Win32::UI::Shell::{ITaskbarList3, TaskbarList};
+use windows::Win32::UI::WindowsAndMessaging::{CreateIconIndirect, DestroyIcon, HICON, ICONINFO};
+use crate::i18n;
+use crate::types::ProxyState;
+
+/// 任务栏按钮右下角的状态圆点
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Badge {
+    Connecting,
+    /// 已连接，按当前节点最近一次测得的延迟分档
+    Fast,
+    Moderate,
+    Slow,
+    Error,
+}
+
+impl Badge {
+    fn from_state(proxy_state: &ProxyState, latency: Option<i64>) -> Option<Self> {
+        match proxy_state {
+            ProxyState::Idle | ProxyState::Disconnecting => None,
+            ProxyState::Connecting | ProxyState::Waiting => Some(Badge::Connecting),
+            ProxyState::Error => Some(Badge::Error),
+            ProxyState::Connected => Some(match latency {
+                Some(ms) if ms < 0 => Badge::Error,
+                Some(ms) if ms >= 500 => Badge::Slow,
+                Some(ms) if ms >= 200 => Badge::Moderate,
+                _ => Badge::Fast,
+            }),
+        }
+    }
+
+    /// RGB
+    fn color(self) -> [u8; 3] {
+        match self {
+            Badge::Connecting => [0x3b, 0x82, 0xf6],
+            Badge::Fast => [0x22, 0xc5, 0x5e],
+            Badge::Moderate => [0xea, 0xb3, 0x08],
+            Badge::Slow => [0xf9, 0x73, 0x16],
+            Badge::Error => [0xef, 0x44, 0x44],
+        }
+    }
+
+    /// 屏幕阅读器读出的说明
+    fn description(self) -> &'static str {
+        match self {
+            Badge::Connecting => i18n::tr("tray.connecting"),
+            Badge::Error => i18n::tr("taskbar.error"),
+            _ => i18n::tr("taskbar.connected"),
+        }
+    }
+}
+
+/// 16x16 带白边的圆点，按 BGRA 预乘排列
+fn badge_pixels(color: [u8; 3]) -> Vec<u8> {
+    const SIZE: usize = 16;
+    let center = (SIZE as f32 - 1.0) / 2.0;
+    let mut pixels = Vec::with_capacity(SIZE * SIZE * 4);
+    for y in 0..SIZE {
+        for x in 0..SIZE {
+            let distance = ((x as f32 - center).powi(2) + (y as f32 - center).powi(2)).sqrt();
+            // 边缘一像素做抗锯齿
+            let coverage = (center + 0.5 - distance).clamp(0.0, 1.0);
+            let [r, g, b] = if distance > center - 1.5 { [0xff, 0xff, 0xff] } else { color };
+            let alpha = (coverage * 255.0) as u8;
+            let premultiply = |c: u8| (c as u16 * alpha as u16 / 255) as u8;
+            pixels.extend_from_slice(&[premultiply(b), premultiply(g), premultiply(r), alpha]);
+        }
+    }
+    pixels
+}
+
+fn apply_overlay(app: &AppHandle, badge: Option<Badge>) -> windows::core::Result<()> {
+    let Some(window) = app.get_webview_window("main") else { return Ok(()) };
+    let Ok(hwnd) = window.hwnd() else { return Ok(()) };
+    let hwnd = HWND(hwnd.0);
+
+    unsafe {
+        let taskbar: ITaskbarList3 = CoCreateInstance(&TaskbarList, None, CLSCTX_INPROC_SERVER)?;
+        taskbar.HrInit()?;
+        let Some(badge) = badge else {
+            return taskbar.SetOverlayIcon(hwnd, HICON::default(), None);
+        };
+
+        let pixels = badge_pixels(badge.color());
+        let color = CreateBitmap(16, 16, 1, 32, Some(pixels.as_ptr() as *const _));
+        let mask_bits = [0u8; 32];
+        let mask = CreateBitmap(16, 16, 1, 1, Some(mask_bits.as_ptr() as *const _));
+        let info = ICONINFO { fIcon: true.into(), hbmColor: color, hbmMask: mask, ..Default::default() };
+        let icon = CreateIconIndirect(&info);
+        let _ = DeleteObject(color.into());
+        let _ = DeleteObject(mask.into());
+        let icon = icon?;
+        // 任务栏会复制一份图标，设置后即可释放
+        let result = taskbar.SetOverlayIcon(hwnd, icon, &HSTRING::from(badge.description()));
+        let _ = DestroyIcon(icon);
+        result
+    }
+}
+
+/// 按连接状态和当前节点延迟更新任务栏角标。任务栏接口需在窗口所在的 UI 线程调用
+pub(crate) fn update_overlay(app: &AppHandle, proxy_state: &ProxyState, latency: Option<i64>) {
+    let badge = Badge::from_state(proxy_state, latency);
+    let handle = app.clone();
+    let _ = app.run_on_main_thread(move || {
+        if let Err(e) = apply_overlay(&handle, badge) {
+            log::warn!("Failed to set taskbar overlay: {}", e);
+        }
+    });
+}
@@ -0,0 +1,357 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use tauri::image::Image;
+use tauri::menu::{CheckMenuItem, Menu, MenuItem, PredefinedMenuItem, Submenu};
+use tauri::{AppHandle, Emitter, Listener, Manager, Wry};
+use crate::i18n;
+use crate::state::AppState;
+use crate::types::ProxyState;
+
+const TRAY_ID: &str = "main";
+/// 节点菜单项的 id 前缀，后接节点 tag
+const NODE_ITEM_PREFIX: &str = "node:";
+/// 配置菜单项的 id 前缀，后接配置 id
+const PROFILE_ITEM_PREFIX: &str = "profile:";
+/// 路由模式菜单项的 id 前缀，后接 routing_mode 的值
+const MODE_ITEM_PREFIX: &str = "mode:";
+
+/// 托盘发起的测速进行中，期间测速菜单项不可点击
+static LATENCY_TESTING: AtomicBool = AtomicBool::new(false);
+
+/// 按 空闲 / 连接中 / 已连接 / 出错 排列的托盘图标
+const COLOR_ICONS: [&[u8]; 4] = [
+    include_bytes!("../icons/tray/idle.png"),
+    include_bytes!("../icons/tray/connecting.png"),
+    include_bytes!("../icons/tray/connected.png"),
+    include_bytes!("../icons/tray/error.png"),
+];
+/// 浅色任务栏用的单色图标，顺序同上
+const MONO_ICONS: [&[u8]; 4] = [
+    include_bytes!("../icons/tray/mono-idle.png"),
+    include_bytes!("../icons/tray/mono-connecting.png"),
+    include_bytes!("../icons/tray/mono-connected.png"),
+    include_bytes!("../icons/tray/mono-error.png"),
+];
+
+fn tray_icon(proxy_state: &ProxyState, style: &str) -> tauri::Result<Image<'static>> {
+    let icons = if style == "mono" { &MONO_ICONS } else { &COLOR_ICONS };
+    let index = match proxy_state {
+        ProxyState::Idle | ProxyState::Disconnecting => 0,
+        ProxyState::Connecting | ProxyState::Waiting => 1,
+        ProxyState::Connected => 2,
+        ProxyState::Error => 3,
+    };
+    Image::from_bytes(icons[index])
+}
+
+/// 托盘显示所需的状态
+struct TrayStatus {
+    proxy_state: ProxyState,
+    icon_style: String,
+    routing_mode: String,
+    /// 当前节点最近一次测得的延迟，-1 为超时
+    latency: Option<i64>,
+}
+
+/// 锁只在短暂更新时被持有，拿不到时按默认值处理，下一次状态事件会再刷新
+fn tray_status<M: Manager<Wry>>(manager: &M) -> TrayStatus {
+    let defaults = crate::types::AppSettings::default();
+    let mut status = TrayStatus {
+        proxy_state: ProxyState::Idle,
+        icon_style: defaults.tray_icon_style,
+        routing_mode: defaults.routing_mode,
+        latency: None,
+    };
+    let Some(state) = manager.try_state::<AppState>() else { return status };
+    if let Ok(proxy_state) = state.proxy_state.try_lock() {
+        status.proxy_state = proxy_state.clone();
+    }
+    if let Ok(settings) = state.settings.try_lock() {
+        status.icon_style = settings.tray_icon_style.clone();
+        status.routing_mode = settings.routing_mode.clone();
+    }
+    if let (Ok(data), Ok(latency)) = (state.profiles_data.try_lock(), state.node_latency.try_lock()) {
+        status.latency = data.active_node_tag.as_ref().and_then(|tag| latency.get(tag).copied());
+    }
+    status
+}
+
+/// 已启用配置的子菜单，选中项为 active_profile_id
+fn build_profiles_submenu<M: Manager<Wry>>(manager: &M) -> tauri::Result<Submenu<Wry>> {
+    let submenu = Submenu::with_id(manager, "profiles", i18n::tr("tray.profiles"), true)?;
+    let Some(state) = manager.try_state::<AppState>() else { return Ok(submenu) };
+
+    let data = crate::commands::profiles::load_profiles_data(&state);
+    let profiles: Vec<_> = data.profiles.iter().filter(|p| p.enabled).collect();
+    if profiles.is_empty() {
+        submenu.append(&MenuItem::with_id(manager, "profiles:empty", i18n::tr("tray.noProfiles"), false, None::<&str>)?)?;
+        return Ok(submenu);
+    }
+    for profile in profiles {
+        let id = format!("{}{}", PROFILE_ITEM_PREFIX, profile.id);
+        let active = data.active_profile_id.as_ref() == Some(&profile.id);
+        submenu.append(&CheckMenuItem::with_id(manager, id, &profile.name, true, active, None::<&str>)?)?;
+    }
+    Ok(submenu)
+}
+
+/// 节点菜单文字，测过速的附上延迟，如 "HK-01  45ms"
+fn node_label(tag: &str, latency: Option<i64>) -> String {
+    match latency {
+        Some(ms) if ms >= 0 => format!("{}  {}ms", tag, ms),
+        Some(_) => format!("{}  {}", tag, i18n::tr("tray.timeout")),
+        None => tag.to_string(),
+    }
+}
+
+/// 当前配置的节点子菜单，选中项为 active_node_tag
+fn build_nodes_submenu<M: Manager<Wry>>(manager: &M) -> tauri::Result<Submenu<Wry>> {
+    let submenu = Submenu::with_id(manager, "nodes", i18n::tr("tray.nodes"), true)?;
+    let Some(state) = manager.try_state::<AppState>() else { return Ok(submenu) };
+
+    let data = crate::commands::profiles::load_profiles_data(&state);
+    let nodes = data.active_profile_id.as_deref()
+        .map(|id| crate::commands::profiles::load_profile_nodes(&state, id))
+        .unwrap_or_default();
+    let tags: Vec<String> = nodes.into_iter().filter_map(|n| n.tag).collect();
+    if tags.is_empty() {
+        submenu.append(&MenuItem::with_id(manager, "nodes:empty", i18n::tr("tray.noNodes"), false, None::<&str>)?)?;
+        return Ok(submenu);
+    }
+
+    let latency = state.node_latency.try_lock().map(|l| l.clone()).unwrap_or_default();
+    let active = data.active_node_tag.as_deref().unwrap_or(&tags[0]);
+    for tag in &tags {
+        let id = format!("{}{}", NODE_ITEM_PREFIX, tag);
+        let label = node_label(tag, latency.get(tag).copied());
+        submenu.append(&CheckMenuItem::with_id(manager, id, label, true, tag == active, None::<&str>)?)?;
+    }
+    Ok(submenu)
+}
+
+/// 按当前连接状态显示 连接 / 断开，切换过程中置灰
+fn build_connect_item<M: Manager<Wry>>(manager: &M, proxy_state: &ProxyState) -> tauri::Result<MenuItem<Wry>> {
+    let (label, enabled) = match proxy_state {
+        ProxyState::Connected => ("tray.disconnect", true),
+        ProxyState::Connecting | ProxyState::Waiting => ("tray.connecting", false),
+        ProxyState::Disconnecting => ("tray.disconnecting", false),
+        ProxyState::Idle | ProxyState::Error => ("tray.connect", true),
+    };
+    MenuItem::with_id(manager, "toggle_connect", i18n::tr(label), enabled, None::<&str>)
+}
+
+/// 路由模式子菜单，勾选当前的 routing_mode
+fn build_mode_submenu<M: Manager<Wry>>(manager: &M, routing_mode: &str) -> tauri::Result<Submenu<Wry>> {
+    let submenu = Submenu::with_id(manager, "modes", i18n::tr("tray.mode"), true)?;
+    for (mode, label) in [
+        ("rule", "tray.mode.rule"),
+        ("global-proxy", "tray.mode.global-proxy"),
+        ("global-direct", "tray.mode.global-direct"),
+    ] {
+        let id = format!("{}{}", MODE_ITEM_PREFIX, mode);
+        submenu.append(&CheckMenuItem::with_id(manager, id, i18n::tr(label), true, mode == routing_mode, None::<&str>)?)?;
+    }
+    Ok(submenu)
+}
+
+fn build_tray_menu<M: Manager<Wry>>(manager: &M, status: &TrayStatus) -> tauri::Result<Menu<Wry>> {
+    let show_item = MenuItem::with_id(manager, "show", i18n::tr("tray.show"), true, None::<&str>)?;
+    let connect_item = build_connect_item(manager, &status.proxy_state)?;
+    let mode_menu = build_mode_submenu(manager, &status.routing_mode)?;
+    let profiles_menu = build_profiles_submenu(manager)?;
+    let nodes_menu = build_nodes_submenu(manager)?;
+    let testing = LATENCY_TESTING.load(Ordering::SeqCst);
+    let test_label = if testing { "tray.testingLatency" } else { "tray.testLatency" };
+    let test_item = MenuItem::with_id(manager, "test_latency", i18n::tr(test_label), !testing, None::<&str>)?;
+    let best_item = MenuItem::with_id(manager, "select_best", i18n::tr("tray.selectBest"), !testing, None::<&str>)?;
+    let quit_item = MenuItem::with_id(manager, "quit", i18n::tr("tray.quit"), true, None::<&str>)?;
+    Menu::with_items(manager, &[
+        &show_item,
+        &PredefinedMenuItem::separator(manager)?,
+        &connect_item,
+        &mode_menu,
+        &profiles_menu,
+        &nodes_menu,
+        &test_item,
+        &best_item,
+        &PredefinedMenuItem::separator(manager)?,
+        &quit_item,
+    ])
+}
+
+/// Rebuild the tray menu and icon, e.g. after the language setting or connection state changes
+pub(crate) fn refresh_tray_menu(app: &AppHandle) {
+    let Some(tray) = app.tray_by_id(TRAY_ID) else { return };
+    let status = tray_status(app);
+    match build_tray_menu(app, &status) {
+        Ok(menu) => {
+            let _ = tray.set_menu(Some(menu));
+        }
+        Err(e) => log::warn!("Failed to rebuild tray menu: {}", e),
+    }
+    match tray_icon(&status.proxy_state, &status.icon_style) {
+        Ok(icon) => {
+            let _ = tray.set_icon(Some(icon));
+        }
+        Err(e) => log::warn!("Failed to update tray icon: {}", e),
+    }
+    #[cfg(windows)]
+    crate::taskbar::update_overlay(app, &status.proxy_state, status.latency);
+}
+
+/// 在托盘提示中附加订阅流量 / 到期提醒，None 时只显示程序名
+pub(crate) fn set_tray_hint(app: &AppHandle, hint: Option<&str>) {
+    let Some(tray) = app.tray_by_id(TRAY_ID) else { return };
+    let tooltip = match hint {
+        Some(hint) => format!("KunBox\n{}", hint),
+        None => "KunBox".to_string(),
+    };
+    let _ = tray.set_tooltip(Some(tooltip));
+}
+
+/// 在后台测试当前配置的全部节点，结果写入 node_latency 后节点菜单随之刷新
+async fn test_latency(app: &AppHandle) {
+    if LATENCY_TESTING.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    refresh_tray_menu(app);
+    let state = app.state::<AppState>();
+    if let Err(e) = crate::commands::node_test_all(app.clone(), state, None, None).await {
+        log::warn!("Failed to test latency from tray: {}", e);
+    }
+    LATENCY_TESTING.store(false, Ordering::SeqCst);
+    refresh_tray_menu(app);
+}
+
+/// 从托盘切换节点：连接中走 singbox_switch_node 立即生效，并保存为当前节点
+pub(crate) async fn select_node(app: &AppHandle, tag: String) -> Result<(), String> {
+    crate::commands::selection::activate_node(app, &tag).await?;
+    let _ = app.emit("tray:node-selected", &tag);
+    Ok(())
+}
+
+/// 从托盘切换配置：连接中重新生成配置并重启内核
+pub(crate) async fn select_profile(app: &AppHandle, id: String) -> Result<(), String> {
+    let state = app.state::<AppState>();
+    // 重新点击当前配置时只恢复勾选，profile_set_active 会把节点重置为第一个
+    if crate::commands::profiles::load_profiles_data(&state).active_profile_id.as_ref() == Some(&id) {
+        refresh_tray_menu(app);
+        return Ok(());
+    }
+    crate::commands::profile_set_active(state.clone(), id.clone()).await?;
+    let _ = app.emit("tray:profile-selected", &id);
+    if matches!(*state.proxy_state.lock().await, ProxyState::Connected) {
+        let result = crate::commands::singbox_restart(app.clone(), state).await?;
+        if !result.success {
+            return Err(result.error.unwrap_or_default());
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn setup_tray(app: &tauri::App) -> Result<(), Box<dyn std::error::Error>> {
+    use tauri::tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent};
+
+    let status = tray_status(app);
+    let menu = build_tray_menu(app, &status)?;
+
+    let _tray = TrayIconBuilder::with_id(TRAY_ID)
+        .icon(tray_icon(&status.proxy_state, &status.icon_style)?)
+        .menu(&menu)
+        .tooltip("KunBox")
+        .show_menu_on_left_click(false)
+        .on_tray_icon_event(|tray, event| {
+            if let TrayIconEvent::Click { button: MouseButton::Left, button_state: MouseButtonState::Up, .. } = event {
+                if let Some(window) = tray.app_handle().get_webview_window("main") {
+                    let _ = window.show();
+                    let _ = window.set_focus();
+                }
+            }
+        })
+        .on_menu_event(|app, event| {
+            match event.id.as_ref() {
+                "show" => {
+                    if let Some(window) = app.get_webview_window("main") {
+                        let _ = window.show();
+                        let _ = window.set_focus();
+                    }
+                }
+                "quit" => {
+                    let app = app.clone();
+                    tauri::async_runtime::spawn(async move {
+                        crate::commands::window::request_quit(&app, false).await;
+                    });
+                }
+                "test_latency" => {
+                    let app = app.clone();
+                    tauri::async_runtime::spawn(async move { test_latency(&app).await });
+                }
+                "select_best" => {
+                    let app = app.clone();
+                    tauri::async_runtime::spawn(async move {
+                        if let Err(e) = crate::commands::selection::select_best_node(&app, &Default::default()).await {
+                            log::warn!("Failed to select fastest node from tray: {}", e);
+                        }
+                    });
+                }
+                "toggle_connect" => {
+                    let app = app.clone();
+                    tauri::async_runtime::spawn(async move {
+                        if let Err(e) = crate::commands::hotkeys::toggle_connect(&app).await {
+                            log::warn!("Failed to toggle connection from tray: {}", e);
+                        }
+                    });
+                }
+                id if id.starts_with(MODE_ITEM_PREFIX) => {
+                    let mode = id[MODE_ITEM_PREFIX.len()..].to_string();
+                    let app = app.clone();
+                    tauri::async_runtime::spawn(async move {
+                        let state = app.state::<AppState>();
+                        match crate::commands::singbox_set_mode(app.clone(), state, mode).await {
+                            Ok(result) if !result.success => log::warn!("Failed to switch mode from tray: {}", result.error.unwrap_or_default()),
+                            Err(e) => log::warn!("Failed to switch mode from tray: {}", e),
+                            Ok(_) => {}
+                        }
+                        // 重新选中当前项时系统会取消勾选，统一重建一次
+                        refresh_tray_menu(&app);
+                    });
+                }
+                id if id.starts_with(PROFILE_ITEM_PREFIX) => {
+                    let profile_id = id[PROFILE_ITEM_PREFIX.len()..].to_string();
+                    let app = app.clone();
+                    tauri::async_runtime::spawn(async move {
+                        if let Err(e) = select_profile(&app, profile_id).await {
+                            log::warn!("Failed to switch profile from tray: {}", e);
+                            refresh_tray_menu(&app);
+                        }
+                    });
+                }
+                id if id.starts_with(NODE_ITEM_PREFIX) => {
+                    let tag = id[NODE_ITEM_PREFIX.len()..].to_string();
+                    let app = app.clone();
+                    tauri::async_runtime::spawn(async move {
+                        if let Err(e) = select_node(&app, tag).await {
+                            log::warn!("Failed to switch node from tray: {}", e);
+                            // 切换失败时勾选状态已被系统改掉，重建一次恢复
+                            refresh_tray_menu(&app);
+                        }
+                    });
+                }
+                _ => {}
+            }
+        })
+        .build(app)?;
+
+    // 配置、节点或连接状态变化后重建菜单，多次通知合并为一次
+    let handle = app.handle().clone();
+    let refresh = app.state::<AppState>().tray_refresh.clone();
+    let on_state = refresh.clone();
+    app.listen_any("singbox:state", move |_| on_state.notify_one());
+    tauri::async_runtime::spawn(async move {
+        loop {
+            refresh.notified().await;
+            refresh_tray_menu(&handle);
+        }
+    });
+
+    Ok(())
+}
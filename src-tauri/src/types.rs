@@ -6,6 +6,8 @@ use std::collections::HashMap;
 pub enum ProxyState {
     #[default]
     Idle,
+    /// 等待网络可用（自动连接、睡眠唤醒后）
+    Waiting,
     Connecting,
     Connected,
     Disconnecting,
@@ -41,6 +43,65 @@ pub struct Profile {
     pub dns_pre_resolve: bool,
     #[serde(rename = "dnsServer")]
     pub dns_server: Option<String>,
+    /// 订阅响应头 subscription-userinfo 中的流量和到期时间，本地导入的配置没有
+    #[serde(default)]
+    pub subscription: Option<SubscriptionInfo>,
+    #[serde(rename = "quotaAlert", default)]
+    pub quota_alert: QuotaAlert,
+}
+
+/// 机场通过 subscription-userinfo 下发的用量，单位字节；expire 为到期时间（秒级时间戳）
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SubscriptionInfo {
+    pub upload: u64,
+    pub download: u64,
+    pub total: u64,
+    pub expire: Option<u64>,
+}
+
+impl SubscriptionInfo {
+    /// 剩余流量，total 为 0 表示不限量
+    pub fn remaining(&self) -> Option<u64> {
+        (self.total > 0).then(|| self.total.saturating_sub(self.upload.saturating_add(self.download)))
+    }
+}
+
+/// 订阅流量不足或即将到期时的提醒阈值
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QuotaAlert {
+    pub enabled: bool,
+    /// 剩余流量低于该值时提醒（MB）
+    #[serde(rename = "remainingMb")]
+    pub remaining_mb: u64,
+    /// 距到期不足该天数时提醒
+    #[serde(rename = "expireDays")]
+    pub expire_days: u32,
+}
+
+impl QuotaAlert {
+    /// 阈值上限：1 TB 剩余流量、一年
+    pub const MAX_REMAINING_MB: u64 = 1024 * 1024;
+    pub const MAX_EXPIRE_DAYS: u32 = 365;
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.remaining_mb > Self::MAX_REMAINING_MB {
+            return Err(format!("remainingMb must be at most {}", Self::MAX_REMAINING_MB));
+        }
+        if self.expire_days > Self::MAX_EXPIRE_DAYS {
+            return Err(format!("expireDays must be at most {}", Self::MAX_EXPIRE_DAYS));
+        }
+        Ok(())
+    }
+}
+
+impl Default for QuotaAlert {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            remaining_mb: 1024,
+            expire_days: 3,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -78,12 +139,25 @@ pub struct AppSettings {
     pub socks_port: u16,
     #[serde(rename = "allowLan")]
     pub allow_lan: bool,
+    /// 入站监听的网卡名称或 IP 地址，设置后优先于 allowLan，如只在 vEthernet (WSL) 上开放代理
+    #[serde(rename = "listenInterface", default)]
+    pub listen_interface: Option<String>,
     #[serde(rename = "systemProxy")]
     pub system_proxy: bool,
     #[serde(rename = "tunEnabled")]
     pub tun_enabled: bool,
     #[serde(rename = "tunStack")]
     pub tun_stack: String,
+    #[serde(rename = "tunMtu", default = "default_tun_mtu")]
+    pub tun_mtu: u32,
+    #[serde(rename = "tunStrictRoute", default = "default_true")]
+    pub tun_strict_route: bool,
+    /// 只让这些网段走 TUN，为空表示全部
+    #[serde(rename = "tunRouteAddress", default)]
+    pub tun_route_address: Vec<String>,
+    /// 不走 TUN 的网段，如公司 VPN 子网
+    #[serde(rename = "tunRouteExcludeAddress", default)]
+    pub tun_route_exclude_address: Vec<String>,
     #[serde(rename = "localDns")]
     pub local_dns: String,
     #[serde(rename = "remoteDns")]
@@ -102,17 +176,122 @@ pub struct AppSettings {
     pub latency_test_url: String,
     #[serde(rename = "latencyTestTimeout")]
     pub latency_test_timeout: u32,
+    /// 批量测延迟时同时进行的测试数
+    #[serde(rename = "latencyTestConcurrency", default = "default_latency_test_concurrency")]
+    pub latency_test_concurrency: u32,
+    /// 每个节点测试的次数，多于一次时才能得出抖动和丢包率
+    #[serde(rename = "latencyTestProbes", default = "default_latency_test_probes")]
+    pub latency_test_probes: u32,
+    /// 当前节点连续多次测速失败时自动切换到延迟最低的可用节点
+    #[serde(rename = "failoverEnabled", default)]
+    pub failover_enabled: bool,
+    /// 连续失败多少次后切换
+    #[serde(rename = "failoverThreshold", default = "default_failover_threshold")]
+    pub failover_threshold: u32,
     #[serde(rename = "autoConnect")]
     pub auto_connect: bool,
+    /// 上次退出或崩溃时仍在连接的处理：auto 自动恢复 / ask 通知前端询问 / off 不处理
+    #[serde(rename = "resumeSession", default = "default_resume_session")]
+    pub resume_session: String,
     #[serde(rename = "minimizeToTray")]
     pub minimize_to_tray: bool,
+    /// 托盘图标样式：color 彩色 / mono 单色（适合浅色任务栏）
+    #[serde(rename = "trayIconStyle", default = "default_tray_icon_style")]
+    pub tray_icon_style: String,
+    /// 窗口背景材质：none / mica / acrylic，Mica 需要 Windows 11
+    #[serde(rename = "windowEffect", default = "default_window_effect")]
+    pub window_effect: String,
+    /// 连接期间阻止系统自动睡眠，避免长时间下载被中断
+    #[serde(rename = "preventSleep", default)]
+    pub prevent_sleep: bool,
     #[serde(rename = "startWithWindows")]
     pub start_with_windows: bool,
     #[serde(rename = "startMinimized")]
     pub start_minimized: bool,
     #[serde(rename = "exitOnClose")]
     pub exit_on_close: bool,
+    /// 连接中退出时先让用户确认
+    #[serde(rename = "confirmQuit", default)]
+    pub confirm_quit: bool,
+    /// 老板键隐藏窗口期间让前端不弹出通知
+    #[serde(rename = "bossKeyMute", default = "default_true")]
+    pub boss_key_mute: bool,
     pub theme: String,
+    /// 托盘、通知和命令错误信息使用的语言，见 i18n::LANGUAGES
+    #[serde(default = "default_language")]
+    pub language: String,
+    #[serde(rename = "kernelPath", default)]
+    pub kernel_path: Option<String>,
+    #[serde(rename = "githubToken", default)]
+    pub github_token: Option<String>,
+    #[serde(rename = "kernelSource", default)]
+    pub kernel_source: KernelSource,
+    /// 远程规则集交给内核自行下载和更新，而不是引用本地缓存
+    #[serde(rename = "remoteRuleSets", default)]
+    pub remote_rule_sets: bool,
+    /// 内核、规则集下载共用的 GitHub 镜像前缀
+    #[serde(rename = "githubMirrors", default = "default_github_mirrors")]
+    pub github_mirrors: Vec<String>,
+    /// 系统代理例外列表（写入 ProxyOverride），分号分隔，<local> 表示不含点的主机名
+    #[serde(rename = "proxyBypass", default = "default_proxy_bypass")]
+    pub proxy_bypass: String,
+    /// 系统代理被其他程序改写时的处理：reassert 自动改回 / notify 仅通知 / off 不检测
+    #[serde(rename = "proxyGuardMode", default = "default_proxy_guard_mode")]
+    pub proxy_guard_mode: String,
+    /// 用 DPAPI 加密 profiles.json 和 configs 下的节点文件
+    #[serde(rename = "encryptData", default)]
+    pub encrypt_data: bool,
+    /// 连接时同时设置 WinHTTP（机器级）代理，需要管理员权限
+    #[serde(rename = "winHttpProxy", default)]
+    pub winhttp_proxy: bool,
+    #[serde(rename = "networkProfiles", default)]
+    pub network_profiles: Vec<NetworkProfile>,
+    #[serde(default)]
+    pub hotkeys: HotkeySettings,
+}
+
+fn default_proxy_guard_mode() -> String {
+    "reassert".to_string()
+}
+
+fn default_tray_icon_style() -> String {
+    "color".to_string()
+}
+
+fn default_latency_test_concurrency() -> u32 {
+    5
+}
+
+fn default_latency_test_probes() -> u32 {
+    1
+}
+
+fn default_failover_threshold() -> u32 {
+    3
+}
+
+fn default_window_effect() -> String {
+    "none".to_string()
+}
+
+fn default_resume_session() -> String {
+    "ask".to_string()
+}
+
+fn default_language() -> String {
+    "zh-CN".to_string()
+}
+
+fn default_tun_mtu() -> u32 {
+    1500
+}
+
+fn default_proxy_bypass() -> String {
+    "localhost;127.*;10.*;192.168.*;<local>".to_string()
+}
+
+fn default_github_mirrors() -> Vec<String> {
+    crate::github::DEFAULT_MIRRORS.iter().map(|m| m.to_string()).collect()
 }
 
 impl Default for AppSettings {
@@ -121,9 +300,14 @@ impl Default for AppSettings {
             local_port: 7890,
             socks_port: 7891,
             allow_lan: false,
+            listen_interface: None,
             system_proxy: true,
             tun_enabled: false,
             tun_stack: "mixed".to_string(),
+            tun_mtu: default_tun_mtu(),
+            tun_strict_route: true,
+            tun_route_address: Vec::new(),
+            tun_route_exclude_address: Vec::new(),
             local_dns: "223.5.5.5".to_string(),
             remote_dns: "https://dns.google/dns-query".to_string(),
             fake_dns: false,
@@ -133,17 +317,263 @@ impl Default for AppSettings {
             default_rule: "proxy".to_string(),
             latency_test_url: "https://www.gstatic.com/generate_204".to_string(),
             latency_test_timeout: 5000,
+            latency_test_concurrency: default_latency_test_concurrency(),
+            latency_test_probes: default_latency_test_probes(),
+            failover_enabled: false,
+            failover_threshold: default_failover_threshold(),
             auto_connect: false,
+            resume_session: default_resume_session(),
             minimize_to_tray: true,
+            tray_icon_style: default_tray_icon_style(),
+            window_effect: default_window_effect(),
+            prevent_sleep: false,
             start_with_windows: false,
             start_minimized: false,
             exit_on_close: false,
+            confirm_quit: false,
+            boss_key_mute: true,
             theme: "dark".to_string(),
+            language: default_language(),
+            kernel_path: None,
+            github_token: None,
+            kernel_source: KernelSource::default(),
+            remote_rule_sets: false,
+            github_mirrors: default_github_mirrors(),
+            proxy_bypass: default_proxy_bypass(),
+            proxy_guard_mode: default_proxy_guard_mode(),
+            encrypt_data: false,
+            winhttp_proxy: false,
+            network_profiles: Vec::new(),
+            hotkeys: HotkeySettings::default(),
         }
     }
 }
 
+/// 全局快捷键，值为加速键字符串（如 "Ctrl+Alt+K"），None 表示不启用
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct HotkeySettings {
+    #[serde(rename = "toggleConnect", default)]
+    pub toggle_connect: Option<String>,
+    #[serde(rename = "toggleSystemProxy", default)]
+    pub toggle_system_proxy: Option<String>,
+    #[serde(rename = "toggleWindow", default)]
+    pub toggle_window: Option<String>,
+    #[serde(rename = "nextNode", default)]
+    pub next_node: Option<String>,
+    /// 老板键：立即隐藏所有窗口，再按一次恢复
+    #[serde(rename = "bossKey", default)]
+    pub boss_key: Option<String>,
+    /// 按最近的测速结果切换到最快节点
+    #[serde(rename = "bestNode", default)]
+    pub best_node: Option<String>,
+}
+
+impl HotkeySettings {
+    /// (动作名, 快捷键)，动作名与字段的 JSON 名称一致
+    pub fn entries(&self) -> [(&'static str, Option<&str>); 6] {
+        [
+            ("toggleConnect", self.toggle_connect.as_deref()),
+            ("toggleSystemProxy", self.toggle_system_proxy.as_deref()),
+            ("toggleWindow", self.toggle_window.as_deref()),
+            ("nextNode", self.next_node.as_deref()),
+            ("bossKey", self.boss_key.as_deref()),
+            ("bestNode", self.best_node.as_deref()),
+        ]
+    }
+}
+
+/// 上次会话的连接状态，连接成功时写入，手动断开时清除，用于重启或崩溃后恢复
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SessionState {
+    pub connected: bool,
+    #[serde(rename = "profileId", default)]
+    pub profile_id: Option<String>,
+    #[serde(rename = "nodeTag", default)]
+    pub node_tag: Option<String>,
+    #[serde(rename = "routingMode", default)]
+    pub routing_mode: String,
+    #[serde(rename = "updatedAt", default)]
+    pub updated_at: u64,
+}
+
+/// 按所在网络调整行为：匹配 Wi-Fi 名称或网卡（GUID / 名称），命中后覆盖对应设置，None 表示沿用全局设置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NetworkProfile {
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub ssid: Option<String>,
+    #[serde(default)]
+    pub adapter: Option<String>,
+    #[serde(rename = "autoConnect", default)]
+    pub auto_connect: Option<bool>,
+    #[serde(rename = "systemProxy", default)]
+    pub system_proxy: Option<bool>,
+    #[serde(rename = "profileId", default)]
+    pub profile_id: Option<String>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// 内核发布源：任意兼容 GitHub Releases API 的仓库（官方、fork 或企业内网镜像）
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KernelSource {
+    #[serde(rename = "apiBase")]
+    pub api_base: String,
+    pub repo: String,
+    /// 资源文件名模板，支持 {version} 与 {tag} 占位符
+    #[serde(rename = "assetPattern")]
+    pub asset_pattern: String,
+}
+
+impl Default for KernelSource {
+    fn default() -> Self {
+        Self {
+            api_base: "https://api.github.com".to_string(),
+            repo: "SagerNet/sing-box".to_string(),
+            asset_pattern: "sing-box-{version}-windows-amd64.zip".to_string(),
+        }
+    }
+}
+
+impl KernelSource {
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.api_base.starts_with("https://") && !self.api_base.starts_with("http://") {
+            return Err(format!("Invalid kernel source API address: {}", self.api_base));
+        }
+        let mut parts = self.repo.split('/');
+        let valid_repo = matches!((parts.next(), parts.next(), parts.next()), (Some(owner), Some(name), None) if !owner.is_empty() && !name.is_empty());
+        if !valid_repo {
+            return Err(format!("Kernel source repo must be owner/name: {}", self.repo));
+        }
+        if !self.asset_pattern.contains("{version}") && !self.asset_pattern.contains("{tag}") {
+            return Err("Kernel asset pattern must contain {version} or {tag}".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn releases_api(&self) -> String {
+        format!("{}/repos/{}/releases", self.api_base.trim_end_matches('/'), self.repo)
+    }
+
+    pub fn releases_page(&self) -> String {
+        if self.api_base.trim_end_matches('/') == "https://api.github.com" {
+            format!("https://github.com/{}/releases", self.repo)
+        } else {
+            self.api_base.clone()
+        }
+    }
+
+    pub fn asset_name(&self, tag_name: &str) -> String {
+        self.asset_pattern
+            .replace("{version}", tag_name.trim_start_matches('v'))
+            .replace("{tag}", tag_name)
+    }
+}
+
+/// 一个节点多次延迟测试的汇总，单位毫秒；loss 为失败次数占比（0 ~ 1）
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LatencyStats {
+    pub min: i64,
+    pub avg: i64,
+    pub max: i64,
+    /// 相邻两次成功结果之差的平均值
+    pub jitter: i64,
+    pub loss: f64,
+    pub samples: u32,
+}
+
+impl LatencyStats {
+    /// samples 中 -1 表示该次失败；全部失败时各项延迟均为 -1
+    pub fn from_samples(samples: &[i64]) -> Self {
+        let ok: Vec<i64> = samples.iter().copied().filter(|ms| *ms >= 0).collect();
+        let loss = if samples.is_empty() { 1.0 } else { 1.0 - ok.len() as f64 / samples.len() as f64 };
+        if ok.is_empty() {
+            return Self { min: -1, avg: -1, max: -1, jitter: -1, loss, samples: samples.len() as u32 };
+        }
+        let jitter = if ok.len() > 1 {
+            ok.windows(2).map(|w| (w[1] - w[0]).abs()).sum::<i64>() / (ok.len() - 1) as i64
+        } else {
+            0
+        };
+        Self {
+            min: *ok.iter().min().unwrap_or(&-1),
+            avg: ok.iter().sum::<i64>() / ok.len() as i64,
+            max: *ok.iter().max().unwrap_or(&-1),
+            jitter,
+            loss,
+            samples: samples.len() as u32,
+        }
+    }
+
+    /// 用于排序和显示的单个延迟值
+    pub fn latency(&self) -> i64 {
+        self.avg
+    }
+}
+
+/// 根据一个节点的延迟历史得出的稳定性评价
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeScore {
+    /// 0 ~ 100，成功率越高、波动越小越高
+    pub score: u32,
+    /// fast（< 200ms）/ moderate（< 500ms）/ slow / unreachable，与任务栏角标的分档一致
+    pub tier: String,
+    pub success_rate: f64,
+    /// 成功结果的平均延迟和标准差（毫秒），全部失败时为 -1
+    pub avg: i64,
+    pub stddev: i64,
+    pub samples: u32,
+}
+
+impl NodeScore {
+    /// history 中 -1 表示该次失败；没有任何记录时返回 None
+    pub fn from_history(history: &[i64]) -> Option<Self> {
+        if history.is_empty() {
+            return None;
+        }
+        let ok: Vec<f64> = history.iter().filter(|ms| **ms >= 0).map(|ms| *ms as f64).collect();
+        let success_rate = ok.len() as f64 / history.len() as f64;
+        if ok.is_empty() {
+            return Some(Self { score: 0, tier: "unreachable".to_string(), success_rate, avg: -1, stddev: -1, samples: history.len() as u32 });
+        }
+        let avg = ok.iter().sum::<f64>() / ok.len() as f64;
+        let stddev = (ok.iter().map(|ms| (ms - avg).powi(2)).sum::<f64>() / ok.len() as f64).sqrt();
+        // 波动和平均延迟相当时稳定性只算一半
+        let stability = if avg > 0.0 { avg / (avg + stddev) } else { 1.0 };
+        let tier = match avg as i64 {
+            0..=199 => "fast",
+            200..=499 => "moderate",
+            _ => "slow",
+        };
+        Some(Self {
+            score: (100.0 * success_rate * stability).round() as u32,
+            tier: tier.to_string(),
+            success_rate,
+            avg: avg.round() as i64,
+            stddev: stddev.round() as i64,
+            samples: history.len() as u32,
+        })
+    }
+
+    /// 排序用的等效延迟：平均延迟加上两倍波动，再按成功率放大；越小越好
+    pub fn effective_latency(&self) -> i64 {
+        if self.avg < 0 || self.success_rate <= 0.0 {
+            return i64::MAX;
+        }
+        ((self.avg + 2 * self.stddev) as f64 / self.success_rate).round() as i64
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct SingBoxOutbound {
     pub tag: Option<String>,
     #[serde(rename = "type")]
@@ -170,6 +600,122 @@ pub struct RuleSet {
     pub enabled: bool,
     #[serde(rename = "isBuiltIn")]
     pub is_built_in: bool,
+    /// 自动更新间隔（分钟），0 表示不自动更新
+    #[serde(rename = "updateInterval", default = "default_ruleset_update_interval")]
+    pub update_interval: u32,
+    #[serde(rename = "lastUpdated", default)]
+    pub last_updated: Option<u64>,
+    #[serde(default)]
+    pub etag: Option<String>,
+    /// 返回 etag 的下载地址；各镜像的 ETag 互不相同，只与同一地址比较
+    #[serde(rename = "etagUrl", default, skip_serializing_if = "Option::is_none")]
+    pub etag_url: Option<String>,
+    #[serde(rename = "lastError", default)]
+    pub last_error: Option<String>,
+    /// 缓存文件大小，ruleset_list 时按磁盘实际情况刷新，未缓存为 None
+    #[serde(rename = "fileSize", default)]
+    pub file_size: Option<u64>,
+    /// 下载内容需要先转换的原始格式，目前只有 "blocklist"（hosts / AdGuard 列表）
+    #[serde(rename = "sourceFormat", default, skip_serializing_if = "Option::is_none")]
+    pub source_format: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schedule: Option<TimeWindow>,
+    /// 规则集内域名使用的 DNS 服务器：dns-local / dns-remote / dns-fakeip，为空时走默认的 dns-remote
+    #[serde(rename = "dnsServer", default, skip_serializing_if = "Option::is_none")]
+    pub dns_server: Option<String>,
+    /// 取反：不在规则集内的流量走该出站
+    #[serde(default)]
+    pub invert: bool,
+    /// 例外域名（后缀匹配），命中时跳过该规则继续匹配后续规则
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exceptions: Vec<String>,
+}
+
+/// 规则生效的时间段，如工作日 09:00-18:00；结束早于开始表示跨越午夜
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimeWindow {
+    pub start: String,
+    pub end: String,
+    /// 1 = 周一 ... 7 = 周日，为空表示每天
+    #[serde(default)]
+    pub days: Vec<u8>,
+}
+
+impl TimeWindow {
+    fn parse_minutes(time: &str) -> Option<u32> {
+        let (h, m) = time.trim().split_once(':')?;
+        let (h, m): (u32, u32) = (h.parse().ok()?, m.parse().ok()?);
+        (h < 24 && m < 60).then_some(h * 60 + m)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if Self::parse_minutes(&self.start).is_none() || Self::parse_minutes(&self.end).is_none() {
+            return Err(format!("Invalid time window: {}-{}", self.start, self.end));
+        }
+        if self.days.iter().any(|d| !(1..=7).contains(d)) {
+            return Err("Time window days must be between 1 and 7".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn is_active_at(&self, now: chrono::DateTime<chrono::Local>) -> bool {
+        use chrono::{Datelike, Timelike};
+        let (start, end) = match (Self::parse_minutes(&self.start), Self::parse_minutes(&self.end)) {
+            (Some(start), Some(end)) => (start, end),
+            _ => return true,
+        };
+        let minutes = now.hour() * 60 + now.minute();
+        let weekday = now.weekday().number_from_monday() as u8;
+        let in_window = if start <= end { minutes >= start && minutes < end } else { minutes >= start || minutes < end };
+        // 跨午夜的后半段按前一天的星期判断
+        let day = if start > end && minutes < end { (weekday + 5) % 7 + 1 } else { weekday };
+        in_window && (self.days.is_empty() || self.days.contains(&day))
+    }
+}
+
+fn default_ruleset_update_interval() -> u32 {
+    1440
+}
+
+/// 用户自定义的单条路由规则，生成配置时插入在规则集规则之前
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CustomRule {
+    pub id: String,
+    /// domain / domain_suffix / domain_keyword / domain_regex / domain_wildcard / ip_cidr / port / process_name / process_path
+    #[serde(rename = "type")]
+    pub rule_type: String,
+    pub values: Vec<String>,
+    #[serde(rename = "outboundMode")]
+    pub outbound_mode: String,
+    #[serde(rename = "outboundValue")]
+    pub outbound_value: Option<String>,
+    pub enabled: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schedule: Option<TimeWindow>,
+    #[serde(default)]
+    pub invert: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exceptions: Vec<String>,
+}
+
+/// set_settings 中单个设置项的校验错误，field 为设置项的 camelCase 名称
+#[derive(Debug, Clone, Serialize)]
+pub struct SettingsFieldError {
+    pub field: String,
+    pub message: String,
+}
+
+/// set_settings 的错误：message 供直接展示，fields 供界面定位到具体设置项
+#[derive(Debug, Clone, Serialize)]
+pub struct SettingsError {
+    pub message: String,
+    pub fields: Vec<SettingsFieldError>,
+}
+
+impl From<String> for SettingsError {
+    fn from(message: String) -> Self {
+        Self { message, fields: Vec::new() }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]